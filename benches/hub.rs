@@ -0,0 +1,254 @@
+//! Hub throughput under contention: the global-`Mutex` hub this server started with
+//! against the sharded `Hub`, for concurrent register + join and concurrent broadcast.
+//!
+//!     cargo bench --bench hub
+//!
+//! The crate is a binary, so the hub and the modules it depends on are compiled in
+//! from `src/` directly.  They are linted with the server itself, not again here.
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+#[allow(unused, clippy::all)]
+#[path = "../src/error.rs"]
+mod error;
+#[allow(unused, clippy::all)]
+#[path = "../src/models/mod.rs"]
+mod models;
+#[allow(unused, clippy::all)]
+#[path = "../src/websocket/mod.rs"]
+mod websocket;
+
+use websocket::codec::{Frame, WireFormat};
+use websocket::hub::Hub;
+use websocket::protocol::{ServerMessage, WsUser, PROTOCOL_VERSION};
+
+const THREADS: usize = 8;
+const USERS: usize = 1_024;
+const ROOMS: usize = 16;
+/// Messages each thread broadcasts to each room per iteration.  THREADS × this stays
+/// under the 64-slot connection channel, so nothing is dropped as "channel full".
+const MESSAGES_PER_ROOM: usize = 4;
+
+/// The hub as it was before sharding: one lock around every map, one connection per
+/// user, and each connection serializes every message it receives.
+mod mutex_hub {
+    use super::*;
+
+    #[derive(Clone)]
+    pub struct MutexHub {
+        inner: Arc<Mutex<Inner>>,
+    }
+
+    struct Inner {
+        connections: HashMap<Uuid, mpsc::Sender<ServerMessage>>,
+        rooms:       HashMap<Uuid, HashSet<Uuid>>,
+    }
+
+    impl MutexHub {
+        pub fn new() -> Self {
+            MutexHub { inner: Arc::new(Mutex::new(Inner { connections: HashMap::new(), rooms: HashMap::new() })) }
+        }
+
+        pub fn register(&self, user_id: Uuid) -> mpsc::Receiver<ServerMessage> {
+            let (tx, rx) = mpsc::channel(64);
+            self.inner.lock().unwrap().connections.insert(user_id, tx);
+            rx
+        }
+
+        pub fn join_room(&self, room_id: Uuid, user_id: Uuid, username: &str) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.rooms.entry(room_id).or_default().insert(user_id);
+            let joined = ServerMessage::UserJoined {
+                room_id,
+                user: WsUser { id: user_id, username: username.to_string(), display_name: None },
+            };
+            Self::broadcast_inner(&inner, room_id, &joined, Some(user_id));
+            let online = inner.rooms.get(&room_id)
+                .map(|s| s.iter().map(|id| id.to_string()).collect())
+                .unwrap_or_default();
+            if let Some(tx) = inner.connections.get(&user_id) {
+                let _ = tx.try_send(ServerMessage::OnlineUsers { room_id, user_ids: online });
+            }
+        }
+
+        pub fn broadcast_to_room(&self, room_id: Uuid, msg: &ServerMessage) {
+            let inner = self.inner.lock().unwrap();
+            Self::broadcast_inner(&inner, room_id, msg, None);
+        }
+
+        fn broadcast_inner(inner: &Inner, room_id: Uuid, msg: &ServerMessage, skip: Option<Uuid>) {
+            let Some(members) = inner.rooms.get(&room_id) else { return };
+            for &uid in members {
+                if skip == Some(uid) { continue; }
+                if let Some(tx) = inner.connections.get(&uid) {
+                    let _ = tx.try_send(msg.clone());
+                }
+            }
+        }
+    }
+}
+
+use mutex_hub::MutexHub;
+
+struct Fixture {
+    users:     Vec<Uuid>,
+    rooms:     Vec<Uuid>,
+    workspace: Uuid,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Fixture {
+            users:     (0..USERS).map(|_| Uuid::new_v4()).collect(),
+            rooms:     (0..ROOMS).map(|_| Uuid::new_v4()).collect(),
+            workspace: Uuid::new_v4(),
+        }
+    }
+
+    fn room_of(&self, i: usize) -> Uuid {
+        self.rooms[i % ROOMS]
+    }
+}
+
+fn message(room_id: Uuid) -> ServerMessage {
+    ServerMessage::Message {
+        message_id: Uuid::new_v4(),
+        room_id,
+        user:       WsUser { id: Uuid::new_v4(), username: "bench".into(), display_name: None },
+        content:    "x".repeat(200),
+        timestamp:  Utc::now(),
+    }
+}
+
+/// Run `work(thread_index)` on `THREADS` threads at once.
+fn in_parallel(work: impl Fn(usize) + Sync) {
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            let work = &work;
+            s.spawn(move || work(t));
+        }
+    });
+}
+
+/// Users `t`, `t + THREADS`, ... belong to thread `t`.
+fn chunk(t: usize) -> impl Iterator<Item = usize> {
+    (t..USERS).step_by(THREADS)
+}
+
+fn mutex_populated(fx: &Fixture) -> (MutexHub, Vec<mpsc::Receiver<ServerMessage>>) {
+    let hub = MutexHub::new();
+    let rxs = fx.users.iter().map(|&u| hub.register(u)).collect();
+    for (i, &u) in fx.users.iter().enumerate() {
+        hub.join_room(fx.room_of(i), u, "bench");
+    }
+    (hub, rxs)
+}
+
+fn sharded_populated(fx: &Fixture) -> (Hub, Vec<mpsc::Receiver<Frame>>) {
+    let hub = Hub::new();
+    let rxs = fx.users.iter()
+        .map(|&u| hub.register(u, "bench".into(), Some(fx.workspace), WireFormat::Json, PROTOCOL_VERSION).1)
+        .collect();
+    for (i, &u) in fx.users.iter().enumerate() {
+        hub.join_room(fx.room_of(i), fx.workspace, u, "bench", None);
+    }
+    (hub, rxs)
+}
+
+/// Empty every channel, serializing as the old per-connection writer did.
+fn drain_mutex(rxs: &mut [mpsc::Receiver<ServerMessage>]) {
+    for rx in rxs {
+        while let Ok(msg) = rx.try_recv() {
+            criterion::black_box(serde_json::to_string(&msg).unwrap());
+        }
+    }
+}
+
+/// Empty every channel; frames arrive already encoded.
+fn drain_sharded(rxs: &mut [mpsc::Receiver<Frame>]) {
+    for rx in rxs {
+        while let Ok(frame) = rx.try_recv() {
+            criterion::black_box(frame);
+        }
+    }
+}
+
+fn register_and_join(c: &mut Criterion) {
+    let fx = Fixture::new();
+    let mut group = c.benchmark_group("register_join");
+    group.throughput(Throughput::Elements(USERS as u64));
+
+    group.bench_function("mutex", |b| {
+        b.iter_batched(MutexHub::new, |hub| {
+            in_parallel(|t| {
+                // Receivers are kept until the end so joins still have live channels.
+                let _rxs: Vec<_> = chunk(t).map(|i| {
+                    let rx = hub.register(fx.users[i]);
+                    hub.join_room(fx.room_of(i), fx.users[i], "bench");
+                    rx
+                }).collect();
+            });
+        }, BatchSize::SmallInput)
+    });
+
+    group.bench_function("sharded", |b| {
+        b.iter_batched(Hub::new, |hub| {
+            in_parallel(|t| {
+                let _rxs: Vec<_> = chunk(t).map(|i| {
+                    let (_, rx) = hub.register(fx.users[i], "bench".into(), Some(fx.workspace), WireFormat::Json, PROTOCOL_VERSION);
+                    hub.join_room(fx.room_of(i), fx.workspace, fx.users[i], "bench", None);
+                    rx
+                }).collect();
+            });
+        }, BatchSize::SmallInput)
+    });
+    group.finish();
+}
+
+fn broadcast(c: &mut Criterion) {
+    let fx = Fixture::new();
+    let messages: Vec<ServerMessage> = fx.rooms.iter().map(|&r| message(r)).collect();
+    let mut group = c.benchmark_group("broadcast");
+    // Deliveries per iteration: every member receives every message of its room.
+    group.throughput(Throughput::Elements((THREADS * MESSAGES_PER_ROOM * USERS) as u64));
+
+    let (hub, mut rxs) = mutex_populated(&fx);
+    drain_mutex(&mut rxs);
+    group.bench_function("mutex", |b| {
+        b.iter(|| {
+            in_parallel(|_| {
+                for _ in 0..MESSAGES_PER_ROOM {
+                    for (room_id, msg) in fx.rooms.iter().zip(&messages) {
+                        hub.broadcast_to_room(*room_id, msg);
+                    }
+                }
+            });
+            drain_mutex(&mut rxs);
+        })
+    });
+
+    let (hub, mut rxs) = sharded_populated(&fx);
+    drain_sharded(&mut rxs);
+    group.bench_function("sharded", |b| {
+        b.iter(|| {
+            in_parallel(|_| {
+                for _ in 0..MESSAGES_PER_ROOM {
+                    for (room_id, msg) in fx.rooms.iter().zip(&messages) {
+                        hub.broadcast_to_room(*room_id, msg, None);
+                    }
+                }
+            });
+            drain_sharded(&mut rxs);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, register_and_join, broadcast);
+criterion_main!(benches);
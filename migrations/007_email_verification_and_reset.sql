@@ -0,0 +1,30 @@
+-- +migrate Up
+ALTER TABLE users
+    ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+-- Single-use tokens for email verification and password reset.  Only a hash is stored.
+CREATE TABLE user_tokens (
+    id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id     UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose     VARCHAR(32) NOT NULL,
+    token_hash  VARCHAR(64) UNIQUE NOT NULL,
+    expires_at  TIMESTAMPTZ NOT NULL,
+    used_at     TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_user_tokens_user_id ON user_tokens(user_id, purpose);
+
+-- Written by the `outbox` mail transport instead of sending anything.
+CREATE TABLE mail_outbox (
+    id          UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    recipient   VARCHAR(255) NOT NULL,
+    subject     TEXT        NOT NULL,
+    body        TEXT        NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- +migrate Down
+DROP TABLE IF EXISTS mail_outbox;
+DROP TABLE IF EXISTS user_tokens;
+ALTER TABLE users
+    DROP COLUMN IF EXISTS email_verified_at;
@@ -0,0 +1,21 @@
+-- +migrate Up
+-- Accounts created through single sign-on have no local password.
+ALTER TABLE users
+    ALTER COLUMN password_hash DROP NOT NULL;
+
+CREATE TABLE user_identities (
+    id             UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id        UUID         NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider       VARCHAR(64)  NOT NULL,
+    subject        VARCHAR(255) NOT NULL,
+    email          VARCHAR(255),
+    created_at     TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    last_login_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, subject)
+);
+CREATE INDEX idx_user_identities_user_id ON user_identities(user_id);
+
+-- +migrate Down
+DROP TABLE IF EXISTS user_identities;
+ALTER TABLE users
+    ALTER COLUMN password_hash SET NOT NULL;
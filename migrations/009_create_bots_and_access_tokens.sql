@@ -0,0 +1,26 @@
+-- +migrate Up
+ALTER TABLE users
+    ADD COLUMN is_bot       BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN bot_owner_id UUID    REFERENCES users(id) ON DELETE CASCADE;
+CREATE INDEX idx_users_bot_owner_id ON users(bot_owner_id);
+
+CREATE TABLE personal_access_tokens (
+    id            UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id       UUID         NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name          VARCHAR(100) NOT NULL,
+    -- First characters of the token, shown in listings so users can tell tokens apart.
+    token_prefix  VARCHAR(16)  NOT NULL,
+    token_hash    VARCHAR(64)  UNIQUE NOT NULL,
+    scopes        TEXT[]       NOT NULL,
+    expires_at    TIMESTAMPTZ,
+    last_used_at  TIMESTAMPTZ,
+    revoked_at    TIMESTAMPTZ,
+    created_at    TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_personal_access_tokens_user_id ON personal_access_tokens(user_id);
+
+-- +migrate Down
+DROP TABLE IF EXISTS personal_access_tokens;
+ALTER TABLE users
+    DROP COLUMN IF EXISTS bot_owner_id,
+    DROP COLUMN IF EXISTS is_bot;
@@ -0,0 +1,21 @@
+-- +migrate Up
+ALTER TABLE users
+    ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE audit_events (
+    id              UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    action          VARCHAR(64) NOT NULL,
+    -- Who did it; NULL for events raised by the system itself.
+    actor_id        UUID        REFERENCES users(id) ON DELETE SET NULL,
+    target_user_id  UUID        REFERENCES users(id) ON DELETE SET NULL,
+    ip              VARCHAR(64),
+    details         JSONB       NOT NULL DEFAULT '{}',
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_audit_events_created_at ON audit_events(created_at DESC);
+CREATE INDEX idx_audit_events_target_user_id ON audit_events(target_user_id);
+
+-- +migrate Down
+DROP TABLE IF EXISTS audit_events;
+ALTER TABLE users
+    DROP COLUMN IF EXISTS is_admin;
@@ -0,0 +1,20 @@
+-- +migrate Up
+CREATE TABLE invite_codes (
+    id          UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- First characters of the code, shown in listings; the code itself is only stored hashed.
+    code_prefix VARCHAR(16)  NOT NULL,
+    code_hash   VARCHAR(64)  UNIQUE NOT NULL,
+    created_by  UUID         NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- NULL means unlimited.
+    max_uses    INTEGER,
+    uses        INTEGER      NOT NULL DEFAULT 0,
+    -- Rooms the new account joins on sign-up.
+    room_ids    UUID[]       NOT NULL DEFAULT '{}',
+    expires_at  TIMESTAMPTZ,
+    revoked_at  TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_invite_codes_created_by ON invite_codes(created_by);
+
+-- +migrate Down
+DROP TABLE IF EXISTS invite_codes;
@@ -0,0 +1,34 @@
+-- +migrate Up
+ALTER TABLE rooms
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE messages
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Creators were recorded as 'admin'; they become the room's owner.
+UPDATE room_members rm SET role = 'owner'
+FROM rooms r
+WHERE rm.room_id = r.id AND rm.user_id = r.created_by;
+UPDATE room_members SET role = 'member'
+WHERE role NOT IN ('owner', 'admin', 'moderator', 'member', 'guest');
+ALTER TABLE room_members
+    ADD CONSTRAINT room_members_role_check
+    CHECK (role IN ('owner', 'admin', 'moderator', 'member', 'guest'));
+
+-- Per-room replacement for a role's default permission bits (see `Permissions`).
+CREATE TABLE room_role_permissions (
+    room_id     UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    role        VARCHAR(20) NOT NULL CHECK (role IN ('admin', 'moderator', 'member', 'guest')),
+    permissions BIGINT      NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, role)
+);
+
+-- +migrate Down
+DROP TABLE IF EXISTS room_role_permissions;
+ALTER TABLE room_members
+    DROP CONSTRAINT IF EXISTS room_members_role_check;
+UPDATE room_members SET role = 'admin' WHERE role = 'owner';
+ALTER TABLE messages
+    DROP COLUMN IF EXISTS updated_at;
+ALTER TABLE rooms
+    DROP COLUMN IF EXISTS updated_at;
@@ -0,0 +1,26 @@
+-- +migrate Up
+CREATE TABLE room_bans (
+    room_id     UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    user_id     UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    banned_by   UUID        REFERENCES users(id) ON DELETE SET NULL,
+    reason      TEXT,
+    -- NULL means permanent.
+    expires_at  TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, user_id)
+);
+
+-- Muted members can read the room but not post to it.
+CREATE TABLE room_mutes (
+    room_id     UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    user_id     UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    muted_by    UUID        REFERENCES users(id) ON DELETE SET NULL,
+    reason      TEXT,
+    expires_at  TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, user_id)
+);
+
+-- +migrate Down
+DROP TABLE IF EXISTS room_mutes;
+DROP TABLE IF EXISTS room_bans;
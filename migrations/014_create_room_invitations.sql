@@ -0,0 +1,50 @@
+-- +migrate Up
+-- Direct invitations to a room, answered by the invitee.
+CREATE TABLE room_invitations (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    room_id       UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    invitee_id    UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    inviter_id    UUID        REFERENCES users(id) ON DELETE SET NULL,
+    status        VARCHAR(16) NOT NULL DEFAULT 'pending'
+                  CHECK (status IN ('pending', 'accepted', 'declined', 'revoked')),
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    responded_at  TIMESTAMPTZ
+);
+CREATE UNIQUE INDEX idx_room_invitations_pending
+    ON room_invitations(room_id, invitee_id) WHERE status = 'pending';
+CREATE INDEX idx_room_invitations_invitee_id ON room_invitations(invitee_id);
+
+-- Shareable links; like invite_codes, only a hash of the code is kept.
+CREATE TABLE room_invite_links (
+    id           UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    room_id      UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    created_by   UUID        REFERENCES users(id) ON DELETE SET NULL,
+    code_prefix  VARCHAR(16) NOT NULL,
+    code_hash    VARCHAR(64) UNIQUE NOT NULL,
+    max_uses     INTEGER,
+    uses         INTEGER     NOT NULL DEFAULT 0,
+    expires_at   TIMESTAMPTZ,
+    revoked_at   TIMESTAMPTZ,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_room_invite_links_room_id ON room_invite_links(room_id);
+
+-- "Let me in" requests, approved or rejected by the room's admins.
+CREATE TABLE room_join_requests (
+    id           UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    room_id      UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    user_id      UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message      TEXT,
+    status       VARCHAR(16) NOT NULL DEFAULT 'pending'
+                 CHECK (status IN ('pending', 'approved', 'rejected')),
+    reviewed_by  UUID        REFERENCES users(id) ON DELETE SET NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_at  TIMESTAMPTZ
+);
+CREATE UNIQUE INDEX idx_room_join_requests_pending
+    ON room_join_requests(room_id, user_id) WHERE status = 'pending';
+
+-- +migrate Down
+DROP TABLE IF EXISTS room_join_requests;
+DROP TABLE IF EXISTS room_invite_links;
+DROP TABLE IF EXISTS room_invitations;
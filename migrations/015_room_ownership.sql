@@ -0,0 +1,14 @@
+-- +migrate Up
+-- At most one owner per room; ownership moves by transfer.
+CREATE UNIQUE INDEX idx_room_members_one_owner ON room_members(room_id) WHERE role = 'owner';
+
+-- The creator is history, not a permission: deleting them must not block or orphan the room.
+ALTER TABLE rooms
+    DROP CONSTRAINT IF EXISTS rooms_created_by_fkey,
+    ADD CONSTRAINT rooms_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
+
+-- +migrate Down
+ALTER TABLE rooms
+    DROP CONSTRAINT IF EXISTS rooms_created_by_fkey,
+    ADD CONSTRAINT rooms_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id);
+DROP INDEX IF EXISTS idx_room_members_one_owner;
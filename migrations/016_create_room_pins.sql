@@ -0,0 +1,12 @@
+-- +migrate Up
+-- A message is pinned at most once; deleting it drops the pin.
+CREATE TABLE room_pins (
+    message_id  UUID        PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    room_id     UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    pinned_by   UUID        REFERENCES users(id) ON DELETE SET NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_room_pins_room_id ON room_pins(room_id, created_at DESC);
+
+-- +migrate Down
+DROP TABLE IF EXISTS room_pins;
@@ -0,0 +1,15 @@
+-- +migrate Up
+ALTER TABLE rooms
+    ADD COLUMN topic      VARCHAR(250),
+    ADD COLUMN avatar_url TEXT,
+    -- Rules / welcome text sent to members as they join.
+    ADD COLUMN rules      TEXT,
+    -- Free-form client settings; the server only checks it is an object.
+    ADD COLUMN settings   JSONB NOT NULL DEFAULT '{}'::jsonb;
+
+-- +migrate Down
+ALTER TABLE rooms
+    DROP COLUMN IF EXISTS settings,
+    DROP COLUMN IF EXISTS rules,
+    DROP COLUMN IF EXISTS avatar_url,
+    DROP COLUMN IF EXISTS topic;
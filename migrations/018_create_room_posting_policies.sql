@@ -0,0 +1,17 @@
+-- +migrate Up
+-- Rooms without a row use the defaults: no slow mode, anyone with SEND may post,
+-- the global length limit, links allowed.
+CREATE TABLE room_posting_policies (
+    room_id             UUID        PRIMARY KEY REFERENCES rooms(id) ON DELETE CASCADE,
+    -- Seconds a member must wait between messages; 0 disables slow mode.
+    slow_mode_secs      INTEGER     NOT NULL DEFAULT 0 CHECK (slow_mode_secs BETWEEN 0 AND 86400),
+    -- Lowest role allowed to post; NULL means anyone holding SEND.
+    min_post_role       VARCHAR(20) CHECK (min_post_role IN ('owner', 'admin', 'moderator', 'member', 'guest')),
+    max_message_length  INTEGER     CHECK (max_message_length > 0),
+    allow_links         BOOLEAN     NOT NULL DEFAULT TRUE,
+    updated_by          UUID        REFERENCES users(id) ON DELETE SET NULL,
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- +migrate Down
+DROP TABLE IF EXISTS room_posting_policies;
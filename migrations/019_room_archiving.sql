@@ -0,0 +1,14 @@
+-- +migrate Up
+ALTER TABLE rooms
+    ADD COLUMN archived_at TIMESTAMPTZ,
+    ADD COLUMN archived_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Set when the owner deletes the room; the purge job removes it after this time.
+    ADD COLUMN purge_after TIMESTAMPTZ;
+CREATE INDEX idx_rooms_purge_after ON rooms(purge_after) WHERE purge_after IS NOT NULL;
+
+-- +migrate Down
+DROP INDEX IF EXISTS idx_rooms_purge_after;
+ALTER TABLE rooms
+    DROP COLUMN IF EXISTS purge_after,
+    DROP COLUMN IF EXISTS archived_by,
+    DROP COLUMN IF EXISTS archived_at;
@@ -0,0 +1,15 @@
+-- +migrate Up
+-- Directory categories; lowercase, one row per tag.
+CREATE TABLE room_tags (
+    room_id  UUID        NOT NULL REFERENCES rooms(id) ON DELETE CASCADE,
+    tag      VARCHAR(32) NOT NULL,
+    PRIMARY KEY (room_id, tag)
+);
+CREATE INDEX idx_room_tags_tag ON room_tags(tag);
+
+-- Serves "latest activity" per room for the directory.
+CREATE INDEX idx_messages_room_created ON messages(room_id, created_at DESC);
+
+-- +migrate Down
+DROP INDEX IF EXISTS idx_messages_room_created;
+DROP TABLE IF EXISTS room_tags;
@@ -0,0 +1,45 @@
+-- +migrate Up
+-- Tenants.  Accounts stay global (one login, possibly several workspaces); rooms
+-- belong to exactly one workspace and users only see rooms and people in theirs.
+CREATE TABLE workspaces (
+    id          UUID         PRIMARY KEY DEFAULT gen_random_uuid(),
+    slug        VARCHAR(50)  UNIQUE NOT NULL,
+    name        VARCHAR(100) NOT NULL,
+    created_by  UUID         REFERENCES users(id) ON DELETE SET NULL,
+    created_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE workspace_members (
+    workspace_id  UUID        NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    user_id       UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role          VARCHAR(20) NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member')),
+    joined_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (workspace_id, user_id)
+);
+CREATE INDEX idx_workspace_members_user_id ON workspace_members(user_id);
+
+-- Existing data moves into one 'default' workspace; server admins administer it.
+-- Bots are users too, so existing bots join it here; new ones join in create_bot.
+INSERT INTO workspaces (slug, name) VALUES ('default', 'Default');
+INSERT INTO workspace_members (workspace_id, user_id, role)
+SELECT w.id, u.id, CASE WHEN u.is_admin THEN 'admin' ELSE 'member' END
+FROM workspaces w CROSS JOIN users u
+WHERE w.slug = 'default';
+
+ALTER TABLE rooms ADD COLUMN workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE;
+UPDATE rooms SET workspace_id = (SELECT id FROM workspaces WHERE slug = 'default');
+ALTER TABLE rooms ALTER COLUMN workspace_id SET NOT NULL;
+CREATE INDEX idx_rooms_workspace_id ON rooms(workspace_id);
+
+-- A token acts in the workspace it was issued for, and stops working if its owner leaves.
+ALTER TABLE personal_access_tokens ADD COLUMN workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE;
+UPDATE personal_access_tokens SET workspace_id = (SELECT id FROM workspaces WHERE slug = 'default');
+ALTER TABLE personal_access_tokens ALTER COLUMN workspace_id SET NOT NULL;
+
+-- +migrate Down
+ALTER TABLE personal_access_tokens DROP COLUMN IF EXISTS workspace_id;
+DROP INDEX IF EXISTS idx_rooms_workspace_id;
+ALTER TABLE rooms DROP COLUMN IF EXISTS workspace_id;
+DROP TABLE IF EXISTS workspace_members;
+DROP TABLE IF EXISTS workspaces;
@@ -0,0 +1,19 @@
+-- +migrate Up
+-- Joining a workspace takes the invitee's consent; admins can't add accounts directly.
+CREATE TABLE workspace_invitations (
+    id            UUID        PRIMARY KEY DEFAULT gen_random_uuid(),
+    workspace_id  UUID        NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    invitee_id    UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    inviter_id    UUID        REFERENCES users(id) ON DELETE SET NULL,
+    role          VARCHAR(20) NOT NULL DEFAULT 'member' CHECK (role IN ('admin', 'member')),
+    status        VARCHAR(16) NOT NULL DEFAULT 'pending'
+                  CHECK (status IN ('pending', 'accepted', 'declined')),
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    responded_at  TIMESTAMPTZ
+);
+CREATE UNIQUE INDEX idx_workspace_invitations_pending
+    ON workspace_invitations(workspace_id, invitee_id) WHERE status = 'pending';
+CREATE INDEX idx_workspace_invitations_invitee_id ON workspace_invitations(invitee_id);
+
+-- +migrate Down
+DROP TABLE IF EXISTS workspace_invitations;
@@ -1,27 +1,184 @@
 use std::env;
 
+/// One OpenID Connect provider, from `OIDC_<NAME>_*`.
+#[derive(Clone, Debug)]
+pub struct OidcProviderConfig {
+    pub name:          String,
+    pub issuer:        String,
+    pub client_id:     String,
+    /// Empty for public clients, which rely on PKCE alone.
+    pub client_secret: String,
+    pub scopes:        String,
+}
+
+/// Who may create an account through `/api/auth/register`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegistrationMode {
+    Open,
+    /// A valid invite code is required.
+    Invite,
+    Closed,
+}
+
+impl std::str::FromStr for RegistrationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" => Ok(RegistrationMode::Open),
+            "invite" => Ok(RegistrationMode::Invite),
+            "closed" => Ok(RegistrationMode::Closed),
+            other => Err(format!("Unknown REGISTRATION_MODE {other}; use open, invite or closed")),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Config {
-    pub database_url:            String,
-    pub redis_url:               String,
-    pub jwt_secret:              String,
-    pub jwt_expiry_secs:         i64,
-    pub jwt_refresh_expiry_secs: i64,
-    pub host:                    String,
-    pub port:                    u16,
+    pub database_url:               String,
+    pub redis_url:                  String,
+    pub jwt_secret:                 String,
+    pub jwt_expiry_secs:            i64,
+    pub jwt_refresh_expiry_secs:    i64,
+    /// HS256 | RS256 | EdDSA
+    pub jwt_algorithm:              String,
+    pub jwt_private_key_file:       String,
+    pub jwt_public_key_file:        String,
+    /// `kid` of the signing key; defaults to its RFC 7638 thumbprint.
+    pub jwt_key_id:                 String,
+    /// Retired public keys still accepted: `kid=path.pem,...`
+    pub jwt_verify_keys:            String,
+    pub host:                       String,
+    pub port:                       u16,
+    pub ws_max_message_size:        usize,
+    pub ws_rate_limit_messages:     u32,
+    pub ws_rate_limit_window_secs:  u64,
+    pub ws_heartbeat_interval_secs: u64,
+    pub ws_pong_timeout_secs:       u64,
+    pub ws_idle_timeout_secs:       u64,
+    pub sse_backlog:                usize,
+    pub sse_resume_window_secs:     u64,
+    pub shutdown_grace_secs:        u64,
+    pub mail_transport:             String,
+    pub smtp_url:                   String,
+    pub mail_from:                  String,
+    pub mail_dir:                   String,
+    /// Public URL of the frontend; links in emails point here.
+    pub app_base_url:               String,
+    pub email_verify_ttl_secs:      i64,
+    pub password_reset_ttl_secs:    i64,
+    pub argon2_memory_kib:          u32,
+    pub argon2_iterations:          u32,
+    pub argon2_parallelism:         u32,
+    /// Max password hashes computed at once; 0 = one per CPU.
+    pub password_hash_concurrency:  usize,
+    pub oidc_providers:             Vec<OidcProviderConfig>,
+    /// Failed logins tolerated per account / per address within the window before a lockout.
+    pub login_max_account_failures: u32,
+    pub login_max_ip_failures:      u32,
+    pub login_failure_window_secs:  u64,
+    pub login_lockout_secs:         u64,
+    /// Take the client address from X-Forwarded-For / X-Real-IP.  Only behind a proxy that sets them.
+    pub trust_proxy_headers:        bool,
+    pub registration_mode:          RegistrationMode,
+    /// Let every user create invite codes, not just admins.
+    pub users_can_invite:           bool,
+    /// Pinned messages allowed per room.
+    pub max_pins_per_room:          i64,
+    /// How long a deleted room stays archived (and restorable) before it is purged.
+    pub room_purge_delay_secs:      u64,
+    pub room_purge_interval_secs:   u64,
+    /// Slug of the workspace new accounts join; empty to leave them in none.
+    pub default_workspace:          String,
 }
 
 
 impl Config {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
-            database_url:            env::var("DATABASE_URL")?,
-            redis_url:               env::var("REDIS_URL")?,
-            jwt_secret:              env::var("JWT_SECRET")?,
-            jwt_expiry_secs:         env::var("JWT_EXPIRY_SECS")?.parse()?,
-            jwt_refresh_expiry_secs: env::var("JWT_REFRESH_EXPIRY_SECS")?.parse()?,
-            host:                    env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into()),
-            port:                    env::var("PORT").unwrap_or_else(|_| "8080".into()).parse()?,
-        })
+        let config = Self {
+            database_url:               env::var("DATABASE_URL")?,
+            redis_url:                  env::var("REDIS_URL")?,
+            jwt_secret:                 env::var("JWT_SECRET")?,
+            jwt_expiry_secs:            env::var("JWT_EXPIRY_SECS")?.parse()?,
+            jwt_refresh_expiry_secs:    env::var("JWT_REFRESH_EXPIRY_SECS")?.parse()?,
+            jwt_algorithm:              env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".into()),
+            jwt_private_key_file:       env::var("JWT_PRIVATE_KEY_FILE").unwrap_or_default(),
+            jwt_public_key_file:        env::var("JWT_PUBLIC_KEY_FILE").unwrap_or_default(),
+            jwt_key_id:                 env::var("JWT_KEY_ID").unwrap_or_default(),
+            jwt_verify_keys:            env::var("JWT_VERIFY_KEYS").unwrap_or_default(),
+            host:                       env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into()),
+            port:                       env::var("PORT").unwrap_or_else(|_| "8080".into()).parse()?,
+            ws_max_message_size:        env::var("WS_MAX_MESSAGE_SIZE").unwrap_or_else(|_| "65536".into()).parse()?,
+            ws_rate_limit_messages:     env::var("WS_RATE_LIMIT_MESSAGES").unwrap_or_else(|_| "20".into()).parse()?,
+            ws_rate_limit_window_secs:  env::var("WS_RATE_LIMIT_WINDOW_SECS").unwrap_or_else(|_| "10".into()).parse()?,
+            ws_heartbeat_interval_secs: env::var("WS_HEARTBEAT_INTERVAL_SECS").unwrap_or_else(|_| "30".into()).parse()?,
+            ws_pong_timeout_secs:       env::var("WS_PONG_TIMEOUT_SECS").unwrap_or_else(|_| "10".into()).parse()?,
+            // 0 disables idle reaping; half-open peers are still caught by the pong deadline.
+            ws_idle_timeout_secs:       env::var("WS_IDLE_TIMEOUT_SECS").unwrap_or_else(|_| "0".into()).parse()?,
+            sse_backlog:                env::var("SSE_BACKLOG").unwrap_or_else(|_| "256".into()).parse()?,
+            sse_resume_window_secs:     env::var("SSE_RESUME_WINDOW_SECS").unwrap_or_else(|_| "30".into()).parse()?,
+            shutdown_grace_secs:        env::var("SHUTDOWN_GRACE_SECS").unwrap_or_else(|_| "10".into()).parse()?,
+            // smtp | file | outbox — see `mail`.
+            mail_transport:             env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "file".into()),
+            smtp_url:                   env::var("SMTP_URL").unwrap_or_default(),
+            mail_from:                  env::var("MAIL_FROM").unwrap_or_else(|_| "rust-chat-server <no-reply@localhost>".into()),
+            mail_dir:                   env::var("MAIL_DIR").unwrap_or_else(|_| "mail".into()),
+            app_base_url:               env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:8080".into()),
+            email_verify_ttl_secs:      env::var("EMAIL_VERIFY_TTL_SECS").unwrap_or_else(|_| "86400".into()).parse()?,
+            password_reset_ttl_secs:    env::var("PASSWORD_RESET_TTL_SECS").unwrap_or_else(|_| "3600".into()).parse()?,
+            argon2_memory_kib:          env::var("ARGON2_MEMORY_KIB").unwrap_or_else(|_| "19456".into()).parse()?,
+            argon2_iterations:          env::var("ARGON2_ITERATIONS").unwrap_or_else(|_| "2".into()).parse()?,
+            argon2_parallelism:         env::var("ARGON2_PARALLELISM").unwrap_or_else(|_| "1".into()).parse()?,
+            password_hash_concurrency:  env::var("PASSWORD_HASH_CONCURRENCY").unwrap_or_else(|_| "0".into()).parse()?,
+            // OIDC_PROVIDERS=corp,google with OIDC_CORP_ISSUER, OIDC_CORP_CLIENT_ID, ...
+            oidc_providers:             oidc_providers_from_env()?,
+            login_max_account_failures: env::var("LOGIN_MAX_ACCOUNT_FAILURES").unwrap_or_else(|_| "10".into()).parse()?,
+            login_max_ip_failures:      env::var("LOGIN_MAX_IP_FAILURES").unwrap_or_else(|_| "50".into()).parse()?,
+            login_failure_window_secs:  env::var("LOGIN_FAILURE_WINDOW_SECS").unwrap_or_else(|_| "900".into()).parse()?,
+            login_lockout_secs:         env::var("LOGIN_LOCKOUT_SECS").unwrap_or_else(|_| "900".into()).parse()?,
+            trust_proxy_headers:        env::var("TRUST_PROXY_HEADERS").unwrap_or_else(|_| "false".into()).parse()?,
+            registration_mode:          env::var("REGISTRATION_MODE").unwrap_or_else(|_| "open".into()).parse()?,
+            users_can_invite:           env::var("USERS_CAN_INVITE").unwrap_or_else(|_| "false".into()).parse()?,
+            max_pins_per_room:          env::var("MAX_PINS_PER_ROOM").unwrap_or_else(|_| "50".into()).parse()?,
+            room_purge_delay_secs:      env::var("ROOM_PURGE_DELAY_SECS").unwrap_or_else(|_| "604800".into()).parse()?,
+            room_purge_interval_secs:   env::var("ROOM_PURGE_INTERVAL_SECS").unwrap_or_else(|_| "3600".into()).parse()?,
+            default_workspace:          env::var("DEFAULT_WORKSPACE").unwrap_or_else(|_| "default".into()),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject values that parse but would break the server at runtime, e.g. a zero
+    /// period that makes a timer panic.
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.ws_heartbeat_interval_secs == 0 {
+            return Err("WS_HEARTBEAT_INTERVAL_SECS must be at least 1".into());
+        }
+        if self.ws_pong_timeout_secs == 0 || self.ws_pong_timeout_secs > self.ws_heartbeat_interval_secs {
+            return Err("WS_PONG_TIMEOUT_SECS must be between 1 and WS_HEARTBEAT_INTERVAL_SECS".into());
+        }
+        if self.room_purge_interval_secs == 0 {
+            return Err("ROOM_PURGE_INTERVAL_SECS must be at least 1".into());
+        }
+        Ok(())
     }
+}
+
+fn oidc_providers_from_env() -> Result<Vec<OidcProviderConfig>, Box<dyn std::error::Error>> {
+    let names = env::var("OIDC_PROVIDERS").unwrap_or_default();
+    names.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let var = |key: &str| format!("OIDC_{}_{key}", name.to_ascii_uppercase());
+            let required = |key: &str| env::var(var(key)).map_err(|_| format!("{} is required", var(key)));
+            Ok(OidcProviderConfig {
+                name:          name.to_ascii_lowercase(),
+                issuer:        required("ISSUER")?,
+                client_id:     required("CLIENT_ID")?,
+                client_secret: env::var(var("CLIENT_SECRET")).unwrap_or_default(),
+                scopes:        env::var(var("SCOPES")).unwrap_or_else(|_| "openid email profile".into()),
+            })
+        })
+        .collect()
 }
\ No newline at end of file
@@ -6,7 +6,7 @@ use uuid::Uuid;
 use chrono::Utc;
 use serde_json;
 
-use crate::models::session::Session;
+use crate::models::session::{Session, WsTicket, OidcPending};
 use crate::error::{Result, AppError};
 
 const SESSION_TTL_SECS: usize = 604_800;
@@ -18,4 +18,51 @@ pub async fn create_connection_manager(url: &str) -> Result<ConnectionManager> {
     ConnectionManager::new(client)
         .await
         .map_err(|e| AppError::Internal(format!("Redis pool: {e}").into()))
-}
\ No newline at end of file
+}
+
+pub const WS_TICKET_TTL_SECS: u64 = 30;
+
+pub async fn set_ws_ticket(redis: &mut ConnectionManager, ticket: &str, data: &WsTicket) -> Result<()> {
+    let payload = serde_json::to_string(data)
+        .map_err(|e| AppError::Internal(format!("Serialize ws ticket: {e}")))?;
+    redis.set_ex::<_, _, ()>(format!("ws_ticket:{ticket}"), payload, WS_TICKET_TTL_SECS).await?;
+    Ok(())
+}
+
+/// Fetch and delete a ticket in one round trip, so it can only ever be redeemed once.
+pub async fn take_ws_ticket(redis: &mut ConnectionManager, ticket: &str) -> Result<Option<WsTicket>> {
+    let payload: Option<String> = redis.get_del(format!("ws_ticket:{ticket}")).await?;
+    payload
+        .map(|p| serde_json::from_str(&p)
+            .map_err(|e| AppError::Internal(format!("Deserialize ws ticket: {e}"))))
+        .transpose()
+}
+
+/// Access and refresh tokens issued before `issued_before_ms` (unix millis) are revoked.
+/// The key only needs to outlive the longest-lived token, so it expires after `ttl_secs`.
+pub async fn revoke_tokens_before(redis: &mut ConnectionManager, user_id: Uuid, issued_before_ms: i64, ttl_secs: u64) -> Result<()> {
+    redis.set_ex::<_, _, ()>(format!("tokens_revoked_before_ms:{user_id}"), issued_before_ms, ttl_secs).await?;
+    Ok(())
+}
+
+pub async fn tokens_revoked_before(redis: &mut ConnectionManager, user_id: Uuid) -> Result<Option<i64>> {
+    Ok(redis.get(format!("tokens_revoked_before_ms:{user_id}")).await?)
+}
+
+pub const OIDC_STATE_TTL_SECS: u64 = 600;
+
+pub async fn set_oidc_pending(redis: &mut ConnectionManager, state: &str, data: &OidcPending) -> Result<()> {
+    let payload = serde_json::to_string(data)
+        .map_err(|e| AppError::Internal(format!("Serialize oidc state: {e}")))?;
+    redis.set_ex::<_, _, ()>(format!("oidc_state:{state}"), payload, OIDC_STATE_TTL_SECS).await?;
+    Ok(())
+}
+
+/// Fetch and delete in one step, so a callback can't be replayed.
+pub async fn take_oidc_pending(redis: &mut ConnectionManager, state: &str) -> Result<Option<OidcPending>> {
+    let payload: Option<String> = redis.get_del(format!("oidc_state:{state}")).await?;
+    payload
+        .map(|p| serde_json::from_str(&p)
+            .map_err(|e| AppError::Internal(format!("Deserialize oidc state: {e}"))))
+        .transpose()
+}
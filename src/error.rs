@@ -41,23 +41,44 @@ pub enum AppError {
 
     #[error("Rate limit exceeded")]
     RateLimited,
+
+    /// Slow mode: the member must wait this many more seconds.
+    #[error("Slow mode: retry in {0}s")]
+    SlowMode(u64),
+
+    /// A room's posting policy refused the message; the first field is the client-facing code.
+    #[error("Posting restricted ({0}): {1}")]
+    PostingRestricted(&'static str, String),
+
+    #[error("Service Unavailable: {0}")]
+    ServiceUnavailable(String),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, code, message) = match &self {
+impl AppError {
+    /// HTTP status, stable error code and client-safe message for this error.
+    pub fn parts(&self) -> (StatusCode, &'static str, String) {
+        match self {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg.clone()),
             AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", msg.clone()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg.clone()),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg.clone()),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "FORBIDDEN", msg.clone()),
             AppError::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "TOO_MANY_REQUESTS", "Rate limit exceeded".into()),
+            AppError::SlowMode(secs) => (StatusCode::TOO_MANY_REQUESTS, "SLOW_MODE", format!("Slow mode is on; wait {secs}s before posting again")),
+            AppError::PostingRestricted(code, msg) => (StatusCode::FORBIDDEN, code, msg.clone()),
+            AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE", msg.clone()),
             AppError::Internal(_)
             | AppError::Database(_)
             | AppError::Redis(_)
             | AppError::Jwt(_)
             | AppError::Bcrypt(_)        => (StatusCode::INTERNAL_SERVER_ERROR,  "INTERNAL_SERVER_ERROR", "Internal server error".into()),
-        };
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = self.parts();
 
         tracing::error!("AppError:{}", self);
 
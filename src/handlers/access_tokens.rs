@@ -0,0 +1,58 @@
+use axum::{extract::{Path, Query, State}, Json};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::models::access_token::{CreateAccessTokenRequest, CreateBotRequest, ListAccessTokensParams};
+use crate::services::access_token_service;
+
+// Credentials are managed from a logged-in session only; a token can't mint more tokens.
+
+pub async fn create_bot(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreateBotRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let bot = access_token_service::create_bot(&state.pool, auth.claims().user_id()?, auth.require_workspace()?, &req).await?;
+    Ok(Json(json!({ "bot": bot })))
+}
+
+pub async fn list_bots(State(state): State<AppState>, auth: AuthUser) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let bots = access_token_service::list_bots(&state.pool, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "bots": bots })))
+}
+
+/// `POST /api/tokens` — the plaintext `token` is in this response only.
+pub async fn create_token(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreateAccessTokenRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let (plain, token) = access_token_service::create_token(&state.pool, auth.claims().user_id()?, auth.workspace_id(), &req).await?;
+    Ok(Json(json!({ "token": plain, "access_token": token })))
+}
+
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<ListAccessTokensParams>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let tokens = access_token_service::list_tokens(&state.pool, auth.claims().user_id()?, params.bot_id).await?;
+    Ok(Json(json!({ "access_tokens": tokens })))
+}
+
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(token_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    access_token_service::revoke_token(&state.pool, auth.claims().user_id()?, token_id).await?;
+    Ok(Json(json!({ "message": "Token revoked" })))
+}
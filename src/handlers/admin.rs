@@ -0,0 +1,32 @@
+use axum::{extract::{Query, State}, Json};
+use serde_json::json;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::client_ip::ClientIp;
+use crate::models::audit::{AuditEventsParams, ClearLockoutRequest};
+use crate::services::admin_service;
+
+/// `POST /api/admin/lockouts/clear` — `{ "email": ..., "ip": ... }`, either or both.
+pub async fn clear_lockout(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    client_ip: ClientIp,
+    Json(req): Json<ClearLockoutRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let ip = client_ip.to_string_opt();
+    admin_service::clear_lockout(&state.pool, auth.claims().user_id()?, &req, ip.as_deref()).await?;
+    Ok(Json(json!({ "message": "Lockout cleared" })))
+}
+
+pub async fn audit_events(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<AuditEventsParams>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let events = admin_service::list_audit_events(&state.pool, auth.claims().user_id()?, &params).await?;
+    Ok(Json(json!({ "events": events })))
+}
@@ -1,37 +1,64 @@
-use axum::{extract::State, Json};
+use axum::{extract::{Path, Query, State}, response::Redirect, Json};
 use serde_json::json;
 use serde::Deserialize;
 
 use crate::AppState;
-use crate::models::user::{self, RegisterRequest};
+use crate::models::user::{
+    self, RegisterRequest, TotpCodeRequest, MfaVerifyRequest, DisableTotpRequest,
+    EmailTokenRequest, ForgotPasswordRequest, ResetPasswordRequest,
+};
 use crate::error::Result;
-use crate::services::auth_service;
+use crate::repositories::user_repo;
+use crate::services::{account_service, auth_service, mfa_service, oidc_service};
+use crate::services::auth_service::LoginOutcome;
 use crate::middleware::auth::AuthUser;
+use crate::middleware::client_ip::ClientIp;
 
 
 pub async fn register(
     State(state): State<AppState>,
     Json(req): Json<RegisterRequest>,
 ) -> Result<Json<serde_json::Value>> {
-    let (user, token) = auth_service::register(&state.pool, &req).await?;
+    let (user, token) = auth_service::register(&state.pool, &state.jwt, &state.config, &req).await?;
+    // The account exists either way; a failed send can be retried via /verify-email/resend.
+    if let Some(created) = user_repo::get_user_by_id(&state.pool.pg, user.id).await? {
+        if let Err(e) = account_service::send_verification_email(&state.pool, state.mailer.as_ref(), &state.config, &created).await {
+            tracing::warn!("Verification email for {} not sent: {e}", user.id);
+        }
+    }
     Ok(Json(json!({"user": user, "token": token})))
 }
 
 pub async fn login(
     State(state): State<AppState>,
+    client_ip: ClientIp,
     Json(req): Json<RegisterRequest>,
 ) -> Result<Json<serde_json::Value>> {
-    let (user, token) = auth_service::login(&state.pool, &req).await?;
-    Ok(Json(json!({"user": user, "token": token})))
+    let ip = client_ip.to_string_opt();
+    let outcome = auth_service::login(&state.pool, &state.jwt, &state.config, &req, ip.as_deref()).await?;
+    Ok(login_response(outcome))
+}
+
+/// Tokens, or the pending second-factor challenge.
+fn login_response(outcome: LoginOutcome) -> Json<serde_json::Value> {
+    match outcome {
+        LoginOutcome::Authenticated(user, token) => Json(json!({"user": user, "token": token})),
+        LoginOutcome::MfaRequired { mfa_token, expires_in } => Json(json!({
+            "mfa_required": true,
+            "mfa_token":    mfa_token,
+            "expires_in":   expires_in,
+        })),
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RefreshRequest { pub refresh_token: String }
 
 pub async fn refresh(
+    State(state): State<AppState>,
     Json(req): Json<RefreshRequest>
 ) -> Result<Json<serde_json::Value>> {
-    let token = auth_service::refresh_token(&req.refresh_token).await?;
+    let token = auth_service::refresh_token(&state.pool, &state.jwt, &req.refresh_token).await?;
     Ok(Json(json!({"token": token})))
 }
 
@@ -47,3 +74,124 @@ pub async fn me(State(state): State<AppState>, auth: AuthUser) -> Result<Json<se
     let user = auth_service::get_current_user(&state.pool, user_id).await?;
     Ok(Json(json!({ "user": user })))
 }
+
+// ──────────────────── Two-factor auth ─────────────────
+pub async fn enroll_totp(State(state): State<AppState>, auth: AuthUser) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let user_id = auth.claims().user_id()?;
+    let enrollment = mfa_service::begin_enrollment(&state.pool, user_id).await?;
+    Ok(Json(json!({ "enrollment": enrollment })))
+}
+
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<TotpCodeRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let user_id = auth.claims().user_id()?;
+    let codes = mfa_service::confirm_enrollment(&state.pool, user_id, &req.code).await?;
+    Ok(Json(json!({ "recovery_codes": codes })))
+}
+
+pub async fn verify_totp(
+    State(state): State<AppState>,
+    Json(req): Json<MfaVerifyRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let (user, token) = mfa_service::verify_login(
+        &state.pool, &state.jwt, &req.mfa_token, req.code.as_deref(), req.recovery_code.as_deref(),
+    ).await?;
+    Ok(Json(json!({"user": user, "token": token})))
+}
+
+pub async fn disable_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<DisableTotpRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let user_id = auth.claims().user_id()?;
+    mfa_service::disable(
+        &state.pool, user_id, &req.password, req.code.as_deref(), req.recovery_code.as_deref(),
+    ).await?;
+    Ok(Json(json!({ "message": "Two-factor authentication disabled" })))
+}
+
+// ──────────────────── Email verification & password reset ─────────────────
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(req): Json<EmailTokenRequest>,
+) -> Result<Json<serde_json::Value>> {
+    account_service::verify_email(&state.pool, &state.config, &req.token).await?;
+    Ok(Json(json!({ "message": "Email verified" })))
+}
+
+pub async fn resend_verification(State(state): State<AppState>, auth: AuthUser) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let user_id = auth.claims().user_id()?;
+    account_service::resend_verification_email(&state.pool, state.mailer.as_ref(), &state.config, user_id).await?;
+    Ok(Json(json!({ "message": "Verification email sent" })))
+}
+
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(req): Json<ForgotPasswordRequest>,
+) -> Result<Json<serde_json::Value>> {
+    account_service::request_password_reset(&state.pool, state.mailer.as_ref(), &state.config, &req.email).await?;
+    Ok(Json(json!({ "message": "If that address is registered, a reset link has been sent" })))
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(req): Json<ResetPasswordRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let user_id = account_service::reset_password(&state.pool, &state.config, &req.token, &req.new_password).await?;
+    state.hub.close_user(user_id, axum::extract::ws::close_code::POLICY, "session revoked");
+    Ok(Json(json!({ "message": "Password updated; please sign in again" })))
+}
+
+/// `GET /.well-known/jwks.json` — public keys other services use to verify our tokens.
+pub async fn jwks(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(state.jwt.jwks())
+}
+
+// ──────────────────── Single sign-on (OIDC) ─────────────────
+pub async fn oidc_providers(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(json!({ "providers": state.oidc.names() }))
+}
+
+/// `GET /api/auth/oidc/:provider/authorize` — redirect the browser to the provider.
+pub async fn oidc_authorize(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> Result<Redirect> {
+    let url = oidc_service::begin(&state.pool, &state.oidc, &state.config, &provider).await?;
+    Ok(Redirect::to(&url))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackParams {
+    pub code:              Option<String>,
+    pub state:             Option<String>,
+    pub error:             Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// `GET /api/auth/oidc/:provider/callback` — where the provider sends the browser back.
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Query(params): Query<OidcCallbackParams>,
+) -> Result<Json<serde_json::Value>> {
+    if let Some(error) = params.error {
+        let detail = params.error_description.unwrap_or(error);
+        return Err(crate::error::AppError::Unauthorized(format!("Sign-in failed: {detail}")));
+    }
+    let (Some(code), Some(oidc_state)) = (params.code, params.state) else {
+        return Err(crate::error::AppError::BadRequest("Missing code or state".into()));
+    };
+    let outcome = oidc_service::complete(
+        &state.pool, &state.oidc, &state.jwt, &state.config, &provider, &code, &oidc_state,
+    ).await?;
+    Ok(login_response(outcome))
+}
@@ -0,0 +1,91 @@
+use std::convert::Infallible;
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+use crate::AppState;
+use crate::error::{AppError, Result};
+use crate::handlers::ws::{handle_client_message, hello};
+use crate::middleware::auth::AuthUser;
+use crate::models::access_token::Scope;
+use crate::websocket::protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use crate::websocket::sse::Resume;
+
+/// `GET /api/events` — Server-Sent Events fallback for clients that can't keep a
+/// WebSocket open.  Each event's data is a JSON `ServerMessage`, exactly as sent over
+/// `/ws`.  Reconnect with `Last-Event-ID` to resume.
+pub async fn stream(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    if state.hub.is_shutting_down() {
+        return Err(AppError::ServiceUnavailable("Server is restarting".into()));
+    }
+    auth.require(Scope::MessagesRead)?;
+    let user_id = auth.claims().user_id()?;
+    let (session, created) = state.sse.attach(user_id, &auth.claims().username, auth.workspace_id());
+    if created {
+        state.hub.send_to_connection(user_id, session.connection_id(), &hello(&state, PROTOCOL_VERSION));
+    }
+
+    let last_event_id = headers.get("last-event-id").and_then(|v| v.to_str().ok());
+    let (mut cursor, gap) = match session.resume_from(last_event_id) {
+        Resume::After(seq) => (seq, false),
+        Resume::Gap        => (session.oldest_cursor(), true),
+    };
+
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        if gap {
+            let err = ServerMessage::Error {
+                code:    "RESUME_GAP".into(),
+                message: "Some events could not be replayed; refetch state".into(),
+            };
+            if let Ok(data) = serde_json::to_string(&err) {
+                let _ = tx.send(Ok(Event::default().data(data))).await;
+            }
+        }
+        'stream: loop {
+            let notified = session.notified();
+            for (seq, data) in session.read_after(cursor) {
+                let event = Event::default().id(session.event_id(seq)).data(&*data);
+                if tx.send(Ok(event)).await.is_err() {
+                    break 'stream;
+                }
+                cursor = seq;
+            }
+            if session.is_closed() {
+                break;
+            }
+            tokio::select! {
+                _ = notified => {}
+                _ = tx.closed() => break,
+            }
+        }
+        session.detach();
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
+}
+
+/// `POST /api/events` — the HTTP counterpart of a client WebSocket frame.  Accepts any
+/// `ClientMessage`; results are delivered on the event stream, and direct replies only
+/// reach it while a stream is open.
+pub async fn send(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(msg): Json<ClientMessage>,
+) -> Result<Json<serde_json::Value>> {
+    let user_id = auth.claims().user_id()?;
+    let workspace_id = auth.workspace_id();
+    let connection_id = state.sse.connection_id(user_id, workspace_id);
+    handle_client_message(&state, user_id, workspace_id, connection_id, auth.access(), msg).await?;
+    Ok(Json(json!({ "status": "ok" })))
+}
@@ -0,0 +1,36 @@
+use axum::{extract::{Path, State}, Json};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::models::invite::CreateInviteRequest;
+use crate::services::invite_service;
+
+/// `POST /api/invites` — the plaintext `code` is in this response only.
+pub async fn create_invite(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreateInviteRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let (code, invite) = invite_service::create_invite(&state.pool, &state.config, auth.claims().user_id()?, &req).await?;
+    Ok(Json(json!({ "code": code, "invite": invite })))
+}
+
+pub async fn list_invites(State(state): State<AppState>, auth: AuthUser) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let invites = invite_service::list_invites(&state.pool, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "invites": invites })))
+}
+
+pub async fn revoke_invite(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    invite_service::revoke_invite(&state.pool, auth.claims().user_id()?, id).await?;
+    Ok(Json(json!({ "message": "Invite revoked" })))
+}
@@ -1 +1,12 @@
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod ws;
+pub mod events;
+pub mod access_tokens;
+pub mod admin;
+pub mod invites;
+pub mod rooms;
+pub mod moderation;
+pub mod room_invites;
+pub mod pins;
+pub mod workspaces;
+pub mod users;
@@ -0,0 +1,132 @@
+use axum::{extract::{Path, State}, Json};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::optional_json::OptionalJson;
+use crate::models::access_token::Scope;
+use crate::models::moderation::{KickRequest, SanctionRequest};
+use crate::services::{moderation_service, room_service};
+use crate::websocket::protocol::ServerMessage;
+
+// Bodies are optional: a bare POST/PUT kicks, bans or mutes without a reason, indefinitely.
+// A body that is present must parse; it never falls back to those defaults.
+
+fn announce(
+    state: &AppState,
+    room_id: Uuid,
+    user_id: Uuid,
+    action: &str,
+    moderator_id: Uuid,
+    reason: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+) -> ServerMessage {
+    let msg = ServerMessage::ModerationAction {
+        room_id,
+        user_id,
+        action: action.into(),
+        moderator_id,
+        reason,
+        expires_at,
+    };
+    state.hub.broadcast_to_room(room_id, &msg, None);
+    msg
+}
+
+/// `POST /api/rooms/:id/members/:user_id/kick`
+pub async fn kick(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+    body: OptionalJson<KickRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let req = body.or_default();
+    let moderator_id = auth.claims().user_id()?;
+    let reason = moderation_service::kick(&state.pool, room_id, moderator_id, user_id, &req).await?;
+    // Announce first so the target sees why, then drop them from the live room.
+    announce(&state, room_id, user_id, "kick", moderator_id, reason, None);
+    state.hub.leave_room(room_id, user_id);
+    Ok(Json(json!({ "message": "Member kicked" })))
+}
+
+/// `PUT /api/rooms/:id/bans/:user_id`
+pub async fn ban(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+    body: OptionalJson<SanctionRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let req = body.or_default();
+    let moderator_id = auth.claims().user_id()?;
+    let ban = moderation_service::ban(&state.pool, room_id, moderator_id, user_id, &req).await?;
+    announce(&state, room_id, user_id, "ban", moderator_id, ban.reason.clone(), ban.expires_at);
+    state.hub.leave_room(room_id, user_id);
+    Ok(Json(json!({ "ban": ban })))
+}
+
+pub async fn unban(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let moderator_id = auth.claims().user_id()?;
+    moderation_service::unban(&state.pool, room_id, moderator_id, user_id).await?;
+    // The target isn't in the room any more; tell them directly.
+    let msg = announce(&state, room_id, user_id, "unban", moderator_id, None, None);
+    let workspace_id = room_service::workspace_of(&state.pool, room_id).await?;
+    state.hub.send_to_user(user_id, workspace_id, &msg);
+    Ok(Json(json!({ "message": "Ban lifted" })))
+}
+
+pub async fn list_bans(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let bans = moderation_service::list_bans(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "bans": bans })))
+}
+
+/// `PUT /api/rooms/:id/mutes/:user_id`
+pub async fn mute(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+    body: OptionalJson<SanctionRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let req = body.or_default();
+    let moderator_id = auth.claims().user_id()?;
+    let mute = moderation_service::mute(&state.pool, room_id, moderator_id, user_id, &req).await?;
+    announce(&state, room_id, user_id, "mute", moderator_id, mute.reason.clone(), mute.expires_at);
+    Ok(Json(json!({ "mute": mute })))
+}
+
+pub async fn unmute(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let moderator_id = auth.claims().user_id()?;
+    moderation_service::unmute(&state.pool, room_id, moderator_id, user_id).await?;
+    announce(&state, room_id, user_id, "unmute", moderator_id, None, None);
+    Ok(Json(json!({ "message": "Mute lifted" })))
+}
+
+pub async fn list_mutes(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let mutes = moderation_service::list_mutes(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "mutes": mutes })))
+}
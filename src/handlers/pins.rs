@@ -0,0 +1,53 @@
+use axum::{extract::{Path, State}, Json};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::models::access_token::Scope;
+use crate::services::pin_service;
+use crate::websocket::protocol::ServerMessage;
+
+pub async fn list_pins(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::MessagesRead)?;
+    let pins = pin_service::list_pins(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "pins": pins })))
+}
+
+/// `PUT /api/rooms/:id/pins/:message_id`
+pub async fn pin_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::MessagesWrite)?;
+    let user_id = auth.claims().user_id()?;
+    let pin = pin_service::pin_message(&state.pool, &state.config, room_id, message_id, user_id).await?;
+    state.hub.broadcast_to_room(room_id, &ServerMessage::MessagePinned {
+        room_id,
+        message_id,
+        pinned_by: user_id,
+    }, None);
+    Ok(Json(json!({ "pin": pin })))
+}
+
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::MessagesWrite)?;
+    let user_id = auth.claims().user_id()?;
+    pin_service::unpin_message(&state.pool, room_id, message_id, user_id).await?;
+    state.hub.broadcast_to_room(room_id, &ServerMessage::MessageUnpinned {
+        room_id,
+        message_id,
+        unpinned_by: user_id,
+    }, None);
+    Ok(Json(json!({ "message": "Message unpinned" })))
+}
@@ -0,0 +1,196 @@
+use axum::{extract::{Path, State}, Json};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::{AppError, Result};
+use crate::middleware::auth::AuthUser;
+use crate::middleware::optional_json::OptionalJson;
+use crate::models::access_token::Scope;
+use crate::models::room::Permissions;
+use crate::models::room_invite::{CreateInviteLinkRequest, CreateJoinRequest, InviteUserRequest, RedeemInviteLinkRequest};
+use crate::repositories::user_repo;
+use crate::services::{room_invite_service, room_service};
+use crate::websocket::protocol::{ServerMessage, WsUser};
+
+async fn ws_user(state: &AppState, user_id: Uuid) -> Result<WsUser> {
+    let user = user_repo::get_user_by_id(&state.pool.pg, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+    Ok(WsUser {
+        id:           user.id,
+        username:     user.username,
+        display_name: user.display_name,
+    })
+}
+
+/// `POST /api/rooms/:id/invitations` — `{ "user_id": ... }`; pushed to the invitee.
+pub async fn invite_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Json(req): Json<InviteUserRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let inviter_id = auth.claims().user_id()?;
+    let (invitation, room) = room_invite_service::invite_user(&state.pool, room_id, inviter_id, req.user_id).await?;
+    state.hub.send_to_user(invitation.invitee_id, room.workspace_id, &ServerMessage::RoomInvitation {
+        invitation_id: invitation.id,
+        room_id,
+        room_name:     room.name,
+        inviter:       ws_user(&state, inviter_id).await?,
+    });
+    Ok(Json(json!({ "invitation": invitation })))
+}
+
+pub async fn list_room_invitations(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let invitations = room_invite_service::list_room_invitations(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "invitations": invitations })))
+}
+
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, invitation_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    room_invite_service::revoke_invitation(&state.pool, room_id, auth.claims().user_id()?, invitation_id).await?;
+    Ok(Json(json!({ "message": "Invitation revoked" })))
+}
+
+/// `GET /api/invitations` — your pending room invitations.
+pub async fn my_invitations(State(state): State<AppState>, auth: AuthUser) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let invitations = room_invite_service::list_my_invitations(&state.pool, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "invitations": invitations })))
+}
+
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let invitation = room_invite_service::respond_to_invitation(&state.pool, auth.claims().user_id()?, invitation_id, true).await?;
+    Ok(Json(json!({ "invitation": invitation })))
+}
+
+pub async fn decline_invitation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let invitation = room_invite_service::respond_to_invitation(&state.pool, auth.claims().user_id()?, invitation_id, false).await?;
+    Ok(Json(json!({ "invitation": invitation })))
+}
+
+/// `POST /api/rooms/:id/links` — the plaintext `code` is in this response only.
+pub async fn create_link(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Json(req): Json<CreateInviteLinkRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let (code, link) = room_invite_service::create_link(&state.pool, room_id, auth.claims().user_id()?, &req).await?;
+    Ok(Json(json!({ "code": code, "link": link })))
+}
+
+pub async fn list_links(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let links = room_invite_service::list_links(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "links": links })))
+}
+
+pub async fn revoke_link(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, link_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    room_invite_service::revoke_link(&state.pool, room_id, auth.claims().user_id()?, link_id).await?;
+    Ok(Json(json!({ "message": "Invite link revoked" })))
+}
+
+/// `POST /api/room-links/redeem` — `{ "code": ... }`.
+pub async fn redeem_link(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<RedeemInviteLinkRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let room = room_invite_service::redeem_link(&state.pool, auth.claims().user_id()?, &req.code).await?;
+    Ok(Json(json!({ "room": room })))
+}
+
+/// `POST /api/rooms/:id/join-requests` — pushed to everyone who can approve it.
+pub async fn request_to_join(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    body: OptionalJson<CreateJoinRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let req = body.or_default();
+    let user_id = auth.claims().user_id()?;
+    let request = room_invite_service::request_to_join(&state.pool, room_id, user_id, req.message.as_deref()).await?;
+    let msg = ServerMessage::JoinRequested {
+        request_id: request.id,
+        room_id,
+        user:       ws_user(&state, user_id).await?,
+        message:    request.message.clone(),
+    };
+    let workspace_id = room_service::workspace_of(&state.pool, room_id).await?;
+    for approver in room_service::members_with_permission(&state.pool, room_id, Permissions::MANAGE_ROOM).await? {
+        state.hub.send_to_user(approver, workspace_id, &msg);
+    }
+    Ok(Json(json!({ "request": request })))
+}
+
+pub async fn list_join_requests(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let requests = room_invite_service::list_join_requests(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "requests": requests })))
+}
+
+async fn review(state: AppState, auth: AuthUser, room_id: Uuid, request_id: Uuid, approve: bool) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let request = room_invite_service::review_join_request(&state.pool, room_id, auth.claims().user_id()?, request_id, approve).await?;
+    let workspace_id = room_service::workspace_of(&state.pool, room_id).await?;
+    state.hub.send_to_user(request.user_id, workspace_id, &ServerMessage::JoinRequestReviewed {
+        request_id: request.id,
+        room_id,
+        approved:   approve,
+    });
+    Ok(Json(json!({ "request": request })))
+}
+
+pub async fn approve_join_request(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, request_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    review(state, auth, room_id, request_id, true).await
+}
+
+pub async fn reject_join_request(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, request_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    review(state, auth, room_id, request_id, false).await
+}
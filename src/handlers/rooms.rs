@@ -0,0 +1,314 @@
+use axum::{extract::{Path, Query, State}, Json};
+use serde_json::json;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::optional_json::OptionalJson;
+use crate::models::access_token::Scope;
+use crate::models::message::PaginationParams;
+use crate::models::posting_policy::PostingPolicy;
+use crate::models::room::{
+    ChangeRoleRequest, CreateRoomRequest, LeaveRoomRequest, ListRoomsParams, Room, RoomRole, SetRolePermissionsRequest,
+    SetRoomTagsRequest, TransferOwnershipRequest, UpdateRoomRequest,
+};
+use crate::handlers::ws::announce_owner_change;
+use crate::services::{message_service, posting_policy_service, room_service};
+use crate::websocket::protocol::ServerMessage;
+
+pub async fn create_room(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreateRoomRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let room = room_service::create_room(&state.pool, &req, auth.claims().user_id()?, auth.require_workspace()?).await?;
+    Ok(Json(json!({ "room": room })))
+}
+
+/// `GET /api/rooms?q=&tag=&sort=created|members|activity&cursor=&limit=` — the public
+/// directory of the active workspace.
+pub async fn list_rooms(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<ListRoomsParams>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    directory_page(&state, &auth, None, &params).await
+}
+
+/// `GET /api/rooms/mine` — the caller's rooms, private ones included.
+pub async fn my_rooms(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<ListRoomsParams>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    directory_page(&state, &auth, Some(auth.claims().user_id()?), &params).await
+}
+
+async fn directory_page(
+    state: &AppState,
+    auth: &AuthUser,
+    member_id: Option<Uuid>,
+    params: &ListRoomsParams,
+) -> Result<Json<serde_json::Value>> {
+    let (mut rooms, next_cursor) = room_service::directory(
+        &state.pool, auth.require_workspace()?, auth.claims().user_id()?, member_id, params,
+    ).await?;
+    for entry in &mut rooms {
+        entry.online_count = state.hub.online_count(entry.room.id);
+    }
+    Ok(Json(json!({ "rooms": rooms, "next_cursor": next_cursor })))
+}
+
+/// `GET /api/room-tags` — directory categories with how many rooms use each.
+pub async fn list_tags(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let tags: Vec<_> = room_service::tag_counts(&state.pool, auth.require_workspace()?, auth.claims().user_id()?)
+        .await?
+        .into_iter()
+        .map(|(tag, rooms)| json!({ "tag": tag, "rooms": rooms }))
+        .collect();
+    Ok(Json(json!({ "tags": tags })))
+}
+
+/// `PUT /api/rooms/:id/tags` — replaces the room's tags.
+pub async fn set_room_tags(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Json(req): Json<SetRoomTagsRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let tags = room_service::set_tags(&state.pool, room_id, auth.claims().user_id()?, &req.tags).await?;
+    Ok(Json(json!({ "tags": tags })))
+}
+
+pub async fn get_room(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let room = room_service::get_room(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "room": room })))
+}
+
+pub async fn update_room(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Json(req): Json<UpdateRoomRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let user_id = auth.claims().user_id()?;
+    let update = room_service::update_room(&state.pool, room_id, user_id, &req).await?;
+    if let Some(message) = &update.message {
+        state.hub.broadcast_to_room(room_id, &ServerMessage::RoomUpdated {
+            room_id,
+            room:       Box::new(update.room.clone()),
+            changed:    update.changed.iter().map(|f| f.to_string()).collect(),
+            updated_by: user_id,
+            message_id: message.id,
+        }, None);
+    }
+    Ok(Json(json!({ "room": update.room, "changed": update.changed })))
+}
+
+/// Tell live subscribers the room is archived, then drop them from it.
+fn announce_archived(state: &AppState, room: &Room, archived_by: Uuid) {
+    state.hub.broadcast_to_room(room.id, &ServerMessage::RoomArchived {
+        room_id:     room.id,
+        archived_by,
+        purge_after: room.purge_after,
+    }, None);
+    state.hub.close_room(room.id);
+}
+
+/// `DELETE /api/rooms/:id` — archives the room now and purges it after `ROOM_PURGE_DELAY_SECS`.
+pub async fn delete_room(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let user_id = auth.claims().user_id()?;
+    let delay = Duration::from_secs(state.config.room_purge_delay_secs);
+    let room = room_service::delete_room(&state.pool, room_id, user_id, delay).await?;
+    announce_archived(&state, &room, user_id);
+    Ok(Json(json!({ "message": "Room scheduled for deletion", "purge_after": room.purge_after })))
+}
+
+pub async fn archive_room(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let user_id = auth.claims().user_id()?;
+    let room = room_service::archive_room(&state.pool, room_id, user_id).await?;
+    announce_archived(&state, &room, user_id);
+    Ok(Json(json!({ "room": room })))
+}
+
+pub async fn unarchive_room(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let room = room_service::unarchive_room(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "room": room })))
+}
+
+/// `POST /api/rooms/:id/leave` — the owner may pass `{ "successor_id": ... }`.
+pub async fn leave_room(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    body: OptionalJson<LeaveRoomRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let req = body.or_default();
+    let user_id = auth.claims().user_id()?;
+    let change = room_service::leave_room(&state.pool, room_id, user_id, req.successor_id).await?;
+    state.hub.leave_room(room_id, user_id);
+    if let Some(change) = change {
+        announce_owner_change(&state.hub, room_id, change);
+    }
+    Ok(Json(json!({ "message": "Left room", "new_owner_id": change.map(|c| c.new) })))
+}
+
+/// `POST /api/rooms/:id/transfer-ownership` — `{ "user_id": ... }`; you become an admin.
+pub async fn transfer_ownership(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Json(req): Json<TransferOwnershipRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let change = room_service::transfer_ownership(&state.pool, room_id, auth.claims().user_id()?, req.user_id).await?;
+    announce_owner_change(&state.hub, room_id, change);
+    Ok(Json(json!({ "previous_owner_id": change.previous, "new_owner_id": change.new })))
+}
+
+pub async fn get_posting_policy(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let policy = posting_policy_service::get_policy(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "policy": policy })))
+}
+
+/// `PUT /api/rooms/:id/posting-policy` — replaces the whole policy.
+pub async fn set_posting_policy(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Json(req): Json<PostingPolicy>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let user_id = auth.claims().user_id()?;
+    let policy = posting_policy_service::set_policy(&state.pool, room_id, user_id, &req).await?;
+    state.hub.broadcast_to_room(room_id, &ServerMessage::PostingPolicyChanged {
+        room_id,
+        policy:     policy.clone(),
+        changed_by: user_id,
+    }, None);
+    Ok(Json(json!({ "policy": policy })))
+}
+
+pub async fn list_members(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let members = room_service::get_room_members(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "members": members })))
+}
+
+/// `PUT /api/rooms/:id/members/:user_id/role` — `{ "role": "moderator" }`.
+pub async fn change_member_role(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<ChangeRoleRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let actor_id = auth.claims().user_id()?;
+    let member = room_service::change_member_role(&state.pool, room_id, actor_id, user_id, req.role).await?;
+    state.hub.broadcast_to_room(room_id, &ServerMessage::RoleChanged {
+        room_id,
+        user_id,
+        role:       member.role.clone(),
+        changed_by: actor_id,
+    }, None);
+    Ok(Json(json!({ "member": member })))
+}
+
+pub async fn get_permissions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let permissions = room_service::get_permissions(&state.pool, room_id, auth.claims().user_id()?).await?;
+    Ok(Json(permissions))
+}
+
+/// `PUT /api/rooms/:id/permissions/:role` — `{ "permissions": ["send", "pin"] }`.
+pub async fn set_role_permissions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, role)): Path<(Uuid, RoomRole)>,
+    Json(req): Json<SetRolePermissionsRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let user_id = auth.claims().user_id()?;
+    room_service::set_role_permissions(&state.pool, room_id, user_id, role, req.permissions).await?;
+    let permissions = room_service::get_permissions(&state.pool, room_id, user_id).await?;
+    Ok(Json(permissions))
+}
+
+/// `DELETE /api/rooms/:id/permissions/:role` — back to the role's defaults.
+pub async fn reset_role_permissions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((room_id, role)): Path<(Uuid, RoomRole)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsWrite)?;
+    let user_id = auth.claims().user_id()?;
+    room_service::reset_role_permissions(&state.pool, room_id, user_id, role).await?;
+    let permissions = room_service::get_permissions(&state.pool, room_id, user_id).await?;
+    Ok(Json(permissions))
+}
+
+pub async fn list_messages(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::MessagesRead)?;
+    let messages = message_service::get_messages(&state.pool, room_id, auth.claims().user_id()?, &params).await?;
+    Ok(Json(json!({ "messages": messages })))
+}
+
+pub async fn delete_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(message_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::MessagesWrite)?;
+    message_service::delete_message(&state.pool, message_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "message": "Message deleted" })))
+}
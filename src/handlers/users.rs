@@ -0,0 +1,34 @@
+use axum::{extract::{Path, Query, State}, Json};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::models::access_token::Scope;
+use crate::models::workspace::UserSearchParams;
+use crate::services::workspace_service;
+
+/// `GET /api/users/search?q=&limit=` — people in the active workspace.
+pub async fn search_users(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<UserSearchParams>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let users = workspace_service::search_users(
+        &state.pool, auth.require_workspace()?, auth.claims().user_id()?, &params.q, params.limit,
+    ).await?;
+    Ok(Json(json!({ "users": users })))
+}
+
+/// `GET /api/users/:id` — only people who share a workspace with the caller.
+pub async fn get_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::RoomsRead)?;
+    let user = workspace_service::lookup_user(&state.pool, auth.claims().user_id()?, user_id).await?;
+    Ok(Json(json!({ "user": user })))
+}
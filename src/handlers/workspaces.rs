@@ -0,0 +1,126 @@
+use axum::{extract::{Path, State}, Json};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::middleware::auth::AuthUser;
+use crate::models::workspace::{ChangeWorkspaceRoleRequest, CreateWorkspaceRequest, InviteWorkspaceMemberRequest, WorkspaceRole};
+use crate::handlers::ws::announce_owner_change;
+use crate::services::workspace_service;
+
+// Workspaces are managed from a logged-in session only, like other account settings.
+
+/// `POST /api/workspaces` — the caller becomes its owner.
+pub async fn create_workspace(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(req): Json<CreateWorkspaceRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let workspace = workspace_service::create_workspace(&state.pool, auth.claims().user_id()?, &req.name, &req.slug).await?;
+    Ok(Json(json!({ "workspace": workspace })))
+}
+
+pub async fn list_workspaces(State(state): State<AppState>, auth: AuthUser) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let workspaces = workspace_service::list_my_workspaces(&state.pool, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "workspaces": workspaces, "active_workspace_id": auth.workspace_id() })))
+}
+
+pub async fn list_members(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(workspace_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let members = workspace_service::list_members(&state.pool, workspace_id, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "members": members })))
+}
+
+/// `POST /api/workspaces/:id/invitations` — the same reply whether or not the
+/// username exists; the invitee joins by accepting.
+pub async fn invite_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(workspace_id): Path<Uuid>,
+    Json(req): Json<InviteWorkspaceMemberRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    workspace_service::invite_member(
+        &state.pool, workspace_id, auth.claims().user_id()?, &req.username, req.role.unwrap_or(WorkspaceRole::Member),
+    ).await?;
+    Ok(Json(json!({ "message": "If that account exists and isn't a member yet, it has been invited" })))
+}
+
+/// `GET /api/workspace-invitations` — your pending workspace invitations.
+pub async fn my_invitations(State(state): State<AppState>, auth: AuthUser) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let invitations = workspace_service::list_my_invitations(&state.pool, auth.claims().user_id()?).await?;
+    Ok(Json(json!({ "invitations": invitations })))
+}
+
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let invitation = workspace_service::respond_to_invitation(&state.pool, auth.claims().user_id()?, invitation_id, true).await?;
+    Ok(Json(json!({ "invitation": invitation })))
+}
+
+pub async fn decline_invitation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let invitation = workspace_service::respond_to_invitation(&state.pool, auth.claims().user_id()?, invitation_id, false).await?;
+    Ok(Json(json!({ "invitation": invitation })))
+}
+
+/// `PUT /api/workspaces/:id/members/:user_id/role`
+pub async fn change_member_role(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<ChangeWorkspaceRoleRequest>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    workspace_service::change_member_role(&state.pool, workspace_id, auth.claims().user_id()?, user_id, req.role).await?;
+    Ok(Json(json!({ "user_id": user_id, "role": req.role.name() })))
+}
+
+/// `DELETE /api/workspaces/:id/members/:user_id` — also how a member leaves.  Their
+/// live subscriptions to the workspace's rooms end with it, and rooms they owned get a
+/// new owner.
+pub async fn remove_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let removal = workspace_service::remove_member(&state.pool, workspace_id, auth.claims().user_id()?, user_id).await?;
+    for room_id in removal.rooms {
+        state.hub.leave_room(room_id, user_id);
+    }
+    for (room_id, change) in removal.owner_changes {
+        announce_owner_change(&state.hub, room_id, change);
+    }
+    Ok(Json(json!({ "message": "Member removed" })))
+}
+
+/// `POST /api/workspaces/:id/switch` — fresh tokens acting in this workspace.
+/// Reconnect the WebSocket with a new ticket to follow the switch.
+pub async fn switch_workspace(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(workspace_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>> {
+    auth.require_session()?;
+    let tokens = workspace_service::switch_workspace(
+        &state.pool, &state.jwt, auth.claims().user_id()?, &auth.claims().username, workspace_id,
+    ).await?;
+    Ok(Json(json!({ "token": tokens })))
+}
@@ -0,0 +1,289 @@
+use axum::{
+    extract::{Query, State, WebSocketUpgrade, ws::WebSocket},
+    http::{header, HeaderMap},
+    response::Response,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::{AppError, Result};
+use crate::config::Config;
+use crate::db::redisdb::WS_TICKET_TTL_SECS;
+use crate::middleware::auth::AuthUser;
+use crate::models::access_token::{Access, Scope};
+use crate::models::session::WsTicket;
+use crate::services::{auth_service, message_service, room_service};
+use crate::repositories::user_repo;
+use crate::websocket::codec::{self, WireFormat};
+use crate::websocket::connection::{run_connection, ConnectionId, ConnectionSettings};
+use crate::websocket::hub::Hub;
+use crate::websocket::protocol::{
+    ClientMessage, ProtocolLimits, ServerMessage, WsUser, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct WsParams {
+    /// Single-use ticket from `POST /api/ws/ticket`.
+    pub ticket:   String,
+    /// Fallback for clients that can't set `Sec-WebSocket-Protocol`.
+    pub encoding: Option<String>,
+    /// Requested protocol version.  Omitted means v1 so existing clients keep working;
+    /// anything newer than we speak is capped at `PROTOCOL_VERSION`.
+    pub version:  Option<u16>,
+}
+
+/// `POST /api/ws/ticket` — trade an access token for a short-lived, single-use ticket,
+/// so the long-lived JWT never appears in a URL (and hence in proxy or access logs).
+pub async fn issue_ticket(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>> {
+    auth.require(Scope::MessagesRead)?;
+    let user_id = auth.claims().user_id()?;
+    let ticket = auth_service::issue_ws_ticket(
+        &state.pool, user_id, &auth.claims().username, auth.workspace_id(), origin(&headers), auth.access(),
+    ).await?;
+    Ok(Json(json!({ "ticket": ticket, "expires_in": WS_TICKET_TTL_SECS })))
+}
+
+fn origin(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::ORIGIN).and_then(|v| v.to_str().ok())
+}
+
+/// `GET /ws?ticket=<ticket>[&encoding=json|msgpack|cbor][&version=N]`
+///
+/// The wire encoding is taken from `Sec-WebSocket-Protocol` when the client offers one we
+/// support, otherwise from the `encoding` query parameter, otherwise JSON.  On v2+ the
+/// first frame is `hello`.
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<WsParams>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    if state.hub.is_shutting_down() {
+        return Err(AppError::ServiceUnavailable("Server is restarting".into()));
+    }
+    let ticket = auth_service::redeem_ws_ticket(&state.pool, &params.ticket, origin(&headers)).await?;
+
+    let requested = match params.encoding.as_deref() {
+        Some(name) => Some(WireFormat::from_name(name)
+            .ok_or_else(|| AppError::BadRequest(format!("Unsupported encoding: {name}")))?),
+        None => None,
+    };
+    let version = params.version.unwrap_or(MIN_PROTOCOL_VERSION);
+    if version < MIN_PROTOCOL_VERSION {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported protocol version {version}; minimum is {MIN_PROTOCOL_VERSION}"
+        )));
+    }
+    let version = version.min(PROTOCOL_VERSION);
+
+    Ok(ws
+        .protocols(codec::SUBPROTOCOLS)
+        .max_message_size(state.config.ws_max_message_size)
+        .on_upgrade(move |socket| handle_socket(socket, state, ticket, requested, version)))
+}
+
+/// Limits advertised in `hello`; the same values drive enforcement.
+fn protocol_limits(cfg: &Config) -> ProtocolLimits {
+    ProtocolLimits {
+        max_message_size:       cfg.ws_max_message_size,
+        max_content_length:     message_service::MAX_CONTENT_LENGTH,
+        rate_limit_messages:    cfg.ws_rate_limit_messages,
+        rate_limit_window_secs: cfg.ws_rate_limit_window_secs,
+    }
+}
+
+fn capabilities() -> Vec<String> {
+    let mut caps: Vec<String> = WireFormat::ALL.iter()
+        .map(|f| format!("encoding:{}", f.name()))
+        .collect();
+    caps.extend(["transport:sse", "rooms", "typing", "dm", "online_users", "room_roles", "moderation", "room_invitations", "room_ownership", "pins", "room_metadata", "posting_policy", "archiving", "workspaces"].map(String::from));
+    caps
+}
+
+/// The `hello` frame for a connection on `version`, shared by every transport.
+pub fn hello(state: &AppState, version: u16) -> ServerMessage {
+    ServerMessage::Hello {
+        protocol_version:        version,
+        server_version:          env!("CARGO_PKG_VERSION").into(),
+        capabilities:            capabilities(),
+        limits:                  protocol_limits(&state.config),
+        heartbeat_interval_secs: state.config.ws_heartbeat_interval_secs,
+    }
+}
+
+async fn handle_socket(
+    socket:    WebSocket,
+    state:     AppState,
+    ticket:    WsTicket,
+    requested: Option<WireFormat>,
+    version:   u16,
+) {
+    let WsTicket { user_id, username, workspace_id, access, .. } = ticket;
+    let format = socket.protocol()
+        .and_then(|p| p.to_str().ok())
+        .and_then(WireFormat::from_name)
+        .or(requested)
+        .unwrap_or_default();
+
+    let (connection_id, rx) = state.hub.register(user_id, username, workspace_id, format, version);
+    let hub = state.hub.clone();
+
+    // Queued before anything else can reach the channel, so it is always the first frame.
+    state.hub.send_to_connection(user_id, connection_id, &hello(&state, version));
+
+    let settings = ConnectionSettings {
+        format,
+        rate_limit_messages: state.config.ws_rate_limit_messages,
+        rate_limit_window:   Duration::from_secs(state.config.ws_rate_limit_window_secs),
+        ping_interval:       Duration::from_secs(state.config.ws_heartbeat_interval_secs),
+        pong_timeout:        Duration::from_secs(state.config.ws_pong_timeout_secs),
+        idle_timeout:        Some(state.config.ws_idle_timeout_secs)
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs),
+    };
+
+    let reason = run_connection(
+        socket,
+        user_id,
+        settings,
+        rx,
+        move |uid, msg| {
+            let state = state.clone();
+            let access = access.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_client_message(&state, uid, workspace_id, Some(connection_id), &access, msg).await {
+                    let (_, code, message) = e.parts();
+                    state.hub.send_to_connection(uid, connection_id, &ServerMessage::Error { code: code.into(), message });
+                }
+            });
+        },
+        {
+            let hub = hub.clone();
+            move |uid| hub.disconnect(uid, connection_id)
+        },
+    )
+    .await;
+    hub.record_disconnect(user_id, reason);
+}
+
+/// Tell the room who owns it now.
+pub fn announce_owner_change(hub: &Hub, room_id: Uuid, change: room_service::OwnerChange) {
+    hub.broadcast_to_room(room_id, &ServerMessage::OwnerChanged {
+        room_id,
+        previous_owner_id: change.previous,
+        new_owner_id:      change.new,
+    }, None);
+}
+
+/// Scope an access token needs to send `msg`.
+fn required_scope(msg: &ClientMessage) -> Option<Scope> {
+    match msg {
+        ClientMessage::JoinRoom { .. } | ClientMessage::LeaveRoom { .. } => Some(Scope::RoomsWrite),
+        ClientMessage::Message { .. } | ClientMessage::Typing { .. } | ClientMessage::Dm { .. } => Some(Scope::MessagesWrite),
+        ClientMessage::Ping => None,
+    }
+}
+
+/// Apply one client frame: persist through the services, then fan out via the hub.
+/// Transport-agnostic; the SSE fallback posts the same frames over HTTP.  Direct replies
+/// go to `connection_id` only, the connection the frame came from; `None` drops them.
+/// `workspace_id` is that connection's workspace.
+pub async fn handle_client_message(
+    state: &AppState,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+    connection_id: Option<ConnectionId>,
+    access: &Access,
+    msg: ClientMessage,
+) -> Result<()> {
+    let reply = |msg: &ServerMessage| {
+        if let Some(connection_id) = connection_id {
+            state.hub.send_to_connection(user_id, connection_id, msg);
+        }
+    };
+    if let Some(scope) = required_scope(&msg) {
+        if !access.allows(scope) {
+            return Err(AppError::Forbidden(format!("Token is missing the {} scope", scope.name())));
+        }
+    }
+    match msg {
+        ClientMessage::JoinRoom { room_id } => {
+            let room = room_service::join_room(&state.pool, room_id, user_id, workspace_id).await?;
+            let user = user_repo::get_user_by_id(&state.pool.pg, user_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+            if !state.hub.join_room(room_id, room.workspace_id, user_id, &user.username, user.display_name.as_deref()) {
+                return Err(AppError::Forbidden("Switch to this room's workspace to join it".into()));
+            }
+            if room.rules.is_some() || room.topic.is_some() {
+                reply(&ServerMessage::RoomWelcome {
+                    room_id,
+                    topic: room.topic,
+                    rules: room.rules,
+                });
+            }
+        }
+        ClientMessage::LeaveRoom { room_id, successor_id } => {
+            let change = room_service::leave_room(&state.pool, room_id, user_id, successor_id).await?;
+            state.hub.leave_room(room_id, user_id);
+            if let Some(change) = change {
+                announce_owner_change(&state.hub, room_id, change);
+            }
+        }
+        ClientMessage::Message { room_id, content } => {
+            let msg = message_service::send_message(&state.pool, user_id, room_id, &content).await?;
+            let event = message_service::build_message_event(&state.pool, &msg).await?;
+            state.hub.broadcast_to_room(room_id, &ServerMessage::Message {
+                message_id: event.message_id,
+                room_id:    event.room_id,
+                user: WsUser {
+                    id:           event.user.id,
+                    username:     event.user.username,
+                    display_name: event.user.display_name,
+                },
+                content:   event.content,
+                timestamp: event.timestamp,
+            }, None);
+        }
+        ClientMessage::Typing { room_id, is_typing } => {
+            message_service::ensure_can_type(&state.pool, user_id, room_id).await?;
+            let user = user_repo::get_user_by_id(&state.pool.pg, user_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+            state.hub.broadcast_to_room(room_id, &ServerMessage::Typing {
+                room_id,
+                user_id,
+                username: user.username,
+                is_typing,
+            }, Some(user_id));
+        }
+        ClientMessage::Dm { recipient_id, content } => {
+            let workspace_id = workspace_id
+                .ok_or_else(|| AppError::Forbidden("No active workspace; create or switch to one first".into()))?;
+            let dm = message_service::send_dm(&state.pool, user_id, recipient_id, workspace_id, &content).await?;
+            let sender = user_repo::get_user_by_id(&state.pool.pg, user_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+            state.hub.send_to_user(recipient_id, workspace_id, &ServerMessage::Dm {
+                from: WsUser {
+                    id:           sender.id,
+                    username:     sender.username,
+                    display_name: sender.display_name,
+                },
+                content:   dm.content,
+                timestamp: dm.created_at,
+            });
+        }
+        ClientMessage::Ping => reply(&ServerMessage::Pong),
+    }
+    Ok(())
+}
+
@@ -1,18 +1,19 @@
 use tracing_subscriber::{fmt, layer::{SubscriberExt, Layer}, util::SubscriberInitExt};
 use tracing_subscriber::EnvFilter;
-use tracing_appender::rolling;
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
 
-pub fn init_logging() {
+/// Flushes the non-blocking log writers when dropped; hold it until the process exits.
+pub struct LogGuards {
+    _guards: Vec<WorkerGuard>,
+}
+
+pub fn init_logging() -> LogGuards {
     // FILE APPENDERS 
     let access_file = rolling::daily("logs", "access.log");
     let app_file = rolling::daily("logs", "application.log");
 
-    let (access_writer, _access_guard) = tracing_appender::non_blocking(access_file);
-    let (app_writer, _app_guard) = tracing_appender::non_blocking(app_file);
-
-    // IMPORTANT: guards must live forever
-    std::mem::forget(_access_guard);
-    std::mem::forget(_app_guard);
+    let (access_writer, access_guard) = tracing_appender::non_blocking(access_file);
+    let (app_writer, app_guard) = tracing_appender::non_blocking(app_file);
 
     // ACCESS LOG LAYER 
     let access_layer = fmt::layer()
@@ -40,4 +41,7 @@ pub fn init_logging() {
         .with(access_layer)
         .with(app_layer)
         .init();
+
+    // IMPORTANT: guards must live until shutdown, or buffered lines are lost
+    LogGuards { _guards: vec![access_guard, app_guard] }
 }
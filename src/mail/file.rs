@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{Email, Mailer};
+use crate::error::{AppError, Result};
+
+/// Writes every message to `<dir>/<timestamp>-<id>.eml`.
+pub struct FileMailer {
+    dir:  PathBuf,
+    from: String,
+}
+
+impl FileMailer {
+    pub fn new(dir: &str, from: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::Internal(format!("MAIL_DIR {dir}: {e}")))?;
+        Ok(FileMailer { dir: dir.into(), from: from.to_string() })
+    }
+}
+
+#[axum::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let now = Utc::now();
+        let path = self.dir.join(format!("{}-{}.eml", now.format("%Y%m%dT%H%M%S%.3f"), Uuid::new_v4()));
+        let contents = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.from, email.to, email.subject, now.to_rfc2822(), email.body,
+        );
+        tokio::fs::write(&path, contents).await
+            .map_err(|e| AppError::Internal(format!("Write {}: {e}", path.display())))?;
+        Ok(())
+    }
+}
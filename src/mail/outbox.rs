@@ -0,0 +1,28 @@
+use sqlx::PgPool;
+
+use super::{Email, Mailer};
+use crate::error::Result;
+
+/// Records messages in `mail_outbox` instead of sending them.
+pub struct OutboxMailer {
+    pool: PgPool,
+}
+
+impl OutboxMailer {
+    pub fn new(pool: PgPool) -> Self {
+        OutboxMailer { pool }
+    }
+}
+
+#[axum::async_trait]
+impl Mailer for OutboxMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        sqlx::query("INSERT INTO mail_outbox (recipient, subject, body) VALUES ($1, $2, $3)")
+            .bind(&email.to)
+            .bind(&email.subject)
+            .bind(&email.body)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::{Email, Mailer};
+use crate::error::{AppError, Result};
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from:      Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(url: &str, from: &str) -> Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+            .map_err(|e| AppError::Internal(format!("SMTP_URL: {e}")))?
+            .build();
+        let from = from.parse()
+            .map_err(|e| AppError::Internal(format!("MAIL_FROM: {e}")))?;
+        Ok(SmtpMailer { transport, from })
+    }
+}
+
+#[axum::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let to: Mailbox = email.to.parse()
+            .map_err(|e| AppError::BadRequest(format!("Invalid recipient: {e}")))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())
+            .map_err(|e| AppError::Internal(format!("Build mail: {e}")))?;
+        self.transport.send(message).await
+            .map_err(|e| AppError::Internal(format!("SMTP: {e}")))?;
+        Ok(())
+    }
+}
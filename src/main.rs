@@ -8,6 +8,8 @@ mod services;
 mod utils;
 mod config;
 mod logging;
+mod mail;
+mod oidc;
 mod middleware;
 
 use axum::{
@@ -19,21 +21,32 @@ use tower_http::cors::{CorsLayer, Any};
 use tower_http::trace::TraceLayer;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use db::DbPool;
 use websocket::hub::Hub;
+use websocket::sse::SseSessions;
 use config::Config;
+use mail::Mailer;
+use utils::jwt::JwtKeys;
+use oidc::OidcProviders;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: DbPool,
-    pub hub: Hub
+    pub hub: Hub,
+    pub sse: SseSessions,
+    pub mailer: Arc<dyn Mailer>,
+    pub jwt: Arc<JwtKeys>,
+    pub oidc: Arc<OidcProviders>,
+    pub config: Arc<Config>,
 }
 
 #[tokio::main]
 async fn main() {
     let _ = dotenv::dotenv();
-    logging::init_logging();
+    let log_guards = logging::init_logging();
 
     // let env_filter = EnvFilter::try_from_default_env()
     //     .unwrap_or_else(|_| EnvFilter::new("info"));
@@ -45,6 +58,18 @@ async fn main() {
     let cfg = Config::from_env().expect("Failed to load config");
     tracing::info!("Starting rust-chat-server on {}:{}", cfg.host, cfg.port);
 
+    let jwt = JwtKeys::from_config(&cfg).expect("Invalid JWT configuration");
+    let oidc = OidcProviders::new(&cfg.oidc_providers).expect("Failed to set up OIDC providers");
+
+    utils::password::init(
+        utils::password::PasswordPolicy {
+            memory_kib:  cfg.argon2_memory_kib,
+            iterations:  cfg.argon2_iterations,
+            parallelism: cfg.argon2_parallelism,
+        },
+        cfg.password_hash_concurrency,
+    ).expect("Invalid password hashing parameters");
+
     let pg = db::posgresql::create_pool(&cfg.database_url)
         .await
         .expect("Failed to connect to postgres database");
@@ -52,19 +77,106 @@ async fn main() {
     let redis = db::redisdb::create_connection_manager(&cfg.redis_url).await
         .expect("Failed to connect to redis");
     
+    let mailer = mail::build_mailer(&cfg, &pg).expect("Failed to set up mail transport");
+
+    let hub = Hub::new();
+    let sse = SseSessions::new(hub.clone(), cfg.sse_backlog, Duration::from_secs(cfg.sse_resume_window_secs));
+
+    spawn_room_purge(DbPool { pg: pg.clone(), redis: redis.clone() }, Duration::from_secs(cfg.room_purge_interval_secs));
+
+    let hub_handle = hub.clone();
     let state = AppState {
         pool: DbPool { 
             pg,
             redis
         },
-        hub: Hub::new()
+        hub,
+        sse,
+        mailer,
+        jwt: Arc::new(jwt),
+        oidc: Arc::new(oidc),
+        config: Arc::new(cfg.clone()),
     };
 
     let app = Router::new()
         .route("/health", get(health_check))
+        .route("/metrics", get(metrics))
+        .route("/.well-known/jwks.json", get(handlers::auth::jwks))
 
         .route("/api/auth/register", post(handlers::auth::register))
         .route("/api/auth/login", post(handlers::auth::login))
+        .route("/api/auth/verify-email", post(handlers::auth::verify_email))
+        .route("/api/auth/verify-email/resend", post(handlers::auth::resend_verification))
+        .route("/api/auth/password/forgot", post(handlers::auth::forgot_password))
+        .route("/api/auth/password/reset", post(handlers::auth::reset_password))
+        .route("/api/auth/oidc/providers", get(handlers::auth::oidc_providers))
+        .route("/api/auth/oidc/:provider/authorize", get(handlers::auth::oidc_authorize))
+        .route("/api/auth/oidc/:provider/callback", get(handlers::auth::oidc_callback))
+        .route("/api/auth/2fa/enroll", post(handlers::auth::enroll_totp))
+        .route("/api/auth/2fa/confirm", post(handlers::auth::confirm_totp))
+        .route("/api/auth/2fa/verify", post(handlers::auth::verify_totp))
+        .route("/api/auth/2fa/disable", post(handlers::auth::disable_totp))
+
+        .route("/api/bots", post(handlers::access_tokens::create_bot).get(handlers::access_tokens::list_bots))
+        .route("/api/tokens", post(handlers::access_tokens::create_token).get(handlers::access_tokens::list_tokens))
+        .route("/api/tokens/:id", delete(handlers::access_tokens::revoke_token))
+
+        .route("/api/workspaces", post(handlers::workspaces::create_workspace).get(handlers::workspaces::list_workspaces))
+        .route("/api/workspaces/:id/members", get(handlers::workspaces::list_members))
+        .route("/api/workspaces/:id/invitations", post(handlers::workspaces::invite_member))
+        .route("/api/workspaces/:id/members/:user_id", delete(handlers::workspaces::remove_member))
+        .route("/api/workspaces/:id/members/:user_id/role", put(handlers::workspaces::change_member_role))
+        .route("/api/workspaces/:id/switch", post(handlers::workspaces::switch_workspace))
+        .route("/api/workspace-invitations", get(handlers::workspaces::my_invitations))
+        .route("/api/workspace-invitations/:id/accept", post(handlers::workspaces::accept_invitation))
+        .route("/api/workspace-invitations/:id/decline", post(handlers::workspaces::decline_invitation))
+        .route("/api/users/search", get(handlers::users::search_users))
+        .route("/api/users/:id", get(handlers::users::get_user))
+
+        .route("/api/rooms", post(handlers::rooms::create_room).get(handlers::rooms::list_rooms))
+        .route("/api/rooms/:id", get(handlers::rooms::get_room).put(handlers::rooms::update_room).delete(handlers::rooms::delete_room))
+        .route("/api/rooms/mine", get(handlers::rooms::my_rooms))
+        .route("/api/room-tags", get(handlers::rooms::list_tags))
+        .route("/api/rooms/:id/tags", put(handlers::rooms::set_room_tags))
+        .route("/api/rooms/:id/archive", post(handlers::rooms::archive_room))
+        .route("/api/rooms/:id/unarchive", post(handlers::rooms::unarchive_room))
+        .route("/api/rooms/:id/leave", post(handlers::rooms::leave_room))
+        .route("/api/rooms/:id/transfer-ownership", post(handlers::rooms::transfer_ownership))
+        .route("/api/rooms/:id/posting-policy", get(handlers::rooms::get_posting_policy).put(handlers::rooms::set_posting_policy))
+        .route("/api/rooms/:id/members", get(handlers::rooms::list_members))
+        .route("/api/rooms/:id/members/:user_id/role", put(handlers::rooms::change_member_role))
+        .route("/api/rooms/:id/members/:user_id/kick", post(handlers::moderation::kick))
+        .route("/api/rooms/:id/bans", get(handlers::moderation::list_bans))
+        .route("/api/rooms/:id/bans/:user_id", put(handlers::moderation::ban).delete(handlers::moderation::unban))
+        .route("/api/rooms/:id/mutes", get(handlers::moderation::list_mutes))
+        .route("/api/rooms/:id/mutes/:user_id", put(handlers::moderation::mute).delete(handlers::moderation::unmute))
+        .route("/api/rooms/:id/invitations", post(handlers::room_invites::invite_user).get(handlers::room_invites::list_room_invitations))
+        .route("/api/rooms/:id/invitations/:invitation_id", delete(handlers::room_invites::revoke_invitation))
+        .route("/api/rooms/:id/links", post(handlers::room_invites::create_link).get(handlers::room_invites::list_links))
+        .route("/api/rooms/:id/links/:link_id", delete(handlers::room_invites::revoke_link))
+        .route("/api/rooms/:id/join-requests", post(handlers::room_invites::request_to_join).get(handlers::room_invites::list_join_requests))
+        .route("/api/rooms/:id/join-requests/:request_id/approve", post(handlers::room_invites::approve_join_request))
+        .route("/api/rooms/:id/join-requests/:request_id/reject", post(handlers::room_invites::reject_join_request))
+        .route("/api/invitations", get(handlers::room_invites::my_invitations))
+        .route("/api/invitations/:id/accept", post(handlers::room_invites::accept_invitation))
+        .route("/api/invitations/:id/decline", post(handlers::room_invites::decline_invitation))
+        .route("/api/room-links/redeem", post(handlers::room_invites::redeem_link))
+        .route("/api/rooms/:id/permissions", get(handlers::rooms::get_permissions))
+        .route("/api/rooms/:id/permissions/:role", put(handlers::rooms::set_role_permissions).delete(handlers::rooms::reset_role_permissions))
+        .route("/api/rooms/:id/messages", get(handlers::rooms::list_messages))
+        .route("/api/rooms/:id/pins", get(handlers::pins::list_pins))
+        .route("/api/rooms/:id/pins/:message_id", put(handlers::pins::pin_message).delete(handlers::pins::unpin_message))
+        .route("/api/messages/:id", delete(handlers::rooms::delete_message))
+
+        .route("/api/invites", post(handlers::invites::create_invite).get(handlers::invites::list_invites))
+        .route("/api/invites/:id", delete(handlers::invites::revoke_invite))
+
+        .route("/api/admin/lockouts/clear", post(handlers::admin::clear_lockout))
+        .route("/api/admin/audit-events", get(handlers::admin::audit_events))
+
+        .route("/ws", get(handlers::ws::ws_handler))
+        .route("/api/ws/ticket", post(handlers::ws::issue_ticket))
+        .route("/api/events", get(handlers::events::stream).post(handlers::events::send))
 
         .layer(TraceLayer::new_for_http())
         .layer(
@@ -90,12 +202,75 @@ async fn main() {
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     tracing::info!("Listening on http://{addr}");
-    tracing::info!("WebSocket: ws://{addr}/ws?token=<jwt>");
+    tracing::info!("WebSocket: ws://{addr}/ws?ticket=<ticket>[&encoding=json|msgpack|cbor][&version=N]");
+    tracing::info!("Events:    http://{addr}/api/events (SSE fallback)");
     tracing::info!("Health:    http://{addr}/health");
 
-    axum::serve(listener, app).await.unwrap();
+    let grace = Duration::from_secs(cfg.shutdown_grace_secs);
+    // Peer addresses feed the per-IP login lockout.
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal(hub_handle, grace))
+        .await
+        .unwrap();
+
+    tracing::info!("Shutdown complete");
+    drop(log_guards);
+}
+
+/// Periodically hard-delete rooms whose deletion delay has passed.
+fn spawn_room_purge(pool: DbPool, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            match services::room_service::purge_due_rooms(&pool).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Room purge removed {n} rooms"),
+                Err(e) => tracing::error!("Room purge failed: {e}"),
+            }
+        }
+    });
+}
+
+/// Resolves once SIGINT/SIGTERM has been received and live connections have been
+/// asked to reconnect elsewhere and given `grace` to drain.  axum then stops
+/// accepting and finishes in-flight HTTP requests.
+async fn shutdown_signal(hub: Hub, grace: Duration) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Failed to install Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received, draining connections for up to {grace:?}");
+    hub.begin_shutdown(axum::extract::ws::close_code::RESTART, "server restarting, reconnect");
+
+    let deadline = tokio::time::Instant::now() + grace;
+    while hub.stats().connections > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let remaining = hub.stats().connections;
+    if remaining > 0 {
+        tracing::warn!("Grace period over with {remaining} connections still open");
+    }
 }
 
 async fn health_check() -> axum::Json<serde_json::Value> {
     axum::Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn metrics(axum::extract::State(state): axum::extract::State<AppState>) -> axum::Json<serde_json::Value> {
+    axum::Json(serde_json::json!({ "hub": state.hub.stats() }))
 }
\ No newline at end of file
@@ -1,17 +1,57 @@
 use axum::{
-    body::Body,
-    extract::FromRequest,
-    http::{self, HeaderMap, Request},
+    extract::{FromRef, FromRequestParts},
+    http::{self, HeaderMap, request::Parts},
 };
+use uuid::Uuid;
 
+use crate::AppState;
 use crate::error::{AppError, Result};
+use crate::models::access_token::{Access, Scope};
+use crate::services::{access_token_service, auth_service};
 use crate::utils::jwt::{self, Claims};
 
-pub struct AuthUser(pub Claims);
+/// The authenticated caller: a logged-in user (JWT) or a personal access token.
+pub struct AuthUser {
+    claims: Claims,
+    access: Access,
+}
 
 impl AuthUser {
     pub fn claims(&self) -> &Claims {
-        &self.0
+        &self.claims
+    }
+
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+
+    /// The session's active workspace, if it has one.
+    pub fn workspace_id(&self) -> Option<Uuid> {
+        self.claims.workspace_id
+    }
+
+    /// The active workspace, for endpoints that only make sense inside one.  Services
+    /// still check the caller is a member: the claim may predate a removal.
+    pub fn require_workspace(&self) -> Result<Uuid> {
+        self.claims.workspace_id
+            .ok_or_else(|| AppError::Forbidden("No active workspace; create or switch to one first".into()))
+    }
+
+    /// Fail with 403 unless the caller may use `scope`.  Sessions may use every scope.
+    pub fn require(&self, scope: Scope) -> Result<()> {
+        if self.access.allows(scope) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(format!("Token is missing the {} scope", scope.name())))
+        }
+    }
+
+    /// Account and credential management is off limits to access tokens.
+    pub fn require_session(&self) -> Result<()> {
+        match self.access {
+            Access::Session => Ok(()),
+            Access::Token { .. } => Err(AppError::Forbidden("This endpoint requires a logged-in session".into())),
+        }
     }
 }
 
@@ -28,20 +68,40 @@ fn extract_bearer(header: &HeaderMap) -> Result<&str> {
 
 
 #[axum::async_trait]
-impl<S> FromRequest<S> for AuthUser
-where S: Send + Sync
+impl<S> FromRequestParts<S> for AuthUser
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
 {
     type Rejection = AppError;
 
-    async fn from_request(req: Request<Body>, _state: &S) -> std::result::Result<Self, Self::Rejection> {
-        let header = req.headers();
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> std::result::Result<Self, Self::Rejection> {
+        let header = &parts.headers;
         let token = extract_bearer(header)?;
-        let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".into());
-        let claims = jwt::verify_token(token, &secret)?;
+        let state = AppState::from_ref(state);
+
+        if token.starts_with(access_token_service::TOKEN_PREFIX) {
+            let (user, pat) = access_token_service::authenticate(&state.pool, token).await?;
+            // Same shape as a session so handlers don't care how the caller signed in.
+            let claims = Claims {
+                sub:        user.id.to_string(),
+                username:   user.username,
+                exp:        pat.expires_at.map(|t| t.timestamp()).unwrap_or(i64::MAX),
+                iat:        pat.created_at.timestamp(),
+                iat_ms:     pat.created_at.timestamp_millis(),
+                token_type: "pat".into(),
+                workspace_id: Some(pat.workspace_id),
+            };
+            let access = Access::Token { token_id: pat.id, scopes: pat.scopes() };
+            return Ok(AuthUser { claims, access });
+        }
+
+        let claims = jwt::verify_token(&state.jwt, token)?;
 
         if claims.token_type != "access" {
             return Err(AppError::Unauthorized("Not a valid access token".into()));
         }
-        Ok(AuthUser(claims))
+        auth_service::ensure_not_revoked(&state.pool, &claims).await?;
+        Ok(AuthUser { claims, access: Access::Session })
     }
 }
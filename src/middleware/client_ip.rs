@@ -0,0 +1,48 @@
+use std::net::{IpAddr, SocketAddr};
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{HeaderMap, request::Parts},
+};
+
+use crate::AppState;
+use crate::error::AppError;
+
+/// The caller's address: the peer of the TCP connection, or the address a trusted
+/// reverse proxy reports (`TRUST_PROXY_HEADERS`).  `None` when neither is known.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl ClientIp {
+    pub fn to_string_opt(self) -> Option<String> {
+        self.0.map(|ip| ip.to_string())
+    }
+}
+
+/// The left-most X-Forwarded-For entry is the original client; fall back to X-Real-IP.
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    header("x-forwarded-for")
+        .and_then(|v| v.split(',').next())
+        .or_else(|| header("x-real-ip"))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> std::result::Result<Self, Self::Rejection> {
+        let state = AppState::from_ref(state);
+        if state.config.trust_proxy_headers {
+            if let Some(ip) = forwarded_for(&parts.headers) {
+                return Ok(ClientIp(Some(ip)));
+            }
+        }
+        let peer = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+        Ok(ClientIp(peer))
+    }
+}
@@ -0,0 +1,40 @@
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+};
+use serde::de::DeserializeOwned;
+
+use crate::error::AppError;
+
+/// A JSON body that may be left out.  Unlike `Option<Json<T>>`, only an empty body
+/// counts as absent: malformed JSON or a field of the wrong type is a 400, never a
+/// silent fall back to the defaults.
+#[derive(Debug, Clone)]
+pub struct OptionalJson<T>(pub Option<T>);
+
+impl<T: Default> OptionalJson<T> {
+    pub fn or_default(self) -> T {
+        self.0.unwrap_or_default()
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for OptionalJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> std::result::Result<Self, Self::Rejection> {
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Could not read request body: {e}")))?;
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return Ok(OptionalJson(None));
+        }
+        serde_json::from_slice(&bytes)
+            .map(|value| OptionalJson(Some(value)))
+            .map_err(|e| AppError::BadRequest(format!("Invalid JSON body: {e}")))
+    }
+}
@@ -0,0 +1,127 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What a personal access token may do.  Interactive sessions are not scoped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "rooms:read")]
+    RoomsRead,
+    #[serde(rename = "rooms:write")]
+    RoomsWrite,
+    /// Also covers opening `/ws` or `/api/events` to receive messages.
+    #[serde(rename = "messages:read")]
+    MessagesRead,
+    #[serde(rename = "messages:write")]
+    MessagesWrite,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::RoomsRead, Scope::RoomsWrite, Scope::MessagesRead, Scope::MessagesWrite];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scope::RoomsRead     => "rooms:read",
+            Scope::RoomsWrite    => "rooms:write",
+            Scope::MessagesRead  => "messages:read",
+            Scope::MessagesWrite => "messages:write",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Scope::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+/// How a request was authenticated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Access {
+    /// A JWT from logging in: everything the user can do.
+    Session,
+    /// A personal access token limited to these scopes.
+    Token { token_id: Uuid, scopes: Vec<Scope> },
+}
+
+impl Access {
+    pub fn allows(&self, scope: Scope) -> bool {
+        match self {
+            Access::Session => true,
+            Access::Token { scopes, .. } => scopes.contains(&scope),
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PersonalAccessToken {
+    pub id:            Uuid,
+    pub user_id:       Uuid,
+    pub workspace_id:  Uuid,
+    pub name:          String,
+    pub token_prefix:  String,
+    pub scopes:        Vec<String>,
+    pub expires_at:    Option<DateTime<Utc>>,
+    pub last_used_at:  Option<DateTime<Utc>>,
+    pub revoked_at:    Option<DateTime<Utc>>,
+    pub created_at:    DateTime<Utc>,
+}
+
+impl PersonalAccessToken {
+    /// Stored scopes we still recognise.
+    pub fn scopes(&self) -> Vec<Scope> {
+        self.scopes.iter().filter_map(|s| Scope::from_name(s)).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessTokenResponse {
+    pub id:            Uuid,
+    pub user_id:       Uuid,
+    pub workspace_id:  Uuid,
+    pub name:          String,
+    pub token_prefix:  String,
+    pub scopes:        Vec<Scope>,
+    pub expires_at:    Option<DateTime<Utc>>,
+    pub last_used_at:  Option<DateTime<Utc>>,
+    pub revoked_at:    Option<DateTime<Utc>>,
+    pub created_at:    DateTime<Utc>,
+}
+
+impl From<PersonalAccessToken> for AccessTokenResponse {
+    fn from(t: PersonalAccessToken) -> Self {
+        Self {
+            scopes:        t.scopes(),
+            id:            t.id,
+            user_id:       t.user_id,
+            workspace_id:  t.workspace_id,
+            name:          t.name,
+            token_prefix:  t.token_prefix,
+            expires_at:    t.expires_at,
+            last_used_at:  t.last_used_at,
+            revoked_at:    t.revoked_at,
+            created_at:    t.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAccessTokenRequest {
+    pub name:             String,
+    pub scopes:           Vec<Scope>,
+    /// Omit for a token that never expires.
+    pub expires_in_days:  Option<i64>,
+    /// Issue the token for one of your bots instead of yourself.
+    pub bot_id:           Option<Uuid>,
+    /// The workspace the token acts in; defaults to your active one.
+    pub workspace_id:     Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListAccessTokensParams {
+    pub bot_id:  Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBotRequest {
+    pub username:      String,
+    pub display_name:  Option<String>,
+}
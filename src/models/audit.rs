@@ -0,0 +1,28 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AuditEvent {
+    pub id:              Uuid,
+    pub action:          String,
+    pub actor_id:        Option<Uuid>,
+    pub target_user_id:  Option<Uuid>,
+    pub ip:              Option<String>,
+    pub details:         sqlx::types::Json<serde_json::Value>,
+    pub created_at:      DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventsParams {
+    pub action:          Option<String>,
+    pub target_user_id:  Option<Uuid>,
+    pub limit:           Option<i64>,
+}
+
+/// Clear failed-login state for an account, an address, or both.
+#[derive(Debug, Deserialize)]
+pub struct ClearLockoutRequest {
+    pub email:  Option<String>,
+    pub ip:     Option<String>,
+}
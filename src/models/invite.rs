@@ -0,0 +1,27 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct InviteCode {
+    pub id:           Uuid,
+    pub code_prefix:  String,
+    pub created_by:   Uuid,
+    pub max_uses:     Option<i32>,
+    pub uses:         i32,
+    pub room_ids:     Vec<Uuid>,
+    pub expires_at:   Option<DateTime<Utc>>,
+    pub revoked_at:   Option<DateTime<Utc>>,
+    pub created_at:   DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteRequest {
+    /// Omit for unlimited uses.
+    pub max_uses:         Option<i32>,
+    /// Omit for a code that never expires.
+    pub expires_in_hours: Option<i64>,
+    /// Rooms joined automatically by whoever signs up with the code.
+    #[serde(default)]
+    pub room_ids:         Vec<Uuid>,
+}
@@ -8,13 +8,15 @@ use serde::{Deserialize, Serialize};
 pub struct Message {
     pub id:             Uuid,
     pub room_id:        Uuid,
+    #[sqlx(rename = "user_id")]
     pub sender_id:      Uuid,
     pub content:        String,
     pub message_type:   String,
+    #[sqlx(rename = "metadata")]
     pub meta_data:      Option<Value>,
     // pub is_read:        bool,
     pub created_at:     DateTime<Utc>,
-    pub update_at:      DateTime<Utc>,
+    pub updated_at:     DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, sqlx::FromRow,)]
@@ -53,4 +55,6 @@ pub struct SendMessageRequest {
 pub struct PaginationParams {
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+    /// Only messages containing this text, case-insensitively.
+    pub q: Option<String>,
 }
\ No newline at end of file
@@ -1,4 +1,11 @@
 pub mod user;
 pub mod room;
 pub mod message;
-pub mod session;
\ No newline at end of file
+pub mod session;
+pub mod access_token;
+pub mod audit;
+pub mod invite;
+pub mod moderation;
+pub mod room_invite;pub mod pin;
+pub mod posting_policy;
+pub mod workspace;
@@ -0,0 +1,39 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RoomBan {
+    pub room_id:     Uuid,
+    pub user_id:     Uuid,
+    pub banned_by:   Option<Uuid>,
+    pub reason:      Option<String>,
+    pub expires_at:  Option<DateTime<Utc>>,
+    pub created_at:  DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RoomMute {
+    pub room_id:     Uuid,
+    pub user_id:     Uuid,
+    pub muted_by:    Option<Uuid>,
+    pub reason:      Option<String>,
+    pub expires_at:  Option<DateTime<Utc>>,
+    pub created_at:  DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KickRequest {
+    pub reason:  Option<String>,
+}
+
+/// Body for bans and mutes.  Unknown fields are rejected so a misspelled
+/// `duration_secs` can't turn into a permanent sanction.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SanctionRequest {
+    pub reason:         Option<String>,
+    /// Omit for a sanction that lasts until lifted.
+    pub duration_secs:  Option<i64>,
+}
@@ -0,0 +1,24 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::models::message::Message;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RoomPin {
+    pub message_id:  Uuid,
+    pub room_id:     Uuid,
+    pub pinned_by:   Option<Uuid>,
+    pub created_at:  DateTime<Utc>,
+}
+
+/// A pin together with the message it points at, as listed by `GET /api/rooms/:id/pins`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PinnedMessage {
+    #[sqlx(flatten)]
+    pub message:             Message,
+    /// `None` once the pinning account is gone.
+    pub pinned_by:           Option<Uuid>,
+    pub pinned_by_username:  Option<String>,
+    pub pinned_at:           DateTime<Utc>,
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::room::RoomRole;
+
+/// How a room throttles and filters posts.  Also the body of `PUT /api/rooms/:id/posting-policy`,
+/// where omitted fields take their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[serde(default)]
+pub struct PostingPolicy {
+    /// Seconds between a member's messages; 0 disables slow mode.
+    pub slow_mode_secs:      i32,
+    /// Announcement / read-only mode: only this role and above may post.
+    pub min_post_role:       Option<String>,
+    /// Tighter than the server-wide limit; `None` uses that.
+    pub max_message_length:  Option<i32>,
+    pub allow_links:         bool,
+}
+
+impl Default for PostingPolicy {
+    fn default() -> Self {
+        PostingPolicy {
+            slow_mode_secs:     0,
+            min_post_role:      None,
+            max_message_length: None,
+            allow_links:        true,
+        }
+    }
+}
+
+impl PostingPolicy {
+    pub fn min_post_role(&self) -> Option<RoomRole> {
+        self.min_post_role.as_deref().and_then(RoomRole::from_name)
+    }
+}
@@ -1,24 +1,140 @@
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct Room {
     pub id:             Uuid,
     pub name:           String,
-    pub description:    String,
+    pub description:    Option<String>,
     pub is_private:     bool,
     pub created_at:     DateTime<Utc>,
-    pub update_at:      DateTime<Utc>,
-    pub created_by:     Uuid
+    pub updated_at:     DateTime<Utc>,
+    pub created_by:     Option<Uuid>,
+    pub topic:          Option<String>,
+    /// An http(s) URL the client loads directly.  There is no upload store in this
+    /// server, so the image is hosted wherever the client put it.
+    pub avatar_url:     Option<String>,
+    pub rules:          Option<String>,
+    pub settings:       serde_json::Value,
+    /// Archived rooms are read-only and left out of listings.
+    pub archived_at:    Option<DateTime<Utc>>,
+    pub archived_by:    Option<Uuid>,
+    /// Set once the owner deletes the room; the purge job removes it after this.
+    pub purge_after:    Option<DateTime<Utc>>,
+    pub workspace_id:   Uuid,
+}
+
+impl Room {
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct RoomMember {
-    pub rood_id:    Uuid,
+    pub room_id:    Uuid,
     pub user_id:    Uuid,
     pub role:       String,
-    pub join_at:    DateTime<Utc>,
+    pub joined_at:  DateTime<Utc>,
+}
+
+impl RoomMember {
+    /// Unknown roles (there shouldn't be any) get guest rights.
+    pub fn role(&self) -> RoomRole {
+        RoomRole::from_name(&self.role).unwrap_or(RoomRole::Guest)
+    }
+}
+
+/// A member's standing in a room, highest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoomRole {
+    Owner,
+    Admin,
+    Moderator,
+    Member,
+    /// Can read, can't post.
+    Guest,
+}
+
+impl RoomRole {
+    pub const ALL: [RoomRole; 5] = [RoomRole::Owner, RoomRole::Admin, RoomRole::Moderator, RoomRole::Member, RoomRole::Guest];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RoomRole::Owner => "owner",
+            RoomRole::Admin => "admin",
+            RoomRole::Moderator => "moderator",
+            RoomRole::Member => "member",
+            RoomRole::Guest => "guest",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        RoomRole::ALL.into_iter().find(|r| r.name() == name)
+    }
+
+    /// Higher outranks lower; you can only manage members ranked below you.
+    pub fn rank(self) -> u8 {
+        match self {
+            RoomRole::Owner => 4,
+            RoomRole::Admin => 3,
+            RoomRole::Moderator => 2,
+            RoomRole::Member => 1,
+            RoomRole::Guest => 0,
+        }
+    }
+
+    pub fn default_permissions(self) -> Permissions {
+        match self {
+            RoomRole::Owner | RoomRole::Admin => Permissions::all(),
+            RoomRole::Moderator => Permissions::all().difference(Permissions::MANAGE_ROOM),
+            RoomRole::Member => Permissions::SEND | Permissions::INVITE,
+            RoomRole::Guest => Permissions::empty(),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// What a role may do in a room.  Stored as a BIGINT in `room_role_permissions`,
+    /// exchanged over the API as a list of names.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Permissions: i64 {
+        const SEND          = 1 << 0;
+        const DELETE_OTHERS = 1 << 1;
+        const KICK          = 1 << 2;
+        const BAN           = 1 << 3;
+        const PIN           = 1 << 4;
+        const INVITE        = 1 << 5;
+        const MANAGE_ROOM   = 1 << 6;
+    }
+}
+
+impl Permissions {
+    pub fn names(self) -> Vec<String> {
+        self.iter_names().map(|(name, _)| name.to_ascii_lowercase()).collect()
+    }
+
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Option<Self> {
+        names.iter().try_fold(Permissions::empty(), |acc, name| {
+            Permissions::from_name(&name.as_ref().to_ascii_uppercase()).map(|p| acc | p)
+        })
+    }
+}
+
+impl Serialize for Permissions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.names().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Permissions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        Permissions::from_names(&names)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown permission in {names:?}")))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,9 +144,95 @@ pub struct CreateRoomRequest {
     pub is_private:     Option<bool>
 }
 
+/// Omitted fields are left alone; an empty string clears a text field.
 #[derive(Debug, Deserialize)]
 pub struct UpdateRoomRequest {
     pub name:           Option<String>,
     pub description:    Option<String>,
     pub is_private:     Option<bool>,
-}
\ No newline at end of file
+    pub topic:          Option<String>,
+    pub avatar_url:     Option<String>,
+    pub rules:          Option<String>,
+    /// Replaces the whole settings object.
+    pub settings:       Option<serde_json::Value>,
+}
+
+/// Query for `GET /api/rooms` and `GET /api/rooms/mine`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListRoomsParams {
+    pub limit:             Option<i64>,
+    pub include_archived:  Option<bool>,
+    /// Matched against name, description and topic.
+    pub q:                 Option<String>,
+    pub tag:               Option<String>,
+    #[serde(default)]
+    pub sort:              RoomSort,
+    /// `next_cursor` from the previous page.
+    pub cursor:            Option<String>,
+}
+
+/// Directory order, always descending.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoomSort {
+    #[default]
+    Created,
+    Members,
+    /// Latest message, falling back to creation for empty rooms.
+    Activity,
+}
+
+impl RoomSort {
+    pub fn name(self) -> &'static str {
+        match self {
+            RoomSort::Created => "created",
+            RoomSort::Members => "members",
+            RoomSort::Activity => "activity",
+        }
+    }
+}
+
+/// A room as listed in the directory.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RoomDirectoryEntry {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub room:              Room,
+    pub member_count:      i64,
+    pub last_activity_at:  Option<DateTime<Utc>>,
+    pub tags:              Vec<String>,
+    /// Live subscribers; filled in from the hub, not the database.
+    #[sqlx(skip)]
+    pub online_count:      usize,
+    /// Value of the current sort column, for the cursor.
+    #[serde(skip)]
+    pub sort_key:          i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRoomTagsRequest {
+    pub tags:   Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferOwnershipRequest {
+    pub user_id:    Uuid,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaveRoomRequest {
+    /// Required when the owner leaves and the room has no other admin.
+    pub successor_id:   Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeRoleRequest {
+    pub role:   RoomRole,
+}
+
+/// Replaces the role's defaults in this room.
+#[derive(Debug, Deserialize)]
+pub struct SetRolePermissionsRequest {
+    pub permissions:    Permissions,
+}
@@ -0,0 +1,65 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RoomInvitation {
+    pub id:            Uuid,
+    pub room_id:       Uuid,
+    pub invitee_id:    Uuid,
+    pub inviter_id:    Option<Uuid>,
+    /// pending | accepted | declined | revoked
+    pub status:        String,
+    pub created_at:    DateTime<Utc>,
+    pub responded_at:  Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RoomInviteLink {
+    pub id:           Uuid,
+    pub room_id:      Uuid,
+    pub created_by:   Option<Uuid>,
+    pub code_prefix:  String,
+    pub max_uses:     Option<i32>,
+    pub uses:         i32,
+    pub expires_at:   Option<DateTime<Utc>>,
+    pub revoked_at:   Option<DateTime<Utc>>,
+    pub created_at:   DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RoomJoinRequest {
+    pub id:           Uuid,
+    pub room_id:      Uuid,
+    pub user_id:      Uuid,
+    pub message:      Option<String>,
+    /// pending | approved | rejected
+    pub status:       String,
+    pub reviewed_by:  Option<Uuid>,
+    pub created_at:   DateTime<Utc>,
+    pub reviewed_at:  Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteUserRequest {
+    pub user_id:  Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteLinkRequest {
+    /// Omit for unlimited uses.
+    pub max_uses:         Option<i32>,
+    /// Omit for a link that never expires.
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedeemInviteLinkRequest {
+    pub code:  String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateJoinRequest {
+    pub message:  Option<String>,
+}
@@ -2,6 +2,8 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::models::access_token::Access;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub user_id:    Uuid,
@@ -10,6 +12,29 @@ pub struct Session {
     pub last_active: DateTime<Utc>,
 }
 
+/// A single-use WebSocket ticket as stored in Redis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsTicket {
+    pub user_id:    Uuid,
+    pub username:   String,
+    /// `Origin` of the request that issued the ticket; the upgrade must match it.
+    pub origin:     Option<String>,
+    pub issued_at:  DateTime<Utc>,
+    /// Carried over from the request that issued the ticket; scopes apply per frame.
+    pub access:     Access,
+    /// The connection only hears from rooms in this workspace.
+    #[serde(default)]
+    pub workspace_id: Option<Uuid>,
+}
+
+/// An OIDC sign-in in progress, keyed in Redis by its `state` parameter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcPending {
+    pub provider:       String,
+    pub code_verifier:  String,
+    pub nonce:          String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthTokens {
     pub access_token:     String,
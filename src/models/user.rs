@@ -8,11 +8,30 @@ pub struct User {
     pub id:             Uuid,
     pub username:       String,
     pub email:          String,
-    pub password_hash:  String,
+    /// `None` for accounts created through single sign-on.
+    pub password_hash:  Option<String>,
     pub display_name:   Option<String>,
     pub avatar_url:     Option<String>,
     pub created_at:     DateTime<Utc>,
     pub updated_at:      DateTime<Utc>,
+    /// Base32 TOTP secret; set at enrollment, active once `totp_enabled_at` is set.
+    pub totp_secret:    Option<String>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub is_bot:         bool,
+    /// The human who created this bot and manages its tokens.
+    pub bot_owner_id:   Option<Uuid>,
+    pub is_admin:       bool,
+}
+
+impl User {
+    pub fn has_totp(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    pub fn is_email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -23,6 +42,8 @@ pub struct UserResponse {
     pub avatar_url:     Option<String>,
     pub created_at:     DateTime<Utc>,
     pub updated_at:      DateTime<Utc>,
+    pub email_verified: bool,
+    pub is_bot:         bool,
 }
 
 impl From<User> for UserResponse {
@@ -34,6 +55,8 @@ impl From<User> for UserResponse {
             avatar_url:     u.avatar_url,
             created_at:     u.created_at,
             updated_at:     u.updated_at,
+            email_verified: u.email_verified_at.is_some(),
+            is_bot:         u.is_bot,
         }
     }
 }
@@ -44,6 +67,8 @@ pub struct RegisterRequest {
     pub email:          String,
     pub password:       String,
     pub display_name:   Option<String>,
+    /// Required when `REGISTRATION_MODE=invite`; optional otherwise.
+    pub invite_code:    Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,3 +82,47 @@ pub struct UpdateProfileRequest {
     pub display_name:   Option<String>,
     pub avatar_url:     Option<String>,
 }
+
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollment {
+    pub secret:       String,
+    pub otpauth_uri:  String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpCodeRequest {
+    pub code:   String,
+}
+
+/// Second login step: the `mfa_token` from `/login` plus a TOTP or recovery code.
+#[derive(Debug, Deserialize)]
+pub struct MfaVerifyRequest {
+    pub mfa_token:      String,
+    pub code:           Option<String>,
+    pub recovery_code:  Option<String>,
+}
+
+/// Turning 2FA off re-authenticates with the password and a current second factor.
+#[derive(Debug, Deserialize)]
+pub struct DisableTotpRequest {
+    pub password:       String,
+    pub code:           Option<String>,
+    pub recovery_code:  Option<String>,
+}
+
+/// Body for `/verify-email` and anything else that only carries an emailed token.
+#[derive(Debug, Deserialize)]
+pub struct EmailTokenRequest {
+    pub token:  String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email:  String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token:          String,
+    pub new_password:   String,
+}
@@ -0,0 +1,104 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Workspace {
+    pub id:          Uuid,
+    pub slug:        String,
+    pub name:        String,
+    pub created_by:  Option<Uuid>,
+    pub created_at:  DateTime<Utc>,
+    pub updated_at:  DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct WorkspaceMember {
+    pub workspace_id:  Uuid,
+    pub user_id:       Uuid,
+    pub role:          String,
+    pub joined_at:     DateTime<Utc>,
+}
+
+impl WorkspaceMember {
+    pub fn role(&self) -> WorkspaceRole {
+        WorkspaceRole::from_name(&self.role).unwrap_or(WorkspaceRole::Member)
+    }
+}
+
+/// An offer to join a workspace; the invitee becomes a member only by accepting.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct WorkspaceInvitation {
+    pub id:             Uuid,
+    pub workspace_id:   Uuid,
+    /// Filled in when listing the invitee's own invitations.
+    #[sqlx(default)]
+    pub workspace_name: Option<String>,
+    pub invitee_id:     Uuid,
+    pub inviter_id:     Option<Uuid>,
+    pub role:           String,
+    /// pending | accepted | declined
+    pub status:         String,
+    pub created_at:     DateTime<Utc>,
+    pub responded_at:   Option<DateTime<Utc>>,
+}
+
+/// A member's standing in a workspace, highest first.  Admins manage membership;
+/// the owner can't be removed or demoted by anyone else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl WorkspaceRole {
+    pub const ALL: [WorkspaceRole; 3] = [WorkspaceRole::Owner, WorkspaceRole::Admin, WorkspaceRole::Member];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Member => "member",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        WorkspaceRole::ALL.into_iter().find(|r| r.name() == name)
+    }
+
+    pub fn rank(self) -> u8 {
+        match self {
+            WorkspaceRole::Owner => 2,
+            WorkspaceRole::Admin => 1,
+            WorkspaceRole::Member => 0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWorkspaceRequest {
+    pub name:   String,
+    /// Lowercase letters, digits and dashes; unique across the deployment.
+    pub slug:   String,
+}
+
+/// Invites an existing account, by username.
+#[derive(Debug, Deserialize)]
+pub struct InviteWorkspaceMemberRequest {
+    pub username:  String,
+    /// Defaults to member.
+    pub role:      Option<WorkspaceRole>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeWorkspaceRoleRequest {
+    pub role:   WorkspaceRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserSearchParams {
+    pub q:      String,
+    pub limit:  Option<i64>,
+}
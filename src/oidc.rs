@@ -0,0 +1,229 @@
+/// OpenID Connect relying party.  Talks to the configured providers: discovery,
+/// authorization URLs, code exchange and ID token validation.  Account linking and
+/// token issuance live in `services::oidc_service`.
+///
+/// Discovery documents are fetched on first use and cached; provider signing keys are
+/// cached too and refetched when a token arrives with a `kid` we haven't seen.
+use std::collections::HashMap;
+use std::time::Duration;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::jwk::Jwk;
+use serde::Deserialize;
+use tokio::sync::{OnceCell, RwLock};
+use url::Url;
+
+use crate::config::OidcProviderConfig;
+use crate::error::{AppError, Result};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Signature algorithms we accept on ID tokens.  Never HS*: the client secret is not a
+/// signing key we want to trust.
+const ID_TOKEN_ALGORITHMS: [Algorithm; 4] = [Algorithm::RS256, Algorithm::PS256, Algorithm::ES256, Algorithm::EdDSA];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer:                 String,
+    pub authorization_endpoint: String,
+    pub token_endpoint:         String,
+    pub jwks_uri:               String,
+}
+
+/// The ID token claims we use.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub:                String,
+    pub email:              Option<String>,
+    /// Some providers send this as a string.
+    email_verified:         Option<serde_json::Value>,
+    pub preferred_username: Option<String>,
+    pub name:               Option<String>,
+    nonce:                  Option<String>,
+}
+
+impl IdTokenClaims {
+    pub fn email_verified(&self) -> bool {
+        matches!(&self.email_verified, Some(serde_json::Value::Bool(true)))
+            || matches!(&self.email_verified, Some(serde_json::Value::String(s)) if s == "true")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+struct Provider {
+    cfg:      OidcProviderConfig,
+    metadata: OnceCell<ProviderMetadata>,
+    jwks:     RwLock<Vec<Jwk>>,
+}
+
+pub struct OidcProviders {
+    http:      reqwest::Client,
+    providers: HashMap<String, Provider>,
+}
+
+fn upstream(name: &str, what: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::Internal(format!("OIDC provider {name}: {what}: {e}"))
+}
+
+impl OidcProviders {
+    pub fn new(configs: &[OidcProviderConfig]) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::Internal(format!("HTTP client: {e}")))?;
+        let providers = configs.iter()
+            .map(|cfg| (cfg.name.clone(), Provider {
+                cfg:      cfg.clone(),
+                metadata: OnceCell::new(),
+                jwks:     RwLock::new(Vec::new()),
+            }))
+            .collect();
+        Ok(OidcProviders { http, providers })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.providers.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    fn provider(&self, name: &str) -> Result<&Provider> {
+        self.providers.get(name)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown sign-in provider: {name}")))
+    }
+
+    async fn metadata<'a>(&self, provider: &'a Provider) -> Result<&'a ProviderMetadata> {
+        provider.metadata.get_or_try_init(|| async {
+            let name = &provider.cfg.name;
+            let url = format!("{}/.well-known/openid-configuration", provider.cfg.issuer.trim_end_matches('/'));
+            let metadata: ProviderMetadata = self.http.get(&url).send().await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| upstream(name, "discovery", e))?
+                .json().await
+                .map_err(|e| upstream(name, "discovery", e))?;
+            if metadata.issuer.trim_end_matches('/') != provider.cfg.issuer.trim_end_matches('/') {
+                return Err(upstream(name, "discovery", format!("issuer mismatch ({})", metadata.issuer)));
+            }
+            Ok(metadata)
+        }).await
+    }
+
+    /// Where to send the browser to start signing in.
+    pub async fn authorization_url(
+        &self,
+        name: &str,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String> {
+        let provider = self.provider(name)?;
+        let metadata = self.metadata(provider).await?;
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| upstream(name, "authorization_endpoint", e))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &provider.cfg.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", &provider.cfg.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok(url.into())
+    }
+
+    /// Redeem an authorization code and validate the ID token that comes back.
+    pub async fn exchange_code(
+        &self,
+        name: &str,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims> {
+        let provider = self.provider(name)?;
+        let metadata = self.metadata(provider).await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", provider.cfg.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if !provider.cfg.client_secret.is_empty() {
+            form.push(("client_secret", provider.cfg.client_secret.as_str()));
+        }
+        let response = self.http.post(&metadata.token_endpoint).form(&form).send().await
+            .map_err(|e| upstream(name, "token request", e))?;
+        if response.status().is_client_error() {
+            return Err(AppError::Unauthorized("Sign-in code was rejected by the provider".into()));
+        }
+        let tokens: TokenResponse = response.error_for_status()
+            .map_err(|e| upstream(name, "token request", e))?
+            .json().await
+            .map_err(|e| upstream(name, "token response", e))?;
+        let id_token = tokens.id_token
+            .ok_or_else(|| upstream(name, "token response", "no id_token"))?;
+        self.validate_id_token(provider, metadata, &id_token, nonce).await
+    }
+
+    async fn validate_id_token(
+        &self,
+        provider: &Provider,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims> {
+        let invalid = |why: &str| AppError::Unauthorized(format!("Invalid ID token: {why}"));
+        let header = decode_header(id_token).map_err(|_| invalid("malformed"))?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(invalid("unsupported algorithm"));
+        }
+        let key = self.signing_key(provider, metadata, header.kid.as_deref()).await?
+            .ok_or_else(|| invalid("unknown signing key"))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&provider.cfg.client_id]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| invalid(&e.to_string()))?
+            .claims;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid("nonce mismatch"));
+        }
+        Ok(claims)
+    }
+
+    /// Find the provider key for `kid`, refreshing the cached key set once if it's unknown.
+    async fn signing_key(&self, provider: &Provider, metadata: &ProviderMetadata, kid: Option<&str>) -> Result<Option<DecodingKey>> {
+        let pick = |keys: &[Jwk]| -> Option<DecodingKey> {
+            let jwk = match kid {
+                Some(kid) => keys.iter().find(|k| k.common.key_id.as_deref() == Some(kid)),
+                None if keys.len() == 1 => keys.first(),
+                None => None,
+            }?;
+            DecodingKey::from_jwk(jwk).ok()
+        };
+        if let Some(key) = pick(&provider.jwks.read().await) {
+            return Ok(Some(key));
+        }
+
+        let name = &provider.cfg.name;
+        let set: serde_json::Value = self.http.get(&metadata.jwks_uri).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| upstream(name, "jwks", e))?
+            .json().await
+            .map_err(|e| upstream(name, "jwks", e))?;
+        // Skip key types we can't use rather than rejecting the whole set.
+        let keys: Vec<Jwk> = set.get("keys")
+            .and_then(|k| k.as_array())
+            .map(|keys| keys.iter().filter_map(|k| serde_json::from_value(k.clone()).ok()).collect())
+            .unwrap_or_default();
+        let key = pick(&keys);
+        *provider.jwks.write().await = keys;
+        Ok(key)
+    }
+}
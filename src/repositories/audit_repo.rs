@@ -0,0 +1,43 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::audit::AuditEvent;
+use crate::error::Result;
+
+pub async fn record(
+    pool: &PgPool,
+    action: &str,
+    actor_id: Option<Uuid>,
+    target_user_id: Option<Uuid>,
+    ip: Option<&str>,
+    details: serde_json::Value,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO audit_events (action, actor_id, target_user_id, ip, details) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(action)
+    .bind(actor_id)
+    .bind(target_user_id)
+    .bind(ip)
+    .bind(sqlx::types::Json(details))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list(pool: &PgPool, action: Option<&str>, target_user_id: Option<Uuid>, limit: i64) -> Result<Vec<AuditEvent>> {
+    Ok(sqlx::query_as::<_, AuditEvent>(
+        r#"
+        SELECT * FROM audit_events
+        WHERE ($1::VARCHAR IS NULL OR action = $1)
+          AND ($2::UUID IS NULL OR target_user_id = $2)
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(action)
+    .bind(target_user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?)
+}
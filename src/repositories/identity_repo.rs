@@ -0,0 +1,79 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::user::User;
+use crate::error::Result;
+
+/// The local user linked to `(provider, subject)`, if any.
+pub async fn find_user(pool: &PgPool, provider: &str, subject: &str) -> Result<Option<User>> {
+    Ok(sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.* FROM users u
+        JOIN user_identities i ON i.user_id = u.id
+        WHERE i.provider = $1 AND i.subject = $2
+        "#,
+    )
+    .bind(provider)
+    .bind(subject)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn link(pool: &PgPool, user_id: Uuid, provider: &str, subject: &str, email: Option<&str>) -> Result<()> {
+    sqlx::query("INSERT INTO user_identities (user_id, provider, subject, email) VALUES ($1, $2, $3, $4)")
+        .bind(user_id)
+        .bind(provider)
+        .bind(subject)
+        .bind(email)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn touch(pool: &PgPool, provider: &str, subject: &str, email: Option<&str>) -> Result<()> {
+    sqlx::query(
+        "UPDATE user_identities SET last_login_at = NOW(), email = COALESCE($3, email) WHERE provider = $1 AND subject = $2",
+    )
+    .bind(provider)
+    .bind(subject)
+    .bind(email)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Create a password-less user and link the identity to it in one transaction.
+#[allow(clippy::too_many_arguments)]
+pub async fn create_user_with_identity(
+    pool: &PgPool,
+    username: &str,
+    email: &str,
+    display_name: Option<&str>,
+    email_verified: bool,
+    provider: &str,
+    subject: &str,
+) -> Result<User> {
+    let mut tx = pool.begin().await?;
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (username, email, display_name, email_verified_at)
+        VALUES ($1, $2, $3, CASE WHEN $4 THEN NOW() END)
+        RETURNING *
+        "#,
+    )
+    .bind(username)
+    .bind(email)
+    .bind(display_name)
+    .bind(email_verified)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query("INSERT INTO user_identities (user_id, provider, subject, email) VALUES ($1, $2, $3, $4)")
+        .bind(user.id)
+        .bind(provider)
+        .bind(subject)
+        .bind(email)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(user)
+}
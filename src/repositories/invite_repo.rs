@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::invite::InviteCode;
+use crate::models::user::User;
+use crate::error::Result;
+
+pub async fn create(
+    pool: &PgPool,
+    created_by: Uuid,
+    code_prefix: &str,
+    code_hash: &str,
+    max_uses: Option<i32>,
+    room_ids: &[Uuid],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<InviteCode> {
+    Ok(sqlx::query_as::<_, InviteCode>(
+        r#"
+        INSERT INTO invite_codes (created_by, code_prefix, code_hash, max_uses, room_ids, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(created_by)
+    .bind(code_prefix)
+    .bind(code_hash)
+    .bind(max_uses)
+    .bind(room_ids)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?)
+}
+
+pub async fn get(pool: &PgPool, id: Uuid) -> Result<Option<InviteCode>> {
+    Ok(sqlx::query_as::<_, InviteCode>("SELECT * FROM invite_codes WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// Every invite, or only those made by `created_by`.
+pub async fn list(pool: &PgPool, created_by: Option<Uuid>) -> Result<Vec<InviteCode>> {
+    Ok(sqlx::query_as::<_, InviteCode>(
+        "SELECT * FROM invite_codes WHERE ($1::UUID IS NULL OR created_by = $1) ORDER BY created_at DESC",
+    )
+    .bind(created_by)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn revoke(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE invite_codes SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Redeem an invite and create the user with it, in one transaction: the use is
+/// counted only if the account is created, and concurrent sign-ups can't exceed
+/// `max_uses`.  The user joins the invite's rooms that still exist.  `None` when the
+/// code is unknown, revoked, expired or used up.
+pub async fn create_user_with_invite(
+    pool: &PgPool,
+    code_hash: &str,
+    username: &str,
+    email: &str,
+    password_hash: &str,
+    display_name: Option<&str>,
+) -> Result<Option<User>> {
+    let mut tx = pool.begin().await?;
+    let invite = sqlx::query_as::<_, InviteCode>(
+        r#"
+        UPDATE invite_codes SET uses = uses + 1
+        WHERE code_hash = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > NOW())
+          AND (max_uses IS NULL OR uses < max_uses)
+        RETURNING *
+        "#,
+    )
+    .bind(code_hash)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(invite) = invite else { return Ok(None) };
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (username, email, password_hash, display_name)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(username)
+    .bind(email)
+    .bind(password_hash)
+    .bind(display_name)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO room_members (room_id, user_id, role)
+        SELECT id, $2, 'member' FROM rooms WHERE id = ANY($1)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&invite.room_ids)
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await?;
+    // Rooms are only reachable from inside their workspace.
+    sqlx::query(
+        r#"
+        INSERT INTO workspace_members (workspace_id, user_id)
+        SELECT DISTINCT workspace_id, $2 FROM rooms WHERE id = ANY($1)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&invite.room_ids)
+    .bind(user.id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(Some(user))
+}
@@ -25,18 +25,48 @@ pub async fn create_message(
     Ok(msg)
 }
 
+/// A `system` message recording something that happened in the room, attributed to
+/// whoever caused it.
+pub async fn create_system_message(
+    pool: &PgPool,
+    room_id: Uuid,
+    user_id: Uuid,
+    content: &str,
+    metadata: &serde_json::Value,
+) -> Result<Message> {
+    Ok(sqlx::query_as::<_, Message>(
+        r#"
+        INSERT INTO messages (room_id, user_id, content, message_type, metadata)
+        VALUES ($1, $2, $3, 'system', $4)
+        RETURNING *
+        "#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(content)
+    .bind(metadata)
+    .fetch_one(pool)
+    .await?)
+}
+
 pub async fn get_room_messages(
     pool: &PgPool,
     room_id: Uuid,
     limit: i64,
     offset: i64,
+    pattern: Option<&str>,
 ) -> Result<Vec<Message>> {
     Ok(sqlx::query_as::<_, Message>(
-        "SELECT * FROM messages WHERE room_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+        r#"
+        SELECT * FROM messages
+        WHERE room_id = $1 AND ($4::text IS NULL OR content ILIKE $4)
+        ORDER BY created_at DESC LIMIT $2 OFFSET $3
+        "#,
     )
     .bind(room_id)
     .bind(limit)
     .bind(offset)
+    .bind(pattern)
     .fetch_all(pool)
     .await?)
 }
@@ -1,3 +1,12 @@
 pub mod user_repo;
 pub mod room_repo;
-pub mod message_repo;
\ No newline at end of file
+pub mod message_repo;
+pub mod token_repo;
+pub mod identity_repo;
+pub mod pat_repo;
+pub mod audit_repo;
+pub mod invite_repo;
+pub mod moderation_repo;
+pub mod room_invite_repo;pub mod pin_repo;
+pub mod posting_policy_repo;
+pub mod workspace_repo;
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::moderation::{RoomBan, RoomMute};
+use crate::error::Result;
+
+/// Ban (or re-ban, replacing the old reason and expiry) and drop the membership.
+pub async fn ban(
+    pool: &PgPool,
+    room_id: Uuid,
+    user_id: Uuid,
+    banned_by: Uuid,
+    reason: Option<&str>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<RoomBan> {
+    let mut tx = pool.begin().await?;
+    let ban = sqlx::query_as::<_, RoomBan>(
+        r#"
+        INSERT INTO room_bans (room_id, user_id, banned_by, reason, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (room_id, user_id) DO UPDATE
+            SET banned_by = EXCLUDED.banned_by, reason = EXCLUDED.reason,
+                expires_at = EXCLUDED.expires_at, created_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(banned_by)
+    .bind(reason)
+    .bind(expires_at)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM room_members WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(ban)
+}
+
+pub async fn unban(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM room_bans WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn active_ban(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<Option<RoomBan>> {
+    Ok(sqlx::query_as::<_, RoomBan>(
+        r#"
+        SELECT * FROM room_bans
+        WHERE room_id = $1 AND user_id = $2 AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn list_bans(pool: &PgPool, room_id: Uuid) -> Result<Vec<RoomBan>> {
+    Ok(sqlx::query_as::<_, RoomBan>(
+        r#"
+        SELECT * FROM room_bans
+        WHERE room_id = $1 AND (expires_at IS NULL OR expires_at > NOW())
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn mute(
+    pool: &PgPool,
+    room_id: Uuid,
+    user_id: Uuid,
+    muted_by: Uuid,
+    reason: Option<&str>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<RoomMute> {
+    Ok(sqlx::query_as::<_, RoomMute>(
+        r#"
+        INSERT INTO room_mutes (room_id, user_id, muted_by, reason, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (room_id, user_id) DO UPDATE
+            SET muted_by = EXCLUDED.muted_by, reason = EXCLUDED.reason,
+                expires_at = EXCLUDED.expires_at, created_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(muted_by)
+    .bind(reason)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?)
+}
+
+pub async fn unmute(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM room_mutes WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn active_mute(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<Option<RoomMute>> {
+    Ok(sqlx::query_as::<_, RoomMute>(
+        r#"
+        SELECT * FROM room_mutes
+        WHERE room_id = $1 AND user_id = $2 AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn list_mutes(pool: &PgPool, room_id: Uuid) -> Result<Vec<RoomMute>> {
+    Ok(sqlx::query_as::<_, RoomMute>(
+        r#"
+        SELECT * FROM room_mutes
+        WHERE room_id = $1 AND (expires_at IS NULL OR expires_at > NOW())
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?)
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::access_token::PersonalAccessToken;
+use crate::error::Result;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create(
+    pool: &PgPool,
+    user_id: Uuid,
+    workspace_id: Uuid,
+    name: &str,
+    token_prefix: &str,
+    token_hash: &str,
+    scopes: &[String],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<PersonalAccessToken> {
+    Ok(sqlx::query_as::<_, PersonalAccessToken>(
+        r#"
+        INSERT INTO personal_access_tokens (user_id, workspace_id, name, token_prefix, token_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .bind(name)
+    .bind(token_prefix)
+    .bind(token_hash)
+    .bind(scopes)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?)
+}
+
+pub async fn get(pool: &PgPool, id: Uuid) -> Result<Option<PersonalAccessToken>> {
+    Ok(sqlx::query_as::<_, PersonalAccessToken>("SELECT * FROM personal_access_tokens WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+/// A token that is neither revoked nor expired.
+pub async fn find_active(pool: &PgPool, token_hash: &str) -> Result<Option<PersonalAccessToken>> {
+    Ok(sqlx::query_as::<_, PersonalAccessToken>(
+        r#"
+        SELECT * FROM personal_access_tokens
+        WHERE token_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn list_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<PersonalAccessToken>> {
+    Ok(sqlx::query_as::<_, PersonalAccessToken>(
+        "SELECT * FROM personal_access_tokens WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn revoke(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE personal_access_tokens SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Revoke every token belonging to the user and to the bots they own.
+pub async fn revoke_all_for_user(pool: &PgPool, user_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE personal_access_tokens SET revoked_at = NOW()
+        WHERE revoked_at IS NULL
+          AND (user_id = $1 OR user_id IN (SELECT id FROM users WHERE bot_owner_id = $1))
+        "#,
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record use, at most once a minute per token to keep hot tokens from writing on every request.
+pub async fn touch(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE personal_access_tokens SET last_used_at = NOW()
+        WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
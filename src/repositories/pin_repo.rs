@@ -0,0 +1,71 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::pin::{PinnedMessage, RoomPin};
+use crate::error::Result;
+
+/// What `pin` did.
+pub enum PinOutcome {
+    Pinned(RoomPin),
+    AlreadyPinned,
+    /// The room already has `max_pins`.
+    Full,
+}
+
+/// Pin a message unless the room already has `max_pins` or the message is pinned.
+/// The room row is locked so concurrent pins can't overshoot the cap, and a concurrent
+/// pin of the same message loses on the primary key without an error.
+pub async fn pin(pool: &PgPool, room_id: Uuid, message_id: Uuid, pinned_by: Uuid, max_pins: i64) -> Result<PinOutcome> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT id FROM rooms WHERE id = $1 FOR UPDATE")
+        .bind(room_id)
+        .execute(&mut *tx)
+        .await?;
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM room_pins WHERE room_id = $1")
+        .bind(room_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if count >= max_pins {
+        return Ok(PinOutcome::Full);
+    }
+    let pin = sqlx::query_as::<_, RoomPin>(
+        r#"
+        INSERT INTO room_pins (message_id, room_id, pinned_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (message_id) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(message_id)
+    .bind(room_id)
+    .bind(pinned_by)
+    .fetch_optional(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(pin.map_or(PinOutcome::AlreadyPinned, PinOutcome::Pinned))
+}
+
+pub async fn unpin(pool: &PgPool, message_id: Uuid) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM room_pins WHERE message_id = $1")
+        .bind(message_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Newest pins first.
+pub async fn list_pins(pool: &PgPool, room_id: Uuid) -> Result<Vec<PinnedMessage>> {
+    Ok(sqlx::query_as::<_, PinnedMessage>(
+        r#"
+        SELECT m.*, p.pinned_by, u.username AS pinned_by_username, p.created_at AS pinned_at
+        FROM room_pins p
+        JOIN messages m ON m.id = p.message_id
+        LEFT JOIN users u ON u.id = p.pinned_by
+        WHERE p.room_id = $1
+        ORDER BY p.created_at DESC
+        "#,
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?)
+}
@@ -0,0 +1,36 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::posting_policy::PostingPolicy;
+use crate::error::Result;
+
+pub async fn get_policy(pool: &PgPool, room_id: Uuid) -> Result<Option<PostingPolicy>> {
+    Ok(sqlx::query_as::<_, PostingPolicy>("SELECT * FROM room_posting_policies WHERE room_id = $1")
+        .bind(room_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+pub async fn set_policy(pool: &PgPool, room_id: Uuid, policy: &PostingPolicy, updated_by: Uuid) -> Result<PostingPolicy> {
+    Ok(sqlx::query_as::<_, PostingPolicy>(
+        r#"
+        INSERT INTO room_posting_policies
+            (room_id, slow_mode_secs, min_post_role, max_message_length, allow_links, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (room_id) DO UPDATE
+            SET slow_mode_secs = EXCLUDED.slow_mode_secs, min_post_role = EXCLUDED.min_post_role,
+                max_message_length = EXCLUDED.max_message_length, allow_links = EXCLUDED.allow_links,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(room_id)
+    .bind(policy.slow_mode_secs)
+    .bind(policy.min_post_role.as_deref())
+    .bind(policy.max_message_length)
+    .bind(policy.allow_links)
+    .bind(updated_by)
+    .fetch_one(pool)
+    .await?)
+}
@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::room_invite::{RoomInvitation, RoomInviteLink, RoomJoinRequest};
+use crate::error::Result;
+
+// ──────────────────── Invitations ─────────────────
+
+/// `None` if the user already has a pending invitation to the room.
+pub async fn create_invitation(pool: &PgPool, room_id: Uuid, invitee_id: Uuid, inviter_id: Uuid) -> Result<Option<RoomInvitation>> {
+    Ok(sqlx::query_as::<_, RoomInvitation>(
+        r#"
+        INSERT INTO room_invitations (room_id, invitee_id, inviter_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (room_id, invitee_id) WHERE status = 'pending' DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(room_id)
+    .bind(invitee_id)
+    .bind(inviter_id)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn get_invitation(pool: &PgPool, id: Uuid) -> Result<Option<RoomInvitation>> {
+    Ok(sqlx::query_as::<_, RoomInvitation>("SELECT * FROM room_invitations WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+pub async fn pending_for_room(pool: &PgPool, room_id: Uuid) -> Result<Vec<RoomInvitation>> {
+    Ok(sqlx::query_as::<_, RoomInvitation>(
+        "SELECT * FROM room_invitations WHERE room_id = $1 AND status = 'pending' ORDER BY created_at DESC",
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn pending_for_user(pool: &PgPool, invitee_id: Uuid) -> Result<Vec<RoomInvitation>> {
+    Ok(sqlx::query_as::<_, RoomInvitation>(
+        "SELECT * FROM room_invitations WHERE invitee_id = $1 AND status = 'pending' ORDER BY created_at DESC",
+    )
+    .bind(invitee_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Move a pending invitation to `status`; on `accepted` the invitee becomes a member in
+/// the same transaction.  `None` if it wasn't pending (or wasn't theirs).
+pub async fn resolve_invitation(pool: &PgPool, id: Uuid, status: &str) -> Result<Option<RoomInvitation>> {
+    let mut tx = pool.begin().await?;
+    let invitation = sqlx::query_as::<_, RoomInvitation>(
+        r#"
+        UPDATE room_invitations SET status = $2, responded_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(inv) = invitation.as_ref().filter(|_| status == "accepted") {
+        add_member(&mut tx, inv.room_id, inv.invitee_id).await?;
+    }
+    tx.commit().await?;
+    Ok(invitation)
+}
+
+async fn add_member(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, room_id: Uuid, user_id: Uuid) -> Result<()> {
+    sqlx::query("INSERT INTO room_members (room_id, user_id, role) VALUES ($1, $2, 'member') ON CONFLICT DO NOTHING")
+        .bind(room_id)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+// ──────────────────── Links ─────────────────
+
+pub async fn create_link(
+    pool: &PgPool,
+    room_id: Uuid,
+    created_by: Uuid,
+    code_prefix: &str,
+    code_hash: &str,
+    max_uses: Option<i32>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<RoomInviteLink> {
+    Ok(sqlx::query_as::<_, RoomInviteLink>(
+        r#"
+        INSERT INTO room_invite_links (room_id, created_by, code_prefix, code_hash, max_uses, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(room_id)
+    .bind(created_by)
+    .bind(code_prefix)
+    .bind(code_hash)
+    .bind(max_uses)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?)
+}
+
+pub async fn list_links(pool: &PgPool, room_id: Uuid) -> Result<Vec<RoomInviteLink>> {
+    Ok(sqlx::query_as::<_, RoomInviteLink>(
+        "SELECT * FROM room_invite_links WHERE room_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn revoke_link(pool: &PgPool, room_id: Uuid, id: Uuid) -> Result<u64> {
+    let result = sqlx::query(
+        "UPDATE room_invite_links SET revoked_at = NOW() WHERE id = $1 AND room_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(room_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+const LINK_USABLE: &str = "revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW()) AND (max_uses IS NULL OR uses < max_uses)";
+
+/// A link that can still be used.
+pub async fn find_usable_link(pool: &PgPool, code_hash: &str) -> Result<Option<RoomInviteLink>> {
+    Ok(sqlx::query_as::<_, RoomInviteLink>(&format!(
+        "SELECT * FROM room_invite_links WHERE code_hash = $1 AND {LINK_USABLE}",
+    ))
+    .bind(code_hash)
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// Count a use and add the member atomically; `None` if the link ran out meanwhile.
+pub async fn redeem_link(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<Option<RoomInviteLink>> {
+    let mut tx = pool.begin().await?;
+    let link = sqlx::query_as::<_, RoomInviteLink>(&format!(
+        "UPDATE room_invite_links SET uses = uses + 1 WHERE id = $1 AND {LINK_USABLE} RETURNING *",
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(link) = &link {
+        add_member(&mut tx, link.room_id, user_id).await?;
+    }
+    tx.commit().await?;
+    Ok(link)
+}
+
+// ──────────────────── Join requests ─────────────────
+
+/// `None` if the user already has a pending request for the room.
+pub async fn create_join_request(pool: &PgPool, room_id: Uuid, user_id: Uuid, message: Option<&str>) -> Result<Option<RoomJoinRequest>> {
+    Ok(sqlx::query_as::<_, RoomJoinRequest>(
+        r#"
+        INSERT INTO room_join_requests (room_id, user_id, message)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (room_id, user_id) WHERE status = 'pending' DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(message)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn get_join_request(pool: &PgPool, id: Uuid) -> Result<Option<RoomJoinRequest>> {
+    Ok(sqlx::query_as::<_, RoomJoinRequest>("SELECT * FROM room_join_requests WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+pub async fn pending_join_requests(pool: &PgPool, room_id: Uuid) -> Result<Vec<RoomJoinRequest>> {
+    Ok(sqlx::query_as::<_, RoomJoinRequest>(
+        "SELECT * FROM room_join_requests WHERE room_id = $1 AND status = 'pending' ORDER BY created_at",
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Approve or reject a pending request; approval adds the member in the same transaction.
+pub async fn review_join_request(
+    pool: &PgPool,
+    room_id: Uuid,
+    id: Uuid,
+    reviewer_id: Uuid,
+    approve: bool,
+) -> Result<Option<RoomJoinRequest>> {
+    let mut tx = pool.begin().await?;
+    let request = sqlx::query_as::<_, RoomJoinRequest>(
+        r#"
+        UPDATE room_join_requests SET status = $4, reviewed_by = $3, reviewed_at = NOW()
+        WHERE id = $1 AND room_id = $2 AND status = 'pending'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(room_id)
+    .bind(reviewer_id)
+    .bind(if approve { "approved" } else { "rejected" })
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(req) = request.as_ref().filter(|_| approve) {
+        add_member(&mut tx, req.room_id, req.user_id).await?;
+    }
+    tx.commit().await?;
+    Ok(request)
+}
@@ -1,6 +1,7 @@
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
-use crate::models::room::{Room, RoomMember};
+use crate::models::room::{Permissions, Room, RoomDirectoryEntry, RoomMember, RoomRole, RoomSort, UpdateRoomRequest};
 use crate::error::Result;
 
 pub async fn create_room(
@@ -8,12 +9,13 @@ pub async fn create_room(
     room_name: &str,
     description: Option<&str>,
     is_private: bool,
-    create_by: Uuid
+    create_by: Uuid,
+    workspace_id: Uuid,
 ) -> Result<Room> {
     let room = sqlx::query_as::<_, Room>(
         r#"
-            INSERT INTO rooms (name, description, is_private, create_by)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO rooms (name, description, is_private, created_by, workspace_id)
+            VALUES ($1, $2, $3, $4, $5)
             RETURNING *
         "#,
     )
@@ -21,6 +23,7 @@ pub async fn create_room(
     .bind(description)
     .bind(is_private)
     .bind(create_by)
+    .bind(workspace_id)
     .fetch_one(pool)
     .await?;
     Ok(room)
@@ -33,47 +36,172 @@ pub async fn get_room(pool: &PgPool, id: Uuid) -> Result<Option<Room>> {
         .await?)
 }
 
-pub async fn list_room(pool: &PgPool, include_private: bool, limit: i32) -> Result<Vec<Room>> {
-    if include_private {
-        Ok(sqlx::query_as::<_, Room>("SeLECT * FROM rooms ORDER BY created_at DESC LIMIT $1")
-        .fetch_all(pool)
-        .await?
-        )
-    } else {
-        Ok(sqlx::query_as::<_, Room>("SeLECT * FROM rooms WHERE is_private = false ORDER BY created_at DESC LIMIT $1")
-        .bind(limit)
-        .fetch_all(pool)
-        .await?
+/// Filters for `list_directory`.  `member_id` switches to "rooms this user is in",
+/// private ones included; otherwise only public rooms are listed.
+pub struct DirectoryQuery<'a> {
+    pub workspace_id:      Uuid,
+    pub member_id:         Option<Uuid>,
+    pub include_archived:  bool,
+    /// An ILIKE pattern.
+    pub pattern:           Option<&'a str>,
+    pub tag:               Option<&'a str>,
+    pub sort:              RoomSort,
+    /// Keyset cursor: rows strictly after this (sort key, id).
+    pub after:             Option<(i64, Uuid)>,
+    pub limit:             i64,
+}
+
+pub async fn list_directory(pool: &PgPool, query: &DirectoryQuery<'_>) -> Result<Vec<RoomDirectoryEntry>> {
+    let (after_key, after_id) = query.after.unzip();
+    Ok(sqlx::query_as::<_, RoomDirectoryEntry>(
+        r#"
+        WITH d AS (
+            SELECT r.*,
+                   (SELECT COUNT(*) FROM room_members rm WHERE rm.room_id = r.id) AS member_count,
+                   (SELECT MAX(m.created_at) FROM messages m WHERE m.room_id = r.id) AS last_activity_at,
+                   ARRAY(SELECT t.tag::text FROM room_tags t WHERE t.room_id = r.id ORDER BY t.tag) AS tags
+            FROM rooms r
+            WHERE CASE WHEN $1::uuid IS NULL THEN NOT r.is_private
+                       ELSE EXISTS (SELECT 1 FROM room_members me WHERE me.room_id = r.id AND me.user_id = $1) END
+              AND ($2 OR r.archived_at IS NULL)
+              AND ($3::text IS NULL OR r.name ILIKE $3 OR r.description ILIKE $3 OR r.topic ILIKE $3)
+              AND ($4::text IS NULL OR EXISTS (SELECT 1 FROM room_tags t WHERE t.room_id = r.id AND t.tag = $4))
+              AND r.workspace_id = $9
+        ), k AS (
+            SELECT d.*,
+                   CASE $5
+                       WHEN 'members'  THEN d.member_count
+                       WHEN 'activity' THEN (EXTRACT(EPOCH FROM COALESCE(d.last_activity_at, d.created_at)) * 1000000)::bigint
+                       ELSE (EXTRACT(EPOCH FROM d.created_at) * 1000000)::bigint
+                   END AS sort_key
+            FROM d
         )
-    }
+        SELECT * FROM k
+        WHERE $6::bigint IS NULL OR (sort_key, id) < ($6, $7::uuid)
+        ORDER BY sort_key DESC, id DESC
+        LIMIT $8
+        "#,
+    )
+    .bind(query.member_id)
+    .bind(query.include_archived)
+    .bind(query.pattern)
+    .bind(query.tag)
+    .bind(query.sort.name())
+    .bind(after_key)
+    .bind(after_id)
+    .bind(query.limit)
+    .bind(query.workspace_id)
+    .fetch_all(pool)
+    .await?)
 }
 
-pub async fn update_room(
-    pool: &PgPool,
-    id: Uuid,
-    name: Option<&str>,
-    description: Option<&str>,
-    is_private: Option<bool>
-) -> Result<Room> {
+/// Replace the room's tags.
+pub async fn set_tags(pool: &PgPool, room_id: Uuid, tags: &[String]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM room_tags WHERE room_id = $1")
+        .bind(room_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO room_tags (room_id, tag) SELECT $1, UNNEST($2::text[])")
+        .bind(room_id)
+        .bind(tags)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Tags in use on the workspace's public, unarchived rooms, most used first.
+pub async fn tag_counts(pool: &PgPool, workspace_id: Uuid, limit: i64) -> Result<Vec<(String, i64)>> {
+    Ok(sqlx::query_as(
+        r#"
+        SELECT t.tag::text, COUNT(*) AS rooms
+        FROM room_tags t JOIN rooms r ON r.id = t.room_id
+        WHERE r.workspace_id = $2 AND NOT r.is_private AND r.archived_at IS NULL
+        GROUP BY t.tag
+        ORDER BY rooms DESC, t.tag
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// `None` keeps a column; for the optional text columns `Some("")` sets NULL.
+pub async fn update_room(pool: &PgPool, id: Uuid, req: &UpdateRoomRequest) -> Result<Room> {
     let room = sqlx::query_as::<_, Room>(
         r#"
         UPDATE rooms
         SET name        = COALESCE($2, name),
-            description = COALESCE($3, description)
-            is_private  = COALESCE($4, is_private)
+            description = CASE WHEN $3::text IS NULL THEN description ELSE NULLIF($3, '') END,
+            is_private  = COALESCE($4, is_private),
+            topic       = CASE WHEN $5::text IS NULL THEN topic ELSE NULLIF($5, '') END,
+            avatar_url  = CASE WHEN $6::text IS NULL THEN avatar_url ELSE NULLIF($6, '') END,
+            rules       = CASE WHEN $7::text IS NULL THEN rules ELSE NULLIF($7, '') END,
+            settings    = COALESCE($8, settings),
+            updated_at  = NOW()
         WHERE id = $1
         RETURNING *
         "#,
     )
     .bind(id)
-    .bind(name)
-    .bind(description)
-    .bind(is_private)
+    .bind(req.name.as_deref())
+    .bind(req.description.as_deref())
+    .bind(req.is_private)
+    .bind(req.topic.as_deref())
+    .bind(req.avatar_url.as_deref())
+    .bind(req.rules.as_deref())
+    .bind(req.settings.as_ref())
     .fetch_one(pool)
     .await?;
     Ok(room)
 }
 
+/// Archive the room, or with `purge_after` also schedule its deletion.  An already
+/// archived room keeps its original archive time.
+pub async fn archive_room(pool: &PgPool, id: Uuid, archived_by: Uuid, purge_after: Option<DateTime<Utc>>) -> Result<Room> {
+    Ok(sqlx::query_as::<_, Room>(
+        r#"
+        UPDATE rooms
+        SET archived_at = COALESCE(archived_at, NOW()),
+            archived_by = COALESCE(archived_by, $2),
+            purge_after = COALESCE($3, purge_after),
+            updated_at  = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(archived_by)
+    .bind(purge_after)
+    .fetch_one(pool)
+    .await?)
+}
+
+/// Restore an archived room and cancel any pending purge.
+pub async fn unarchive_room(pool: &PgPool, id: Uuid) -> Result<Room> {
+    Ok(sqlx::query_as::<_, Room>(
+        r#"
+        UPDATE rooms
+        SET archived_at = NULL, archived_by = NULL, purge_after = NULL, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .fetch_one(pool)
+    .await?)
+}
+
+pub async fn rooms_due_for_purge(pool: &PgPool, limit: i64) -> Result<Vec<Uuid>> {
+    Ok(sqlx::query_scalar("SELECT id FROM rooms WHERE purge_after <= NOW() ORDER BY purge_after LIMIT $1")
+        .bind(limit)
+        .fetch_all(pool)
+        .await?)
+}
+
 pub async fn delete_room(pool: &PgPool, id: Uuid) -> Result<u64> {
     let result = sqlx::query("DELETE FROM rooms WHERE id = $1")
         .bind(id)
@@ -122,12 +250,167 @@ pub async fn is_room_member(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Resu
 }
 
 
-pub async fn get_member_role(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<Option<String>> {
-    Ok(sqlx::query_scalar::<_, String>(
-        "SELECT role FROM room_members WHERE room_id = $1 AND user_id = $2",
+pub async fn get_member(pool: &PgPool, room_id: Uuid, user_id: Uuid) -> Result<Option<RoomMember>> {
+    Ok(sqlx::query_as::<_, RoomMember>("SELECT * FROM room_members WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+pub async fn set_member_role(pool: &PgPool, room_id: Uuid, user_id: Uuid, role: RoomRole) -> Result<()> {
+    sqlx::query("UPDATE room_members SET role = $3 WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(user_id)
+        .bind(role.name())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// This room's replacements for role defaults.  Rows naming unknown roles are skipped.
+pub async fn get_permission_overrides(pool: &PgPool, room_id: Uuid) -> Result<Vec<(RoomRole, Permissions)>> {
+    let rows: Vec<(String, i64)> = sqlx::query_as("SELECT role, permissions FROM room_role_permissions WHERE room_id = $1")
+        .bind(room_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter()
+        .filter_map(|(role, bits)| Some((RoomRole::from_name(&role)?, Permissions::from_bits_truncate(bits))))
+        .collect())
+}
+
+pub async fn get_permission_override(pool: &PgPool, room_id: Uuid, role: RoomRole) -> Result<Option<Permissions>> {
+    let bits: Option<i64> = sqlx::query_scalar("SELECT permissions FROM room_role_permissions WHERE room_id = $1 AND role = $2")
+        .bind(room_id)
+        .bind(role.name())
+        .fetch_optional(pool)
+        .await?;
+    Ok(bits.map(Permissions::from_bits_truncate))
+}
+
+pub async fn set_permission_override(pool: &PgPool, room_id: Uuid, role: RoomRole, permissions: Permissions) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO room_role_permissions (room_id, role, permissions) VALUES ($1, $2, $3)
+        ON CONFLICT (room_id, role) DO UPDATE SET permissions = EXCLUDED.permissions, updated_at = NOW()
+        "#,
     )
     .bind(room_id)
+    .bind(role.name())
+    .bind(permissions.bits())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_permission_override(pool: &PgPool, room_id: Uuid, role: RoomRole) -> Result<()> {
+    sqlx::query("DELETE FROM room_role_permissions WHERE room_id = $1 AND role = $2")
+        .bind(room_id)
+        .bind(role.name())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Make `to` the owner and `from` an admin, in one transaction.  False if `from`
+/// wasn't the owner or `to` isn't a member.
+pub async fn transfer_ownership(pool: &PgPool, room_id: Uuid, from: Uuid, to: Uuid) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    // Demote first: the one-owner index is checked per statement.
+    let demoted = sqlx::query("UPDATE room_members SET role = 'admin' WHERE room_id = $1 AND user_id = $2 AND role = 'owner'")
+        .bind(room_id)
+        .bind(from)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    let promoted = sqlx::query("UPDATE room_members SET role = 'owner' WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if demoted == 0 || promoted == 0 {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// The owner leaves and hands the room to `successor`, or to the longest-standing admin
+/// when no successor is given.  Returns the new owner; `None` (and nothing changes)
+/// when there's nobody to hand over to.
+pub async fn leave_as_owner(pool: &PgPool, room_id: Uuid, owner_id: Uuid, successor: Option<Uuid>) -> Result<Option<Uuid>> {
+    let mut tx = pool.begin().await?;
+    let new_owner: Option<Uuid> = match successor {
+        Some(successor) => sqlx::query_scalar(
+            "SELECT user_id FROM room_members WHERE room_id = $1 AND user_id = $2 FOR UPDATE",
+        )
+        .bind(room_id)
+        .bind(successor)
+        .fetch_optional(&mut *tx)
+        .await?,
+        None => sqlx::query_scalar(
+            r#"
+            SELECT user_id FROM room_members
+            WHERE room_id = $1 AND role = 'admin' AND user_id <> $2
+            ORDER BY joined_at
+            LIMIT 1
+            FOR UPDATE
+            "#,
+        )
+        .bind(room_id)
+        .bind(owner_id)
+        .fetch_optional(&mut *tx)
+        .await?,
+    };
+    let Some(new_owner) = new_owner.filter(|&id| id != owner_id) else {
+        tx.rollback().await?;
+        return Ok(None);
+    };
+    sqlx::query("DELETE FROM room_members WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(owner_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE room_members SET role = 'owner' WHERE room_id = $1 AND user_id = $2")
+        .bind(room_id)
+        .bind(new_owner)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(Some(new_owner))
+}
+
+/// Rooms in the workspace that `user_id` owns.
+pub async fn owned_in_workspace(pool: &PgPool, workspace_id: Uuid, user_id: Uuid) -> Result<Vec<Uuid>> {
+    Ok(sqlx::query_scalar(
+        r#"
+        SELECT rm.room_id FROM room_members rm
+        JOIN rooms r ON r.id = rm.room_id
+        WHERE r.workspace_id = $1 AND rm.user_id = $2 AND rm.role = 'owner'
+        "#,
+    )
+    .bind(workspace_id)
     .bind(user_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Who inherits a room from `owner_id`: the longest-standing member of the highest
+/// remaining role.
+pub async fn next_owner(pool: &PgPool, room_id: Uuid, owner_id: Uuid) -> Result<Option<Uuid>> {
+    Ok(sqlx::query_scalar(
+        r#"
+        SELECT user_id FROM room_members
+        WHERE room_id = $1 AND user_id <> $2
+        ORDER BY CASE role WHEN 'admin' THEN 0 WHEN 'moderator' THEN 1 WHEN 'member' THEN 2 ELSE 3 END,
+                 joined_at
+        LIMIT 1
+        "#,
+    )
+    .bind(room_id)
+    .bind(owner_id)
     .fetch_optional(pool)
     .await?)
 }
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::Result;
+
+pub async fn create_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    purpose: &str,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user_id)
+    .bind(purpose)
+    .bind(token_hash)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Mark a live token used and return its owner.  `None` if the token is unknown,
+/// expired, already used or was issued for a different purpose.
+pub async fn consume_token(pool: &PgPool, purpose: &str, token_hash: &str) -> Result<Option<Uuid>> {
+    Ok(sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE user_tokens
+        SET used_at = NOW()
+        WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+    )
+    .bind(token_hash)
+    .bind(purpose)
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// Drop every outstanding token of `purpose` for a user, plus any stale ones.
+pub async fn delete_tokens(pool: &PgPool, user_id: Uuid, purpose: &str) -> Result<()> {
+    sqlx::query("DELETE FROM user_tokens WHERE (user_id = $1 AND purpose = $2) OR expires_at < NOW()")
+        .bind(user_id)
+        .bind(purpose)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -84,4 +84,121 @@ pub async fn search_user(pool: &PgPool, query: &str, limit: i32) -> Result<Vec<U
     .bind(limit)
     .fetch_all(pool)
     .await?)
-}
\ No newline at end of file
+}
+
+// ──────────────────── Two-factor auth ─────────────────
+pub async fn set_totp_secret(pool: &PgPool, user_id: Uuid, secret: &str) -> Result<()> {
+    sqlx::query("UPDATE users SET totp_secret = $2, totp_enabled_at = NULL, updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .bind(secret)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Turn TOTP on and replace the user's recovery codes in one transaction.
+pub async fn enable_totp(pool: &PgPool, user_id: Uuid, recovery_code_hashes: &[String]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE users SET totp_enabled_at = NOW(), updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO user_recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::VARCHAR[])")
+        .bind(user_id)
+        .bind(recovery_code_hashes)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn disable_totp(pool: &PgPool, user_id: Uuid) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Mark a recovery code used.  Returns false if it doesn't exist or was already used.
+pub async fn consume_recovery_code(pool: &PgPool, user_id: Uuid, code_hash: &str) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE user_recovery_codes SET used_at = NOW() WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .bind(code_hash)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// ──────────────────── Email & password recovery ─────────────────
+pub async fn mark_email_verified(pool: &PgPool, user_id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn update_password_hash(pool: &PgPool, user_id: Uuid, password_hash: &str) -> Result<()> {
+    sqlx::query("UPDATE users SET password_hash = $2, updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .bind(password_hash)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Set a new password.  Completing a reset also proves the user controls the address.
+pub async fn reset_password(pool: &PgPool, user_id: Uuid, password_hash: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash     = $2,
+            email_verified_at = COALESCE(email_verified_at, NOW()),
+            updated_at        = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(password_hash)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// ──────────────────── Bots ─────────────────
+/// Bots have no password and a placeholder address under the reserved `.invalid` TLD.
+pub async fn create_bot(pool: &PgPool, owner_id: Uuid, username: &str, display_name: Option<&str>) -> Result<User> {
+    Ok(sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (username, email, display_name, is_bot, bot_owner_id)
+        VALUES ($1, $2, $3, TRUE, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(username)
+    .bind(format!("{}@bots.invalid", username.to_lowercase()))
+    .bind(display_name)
+    .bind(owner_id)
+    .fetch_one(pool)
+    .await?)
+}
+
+pub async fn list_bots(pool: &PgPool, owner_id: Uuid) -> Result<Vec<User>> {
+    Ok(sqlx::query_as::<_, User>("SELECT * FROM users WHERE bot_owner_id = $1 ORDER BY created_at")
+        .bind(owner_id)
+        .fetch_all(pool)
+        .await?)
+}
@@ -0,0 +1,243 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::user::User;
+use crate::models::workspace::{Workspace, WorkspaceInvitation, WorkspaceMember, WorkspaceRole};
+use crate::error::Result;
+
+/// Create a workspace with `owner_id` as its owner.  `None` if the slug is taken.
+pub async fn create_workspace(pool: &PgPool, slug: &str, name: &str, owner_id: Uuid) -> Result<Option<Workspace>> {
+    let mut tx = pool.begin().await?;
+    let workspace = sqlx::query_as::<_, Workspace>(
+        r#"
+        INSERT INTO workspaces (slug, name, created_by) VALUES ($1, $2, $3)
+        ON CONFLICT (slug) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(slug)
+    .bind(name)
+    .bind(owner_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(workspace) = workspace else { return Ok(None) };
+    sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, 'owner')")
+        .bind(workspace.id)
+        .bind(owner_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(Some(workspace))
+}
+
+pub async fn get_workspace_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Workspace>> {
+    Ok(sqlx::query_as::<_, Workspace>("SELECT * FROM workspaces WHERE slug = $1")
+        .bind(slug)
+        .fetch_optional(pool)
+        .await?)
+}
+
+pub async fn list_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<Workspace>> {
+    Ok(sqlx::query_as::<_, Workspace>(
+        r#"
+        SELECT w.* FROM workspaces w
+        JOIN workspace_members wm ON wm.workspace_id = w.id
+        WHERE wm.user_id = $1
+        ORDER BY wm.joined_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// The workspace the user joined first, used when a session has none chosen.
+pub async fn first_for_user(pool: &PgPool, user_id: Uuid) -> Result<Option<Uuid>> {
+    Ok(sqlx::query_scalar(
+        "SELECT workspace_id FROM workspace_members WHERE user_id = $1 ORDER BY joined_at LIMIT 1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn get_member(pool: &PgPool, workspace_id: Uuid, user_id: Uuid) -> Result<Option<WorkspaceMember>> {
+    Ok(sqlx::query_as::<_, WorkspaceMember>(
+        "SELECT * FROM workspace_members WHERE workspace_id = $1 AND user_id = $2",
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn is_member(pool: &PgPool, workspace_id: Uuid, user_id: Uuid) -> Result<bool> {
+    Ok(sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM workspace_members WHERE workspace_id = $1 AND user_id = $2)",
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?)
+}
+
+pub async fn list_members(pool: &PgPool, workspace_id: Uuid) -> Result<Vec<WorkspaceMember>> {
+    Ok(sqlx::query_as::<_, WorkspaceMember>(
+        "SELECT * FROM workspace_members WHERE workspace_id = $1 ORDER BY joined_at",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+pub async fn add_member(pool: &PgPool, workspace_id: Uuid, user_id: Uuid, role: WorkspaceRole) -> Result<Option<WorkspaceMember>> {
+    Ok(sqlx::query_as::<_, WorkspaceMember>(
+        r#"
+        INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .bind(role.name())
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn set_member_role(pool: &PgPool, workspace_id: Uuid, user_id: Uuid, role: WorkspaceRole) -> Result<()> {
+    sqlx::query("UPDATE workspace_members SET role = $3 WHERE workspace_id = $1 AND user_id = $2")
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(role.name())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Remove the member and, with them, their memberships in the workspace's rooms.
+/// Returns the ids of the rooms they were in.
+pub async fn remove_member(pool: &PgPool, workspace_id: Uuid, user_id: Uuid) -> Result<Vec<Uuid>> {
+    let mut tx = pool.begin().await?;
+    let room_ids: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        DELETE FROM room_members rm USING rooms r
+        WHERE rm.room_id = r.id AND r.workspace_id = $1 AND rm.user_id = $2
+        RETURNING rm.room_id
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM workspace_members WHERE workspace_id = $1 AND user_id = $2")
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(room_ids)
+}
+
+/// Whether two users have at least one workspace in common.
+pub async fn shares_workspace(pool: &PgPool, a: Uuid, b: Uuid) -> Result<bool> {
+    Ok(sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM workspace_members x
+            JOIN workspace_members y ON y.workspace_id = x.workspace_id
+            WHERE x.user_id = $1 AND y.user_id = $2
+        )
+        "#,
+    )
+    .bind(a)
+    .bind(b)
+    .fetch_one(pool)
+    .await?)
+}
+
+/// Members of the workspace whose username or display name matches `pattern` (ILIKE).
+pub async fn search_members(pool: &PgPool, workspace_id: Uuid, pattern: &str, limit: i64) -> Result<Vec<User>> {
+    Ok(sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.* FROM users u
+        JOIN workspace_members wm ON wm.user_id = u.id
+        WHERE wm.workspace_id = $1 AND (u.username ILIKE $2 OR u.display_name ILIKE $2)
+        ORDER BY u.username
+        LIMIT $3
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(pattern)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?)
+}
+
+// ──────────────────── Invitations ─────────────────
+
+/// `None` if the user already has a pending invitation to the workspace.
+pub async fn create_invitation(
+    pool: &PgPool,
+    workspace_id: Uuid,
+    invitee_id: Uuid,
+    inviter_id: Uuid,
+    role: WorkspaceRole,
+) -> Result<Option<WorkspaceInvitation>> {
+    Ok(sqlx::query_as::<_, WorkspaceInvitation>(
+        r#"
+        INSERT INTO workspace_invitations (workspace_id, invitee_id, inviter_id, role)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (workspace_id, invitee_id) WHERE status = 'pending' DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(invitee_id)
+    .bind(inviter_id)
+    .bind(role.name())
+    .fetch_optional(pool)
+    .await?)
+}
+
+pub async fn pending_for_user(pool: &PgPool, invitee_id: Uuid) -> Result<Vec<WorkspaceInvitation>> {
+    Ok(sqlx::query_as::<_, WorkspaceInvitation>(
+        r#"
+        SELECT i.*, w.name AS workspace_name
+        FROM workspace_invitations i JOIN workspaces w ON w.id = i.workspace_id
+        WHERE i.invitee_id = $1 AND i.status = 'pending'
+        ORDER BY i.created_at DESC
+        "#,
+    )
+    .bind(invitee_id)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Answer one of `invitee_id`'s pending invitations; accepting adds the membership in
+/// the same transaction.  `None` if there was no such pending invitation.
+pub async fn resolve_invitation(pool: &PgPool, id: Uuid, invitee_id: Uuid, accept: bool) -> Result<Option<WorkspaceInvitation>> {
+    let mut tx = pool.begin().await?;
+    let invitation = sqlx::query_as::<_, WorkspaceInvitation>(
+        r#"
+        UPDATE workspace_invitations SET status = $3, responded_at = NOW()
+        WHERE id = $1 AND invitee_id = $2 AND status = 'pending'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(invitee_id)
+    .bind(if accept { "accepted" } else { "declined" })
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(inv) = invitation.as_ref().filter(|_| accept) {
+        sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+            .bind(inv.workspace_id)
+            .bind(inv.invitee_id)
+            .bind(&inv.role)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(invitation)
+}
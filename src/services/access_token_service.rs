@@ -0,0 +1,115 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::access_token::{AccessTokenResponse, CreateAccessTokenRequest, CreateBotRequest, PersonalAccessToken};
+use crate::models::user::{User, UserResponse};
+use crate::models::workspace::WorkspaceRole;
+use crate::repositories::{pat_repo, user_repo, workspace_repo};
+use crate::services::{auth_service, workspace_service};
+use crate::utils::token;
+
+/// Every personal access token starts with this, which is how `AuthUser` tells them from JWTs.
+pub const TOKEN_PREFIX: &str = "pat_";
+/// Characters of the token kept in clear for listings (`pat_` + 8).
+const DISPLAY_PREFIX_LEN: usize = 12;
+const MAX_EXPIRY_DAYS: i64 = 365 * 5;
+
+/// The bot joins the owner's active workspace, where its tokens then act.
+pub async fn create_bot(db: &DbPool, owner_id: Uuid, workspace_id: Uuid, req: &CreateBotRequest) -> Result<UserResponse> {
+    let owner = user_repo::get_user_by_id(&db.pg, owner_id).await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+    if owner.is_bot {
+        return Err(AppError::Forbidden("Bots can't create bots".into()));
+    }
+    auth_service::validate_username(&req.username)?;
+    workspace_service::ensure_member(db, workspace_id, owner_id).await?;
+    if user_repo::get_user_by_username(&db.pg, &req.username).await?.is_some() {
+        return Err(AppError::Conflict("Username already taken".into()));
+    }
+    let bot = user_repo::create_bot(&db.pg, owner_id, &req.username, req.display_name.as_deref()).await?;
+    workspace_repo::add_member(&db.pg, workspace_id, bot.id, WorkspaceRole::Member).await?;
+    Ok(bot.into())
+}
+
+pub async fn list_bots(db: &DbPool, owner_id: Uuid) -> Result<Vec<UserResponse>> {
+    Ok(user_repo::list_bots(&db.pg, owner_id).await?.into_iter().map(Into::into).collect())
+}
+
+/// The account a token request is about: the caller, or a bot the caller owns.
+async fn token_subject(db: &DbPool, caller: Uuid, bot_id: Option<Uuid>) -> Result<Uuid> {
+    let Some(bot_id) = bot_id else { return Ok(caller) };
+    match user_repo::get_user_by_id(&db.pg, bot_id).await? {
+        Some(bot) if bot.is_bot && bot.bot_owner_id == Some(caller) => Ok(bot.id),
+        _ => Err(AppError::NotFound("Bot not found".into())),
+    }
+}
+
+/// Returns the plaintext token, which is shown once and never stored.  The token acts
+/// in `req.workspace_id`, or the caller's active workspace; both the caller and the
+/// token's user must be members.
+pub async fn create_token(
+    db: &DbPool,
+    caller: Uuid,
+    active_workspace: Option<Uuid>,
+    req: &CreateAccessTokenRequest,
+) -> Result<(String, AccessTokenResponse)> {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::BadRequest("Token name must be 1-100 characters".into()));
+    }
+    if req.scopes.is_empty() {
+        return Err(AppError::BadRequest("At least one scope is required".into()));
+    }
+    let expires_at = match req.expires_in_days {
+        Some(days) if !(1..=MAX_EXPIRY_DAYS).contains(&days) => {
+            return Err(AppError::BadRequest(format!("expires_in_days must be 1-{MAX_EXPIRY_DAYS}")));
+        }
+        Some(days) => Some(Utc::now() + Duration::days(days)),
+        None => None,
+    };
+    let user_id = token_subject(db, caller, req.bot_id).await?;
+    let workspace_id = req.workspace_id.or(active_workspace)
+        .ok_or_else(|| AppError::BadRequest("workspace_id is required without an active workspace".into()))?;
+    workspace_service::ensure_member(db, workspace_id, caller).await?;
+    if user_id != caller {
+        workspace_service::ensure_member(db, workspace_id, user_id).await?;
+    }
+
+    let plain = format!("{TOKEN_PREFIX}{}", token::generate_token(32));
+    let mut scopes: Vec<String> = req.scopes.iter().map(|s| s.name().to_string()).collect();
+    scopes.sort();
+    scopes.dedup();
+    let created = pat_repo::create(
+        &db.pg, user_id, workspace_id, name, &plain[..DISPLAY_PREFIX_LEN], &token::hash_token(&plain), &scopes, expires_at,
+    ).await?;
+    Ok((plain, created.into()))
+}
+
+pub async fn list_tokens(db: &DbPool, caller: Uuid, bot_id: Option<Uuid>) -> Result<Vec<AccessTokenResponse>> {
+    let user_id = token_subject(db, caller, bot_id).await?;
+    Ok(pat_repo::list_for_user(&db.pg, user_id).await?.into_iter().map(Into::into).collect())
+}
+
+pub async fn revoke_token(db: &DbPool, caller: Uuid, token_id: Uuid) -> Result<()> {
+    let not_found = || AppError::NotFound("Token not found".into());
+    let pat = pat_repo::get(&db.pg, token_id).await?.ok_or_else(not_found)?;
+    if pat.user_id != caller {
+        token_subject(db, caller, Some(pat.user_id)).await.map_err(|_| not_found())?;
+    }
+    pat_repo::revoke(&db.pg, token_id).await
+}
+
+/// Resolve a presented token to its user.  Unknown, expired and revoked tokens, and
+/// tokens whose user has left the token's workspace, all get the same answer.
+pub async fn authenticate(db: &DbPool, plain: &str) -> Result<(User, PersonalAccessToken)> {
+    let invalid = || AppError::Unauthorized("Invalid or expired access token".into());
+    let pat = pat_repo::find_active(&db.pg, &token::hash_token(plain)).await?.ok_or_else(invalid)?;
+    let user = user_repo::get_user_by_id(&db.pg, pat.user_id).await?.ok_or_else(invalid)?;
+    if !workspace_repo::is_member(&db.pg, pat.workspace_id, user.id).await? {
+        return Err(invalid());
+    }
+    pat_repo::touch(&db.pg, pat.id).await?;
+    Ok((user, pat))
+}
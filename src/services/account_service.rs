@@ -0,0 +1,124 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::{DbPool, redisdb};
+use crate::error::{AppError, Result};
+use crate::mail::{Email, Mailer};
+use crate::models::user::User;
+use crate::repositories::{pat_repo, token_repo, user_repo};
+use crate::services::auth_service;
+use crate::utils::{password, token};
+
+const PURPOSE_VERIFY_EMAIL: &str = "verify_email";
+const PURPOSE_PASSWORD_RESET: &str = "password_reset";
+/// Minimum gap between two emails of the same kind to one user.
+const MAIL_THROTTLE_SECS: u64 = 60;
+
+/// Mint a signed token, store only its hash, and return the plaintext for the email.
+async fn issue_token(db: &DbPool, cfg: &Config, user_id: Uuid, purpose: &str, ttl_secs: i64) -> Result<String> {
+    token_repo::delete_tokens(&db.pg, user_id, purpose).await?;
+    let plain = token::generate_signed_token(&cfg.jwt_secret, purpose, 32);
+    let expires_at = Utc::now() + Duration::seconds(ttl_secs);
+    token_repo::create_token(&db.pg, user_id, purpose, &token::hash_token(&plain), expires_at).await?;
+    Ok(plain)
+}
+
+/// Check the signature, then burn the token.  Returns the user it was issued to.
+async fn redeem_token(db: &DbPool, cfg: &Config, purpose: &str, plain: &str) -> Result<Uuid> {
+    let invalid = || AppError::BadRequest("Invalid or expired token".into());
+    if !token::verify_signed_token(&cfg.jwt_secret, purpose, plain) {
+        return Err(invalid());
+    }
+    token_repo::consume_token(&db.pg, purpose, &token::hash_token(plain))
+        .await?
+        .ok_or_else(invalid)
+}
+
+/// True at most once per `MAIL_THROTTLE_SECS` for a given user and kind of email.
+async fn may_send(db: &DbPool, user_id: Uuid, purpose: &str) -> Result<bool> {
+    let mut redis = db.redis.clone();
+    let fresh: Option<String> = redis::cmd("SET")
+        .arg(format!("mail_throttle:{purpose}:{user_id}"))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(MAIL_THROTTLE_SECS)
+        .query_async(&mut redis)
+        .await?;
+    Ok(fresh.is_some())
+}
+
+fn link(cfg: &Config, path: &str, plain: &str) -> String {
+    format!("{}/{path}?token={plain}", cfg.app_base_url.trim_end_matches('/'))
+}
+
+pub async fn send_verification_email(db: &DbPool, mailer: &dyn Mailer, cfg: &Config, user: &User) -> Result<()> {
+    if user.is_email_verified() {
+        return Err(AppError::Conflict("Email is already verified".into()));
+    }
+    if !may_send(db, user.id, PURPOSE_VERIFY_EMAIL).await? {
+        return Err(AppError::RateLimited);
+    }
+    let plain = issue_token(db, cfg, user.id, PURPOSE_VERIFY_EMAIL, cfg.email_verify_ttl_secs).await?;
+    mailer.send(&Email {
+        to:      user.email.clone(),
+        subject: "Confirm your email address".into(),
+        body:    format!(
+            "Hi {},\n\nConfirm your email address by opening this link:\n\n{}\n\nThe link expires in {} hours.\n",
+            user.username,
+            link(cfg, "verify-email", &plain),
+            cfg.email_verify_ttl_secs / 3600,
+        ),
+    }).await
+}
+
+pub async fn resend_verification_email(db: &DbPool, mailer: &dyn Mailer, cfg: &Config, user_id: Uuid) -> Result<()> {
+    let user = user_repo::get_user_by_id(&db.pg, user_id).await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+    send_verification_email(db, mailer, cfg, &user).await
+}
+
+pub async fn verify_email(db: &DbPool, cfg: &Config, plain: &str) -> Result<()> {
+    let user_id = redeem_token(db, cfg, PURPOSE_VERIFY_EMAIL, plain).await?;
+    user_repo::mark_email_verified(&db.pg, user_id).await
+}
+
+/// Email a reset link if the address belongs to an account.  Succeeds either way so the
+/// endpoint can't be used to find out which addresses are registered.
+pub async fn request_password_reset(db: &DbPool, mailer: &dyn Mailer, cfg: &Config, email: &str) -> Result<()> {
+    let Some(user) = user_repo::get_user_by_email(&db.pg, email.trim()).await? else {
+        return Ok(());
+    };
+    if !may_send(db, user.id, PURPOSE_PASSWORD_RESET).await? {
+        return Ok(());
+    }
+    let plain = issue_token(db, cfg, user.id, PURPOSE_PASSWORD_RESET, cfg.password_reset_ttl_secs).await?;
+    mailer.send(&Email {
+        to:      user.email.clone(),
+        subject: "Reset your password".into(),
+        body:    format!(
+            "Hi {},\n\nSomeone asked to reset the password for your account. If it was you, open this link:\n\n{}\n\n\
+             The link expires in {} minutes. If you didn't ask for this, you can ignore this email.\n",
+            user.username,
+            link(cfg, "reset-password", &plain),
+            cfg.password_reset_ttl_secs / 60,
+        ),
+    }).await
+}
+
+/// Set a new password from a reset token and revoke every token issued before now,
+/// personal access tokens included.
+/// Returns the user id so the caller can drop live connections.
+pub async fn reset_password(db: &DbPool, cfg: &Config, plain: &str, new_password: &str) -> Result<Uuid> {
+    auth_service::validate_password(new_password)?;
+    let user_id = redeem_token(db, cfg, PURPOSE_PASSWORD_RESET, plain).await?;
+    let hashed = password::hash_password(new_password).await?;
+    user_repo::reset_password(&db.pg, user_id, &hashed).await?;
+    token_repo::delete_tokens(&db.pg, user_id, PURPOSE_PASSWORD_RESET).await?;
+    pat_repo::revoke_all_for_user(&db.pg, user_id).await?;
+
+    let mut redis = db.redis.clone();
+    redisdb::revoke_tokens_before(&mut redis, user_id, Utc::now().timestamp_millis(), cfg.jwt_refresh_expiry_secs as u64).await?;
+    Ok(user_id)
+}
@@ -0,0 +1,44 @@
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::audit::{AuditEvent, AuditEventsParams, ClearLockoutRequest};
+use crate::repositories::{audit_repo, user_repo};
+use crate::services::lockout_service;
+
+const DEFAULT_AUDIT_LIMIT: i64 = 100;
+const MAX_AUDIT_LIMIT: i64 = 500;
+
+/// Admins are flagged in the database (`UPDATE users SET is_admin = TRUE ...`).
+pub async fn ensure_admin(db: &DbPool, user_id: Uuid) -> Result<()> {
+    match user_repo::get_user_by_id(&db.pg, user_id).await? {
+        Some(user) if user.is_admin => Ok(()),
+        _ => Err(AppError::Forbidden("Administrator access required".into())),
+    }
+}
+
+pub async fn clear_lockout(db: &DbPool, admin_id: Uuid, req: &ClearLockoutRequest, ip: Option<&str>) -> Result<()> {
+    ensure_admin(db, admin_id).await?;
+    let email = req.email.as_deref().map(str::trim).filter(|e| !e.is_empty());
+    let target_ip = req.ip.as_deref().map(str::trim).filter(|i| !i.is_empty());
+    if email.is_none() && target_ip.is_none() {
+        return Err(AppError::BadRequest("Give an email, an ip, or both".into()));
+    }
+    lockout_service::clear(db, email, target_ip).await?;
+
+    let target = match email {
+        Some(email) => user_repo::get_user_by_email(&db.pg, email).await?.map(|u| u.id),
+        None => None,
+    };
+    audit_repo::record(
+        &db.pg, "lockout_cleared", Some(admin_id), target, ip,
+        json!({ "email": email, "ip": target_ip }),
+    ).await
+}
+
+pub async fn list_audit_events(db: &DbPool, admin_id: Uuid, params: &AuditEventsParams) -> Result<Vec<AuditEvent>> {
+    ensure_admin(db, admin_id).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_AUDIT_LIMIT).clamp(1, MAX_AUDIT_LIMIT);
+    audit_repo::list(&db.pg, params.action.as_deref(), params.target_user_id, limit).await
+}
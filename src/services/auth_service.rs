@@ -1,13 +1,24 @@
 use uuid::Uuid;
 use chrono::Utc;
 
-use crate::db::DbPool;
+use crate::config::{Config, RegistrationMode};
+use crate::db::{DbPool, redisdb};
+use crate::models::access_token::Access;
 use crate::models::user::RegisterRequest;
-use crate::models::session::{Session, AuthTokens};
-use crate::models::user::{self, UserResponse};
+use crate::models::session::{Session, AuthTokens, WsTicket};
+use crate::models::user::{self, User, UserResponse};
 use crate::error::{Result, AppError};
-use crate::repositories::user_repo;
-use crate::utils::{jwt, password};
+use crate::repositories::{invite_repo, user_repo, workspace_repo};
+use crate::services::{invite_service, lockout_service, mfa_service, workspace_service};
+use crate::utils::{jwt, password, token};
+use crate::utils::jwt::JwtKeys;
+
+/// Result of the password step of login.
+pub enum LoginOutcome {
+    Authenticated(UserResponse, AuthTokens),
+    /// 2FA is on: exchange `mfa_token` and a code at `/api/auth/2fa/verify`.
+    MfaRequired { mfa_token: String, expires_in: i64 },
+}
 
 // async fn store_session(pool: &DbPool, user_id: Uuid, username: &str) -> Result<()> {
 //     let session = Session {
@@ -21,30 +32,32 @@ use crate::utils::{jwt, password};
 //     // rd::set_session(&mut redis, &generate_session_id(), &session).await
 // }
 
-fn read_jwt_env() -> (String, i64, i64) {
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".into());
-    let access_exp  = std::env::var("JWT_EXPIRY_SECS").unwrap_or_else(|_| "86400".into()).parse().unwrap_or(86400);
-    let refresh_exp = std::env::var("JWT_REFRESH_EXPIRY_SECS").unwrap_or_else(|_| "604800".into()).parse().unwrap_or(604800);
-    (secret, access_exp, refresh_exp)
-
-}
-fn make_tokens(user_id: Uuid, username: &str) -> Result<AuthTokens> {
-    let (secret, access_exp, refresh_exp) = read_jwt_env();
+pub fn make_tokens(keys: &JwtKeys, user_id: Uuid, username: &str, workspace_id: Option<Uuid>) -> Result<AuthTokens> {
     Ok(AuthTokens {
-        access_token:  jwt::create_token(user_id, username, &secret, access_exp, "access")?,
-        refresh_token: jwt::create_token(user_id, username, &secret, refresh_exp, "refresh")?,
-        expires_at:    access_exp,
-        refresh_expires: refresh_exp
+        access_token:  jwt::create_token(keys, user_id, username, workspace_id, keys.access_ttl_secs, "access")?,
+        refresh_token: jwt::create_token(keys, user_id, username, workspace_id, keys.refresh_ttl_secs, "refresh")?,
+        expires_at:    keys.access_ttl_secs,
+        refresh_expires: keys.refresh_ttl_secs
     })
 }
-pub async fn register(db: &DbPool, req: &RegisterRequest) -> Result<(UserResponse, AuthTokens)> {
-    if req.username.len() < 3 || req.username.len() > 50 {
-        return Err(AppError::BadRequest("Username must be 3-50 characters".into()));
-    }
-    if req.password.len() < 8 {
-        return Err(AppError::BadRequest("Password must be at least 8 characters".into()));
+
+/// Tokens for a new sign-in, starting in the first workspace the user joined.
+pub async fn start_session(db: &DbPool, keys: &JwtKeys, user_id: Uuid, username: &str) -> Result<AuthTokens> {
+    let workspace_id = workspace_repo::first_for_user(&db.pg, user_id).await?;
+    make_tokens(keys, user_id, username, workspace_id)
+}
+pub async fn register(db: &DbPool, keys: &JwtKeys, cfg: &Config, req: &RegisterRequest) -> Result<(UserResponse, AuthTokens)> {
+    let invite_code = req.invite_code.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    match cfg.registration_mode {
+        RegistrationMode::Closed => return Err(AppError::Forbidden("Registration is closed".into())),
+        RegistrationMode::Invite if invite_code.is_none() => {
+            return Err(AppError::Forbidden("An invite code is required to register".into()));
+        }
+        _ => {}
     }
-    if !is_valid_email(&req.email).await {
+    validate_username(&req.username)?;
+    validate_password(&req.password)?;
+    if !is_valid_email(&req.email) {
         return Err(AppError::BadRequest("Invalid email address".into()));
     }
     if user_repo::get_user_by_username(&db.pg, &req.username).await?.is_some() {
@@ -54,39 +67,172 @@ pub async fn register(db: &DbPool, req: &RegisterRequest) -> Result<(UserRespons
         return Err(AppError::Conflict("Email already registered".into()));
     }
 
-    let hashed = password::hash_password(&req.password)?;
-    let user = user_repo::create_user(&db.pg, &req.username, &req.email, &hashed, req.display_name.as_deref()).await?;
-    let token = make_tokens(user.id, &user.username)?;
+    let hashed = password::hash_password(&req.password).await?;
+    let user = match invite_code {
+        Some(code) => invite_repo::create_user_with_invite(
+            &db.pg, &invite_service::hash_code(code), &req.username, &req.email, &hashed, req.display_name.as_deref(),
+        ).await?
+            .ok_or_else(|| AppError::BadRequest("Invalid or expired invite code".into()))?,
+        None => user_repo::create_user(&db.pg, &req.username, &req.email, &hashed, req.display_name.as_deref()).await?,
+    };
+    workspace_service::join_default(db, cfg, user.id).await?;
+    let token = start_session(db, keys, user.id, &user.username).await?;
     // store_session(pool, user_id, username);
     Ok((user.into(), token))
 }
 
-pub async fn login(db: &DbPool, req: &RegisterRequest) -> Result<(UserResponse, AuthTokens)> {
-    let user = user_repo::get_user_by_email(&db.pg, &req.email).await?
-        .ok_or_else(|| AppError::Unauthorized("Invalid email or password".into()))?;
+/// Password login.  Every failure (unknown email, SSO-only account, wrong password)
+/// looks the same to the caller and takes about as long; repeated failures are slowed
+/// down and eventually locked out by `lockout_service`.
+pub async fn login(db: &DbPool, keys: &JwtKeys, cfg: &Config, req: &RegisterRequest, ip: Option<&str>) -> Result<LoginOutcome> {
+    lockout_service::check(db, &req.email, ip).await?;
+
+    let user = user_repo::get_user_by_email(&db.pg, &req.email).await?;
+    // Single sign-on accounts have no password to check.
+    let valid = match user.as_ref().and_then(|u| u.password_hash.as_deref()) {
+        Some(password_hash) => password::verify_password(&req.password, password_hash).await?,
+        None => password::verify_dummy(&req.password).await?,
+    };
+    let user = match user {
+        Some(user) if valid => user,
+        other => {
+            lockout_service::record_failure(db, cfg, &req.email, other.map(|u| u.id), ip).await?;
+            return Err(AppError::Unauthorized("Invalid email or password".into()));
+        }
+    };
+    lockout_service::record_success(db, &req.email).await?;
 
-    if !password::verify_password(&req.password, &user.password_hash)? {
-        return Err(AppError::Unauthorized("Invalid password".into()));
+    if user.password_hash.as_deref().is_some_and(password::needs_rehash) {
+        upgrade_password_hash(db, user.id, &req.password).await;
     }
-    let token = make_tokens(user.id, &user.username)?;
-    Ok((user.into(), token))
+    finish_login(db, keys, user).await
+}
+
+/// The last step of every first-factor sign-in (password or SSO): ask for the second
+/// factor when 2FA is on, otherwise issue tokens.
+pub async fn finish_login(db: &DbPool, keys: &JwtKeys, user: User) -> Result<LoginOutcome> {
+    if user.has_totp() {
+        return Ok(LoginOutcome::MfaRequired {
+            mfa_token:  mfa_service::issue_mfa_token(keys, &user)?,
+            expires_in: mfa_service::MFA_TOKEN_TTL_SECS,
+        });
+    }
+    let token = start_session(db, keys, user.id, &user.username).await?;
+    Ok(LoginOutcome::Authenticated(user.into(), token))
+}
+
+/// Re-hash with the current policy after a successful login.  Best effort: a failure
+/// here must not fail the login, the upgrade is simply retried next time.
+async fn upgrade_password_hash(db: &DbPool, user_id: Uuid, plain: &str) {
+    let result = async {
+        let hashed = password::hash_password(plain).await?;
+        user_repo::update_password_hash(&db.pg, user_id, &hashed).await
+    }.await;
+    match result {
+        Ok(()) => tracing::info!("Upgraded password hash for {user_id}"),
+        Err(e) => tracing::warn!("Password hash upgrade for {user_id} failed: {e}"),
+    }
+}
+
+pub fn validate_username(username: &str) -> Result<()> {
+    if username.len() < 3 || username.len() > 50 {
+        return Err(AppError::BadRequest("Username must be 3-50 characters".into()));
+    }
+    Ok(())
+}
+
+pub fn validate_password(plain: &str) -> Result<()> {
+    if plain.len() < 8 {
+        return Err(AppError::BadRequest("Password must be at least 8 characters".into()));
+    }
+    Ok(())
 }
 
+/// Structural check of an address: `local@domain` where the local part is dot-atom
+/// text and the domain is at least two DNS labels with an alphabetic TLD.
+/// Deliverability is what email verification is for.
+fn is_valid_email(email: &str) -> bool {
+    const LOCAL_SPECIALS: &str = "!#$%&'*+-/=?^_`{|}~";
+    if email.len() > 254 {
+        return false;
+    }
+    let Some((local, domain)) = email.rsplit_once('@') else { return false };
+    let local_ok = !local.is_empty()
+        && local.len() <= 64
+        && local.split('.').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || LOCAL_SPECIALS.contains(c))
+        });
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels.last().is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+    local_ok && domain_ok
+}
 
-async fn is_valid_email(email: &str) -> bool {
-    if email.contains("@") {
-        return true
+/// Reject tokens issued before the user's sessions were last revoked (password reset).
+pub async fn ensure_not_revoked(db: &DbPool, claims: &jwt::Claims) -> Result<()> {
+    let mut redis = db.redis.clone();
+    if let Some(cutoff) = redisdb::tokens_revoked_before(&mut redis, claims.user_id()?).await? {
+        if claims.iat_ms < cutoff {
+            return Err(AppError::Unauthorized("Session has been revoked".into()));
+        }
     }
-    false
+    Ok(())
 }
 
-pub async fn refresh_token(token: &str) -> Result<AuthTokens> {
-    let (secret, _, _) = read_jwt_env();
-    let claims = jwt::verify_token(token, &secret)?;
+pub async fn refresh_token(db: &DbPool, keys: &JwtKeys, token: &str) -> Result<AuthTokens> {
+    let claims = jwt::verify_token(keys, token)?;
     if claims.token_type != "refresh" {
         return Err(AppError::Unauthorized("Not a valid refresh token".into()));
     }
-    return make_tokens(claims.user_id()?, &claims.username);
+    ensure_not_revoked(db, &claims).await?;
+    let user_id = claims.user_id()?;
+    // Stay in the same workspace unless the user has been removed from it since.
+    let workspace_id = match claims.workspace_id {
+        Some(id) if workspace_repo::is_member(&db.pg, id, user_id).await? => Some(id),
+        _ => workspace_repo::first_for_user(&db.pg, user_id).await?,
+    };
+    make_tokens(keys, user_id, &claims.username, workspace_id)
+}
+
+/// Issue a single-use `/ws` ticket bound to the user and the requesting origin.
+pub async fn issue_ws_ticket(
+    db: &DbPool,
+    user_id: Uuid,
+    username: &str,
+    workspace_id: Option<Uuid>,
+    origin: Option<&str>,
+    access: &Access,
+) -> Result<String> {
+    let ticket = token::generate_token(32);
+    let data = WsTicket {
+        user_id,
+        username:  username.to_string(),
+        origin:    origin.map(|o| o.to_string()),
+        issued_at: Utc::now(),
+        access:    access.clone(),
+        workspace_id,
+    };
+    let mut redis = db.redis.clone();
+    redisdb::set_ws_ticket(&mut redis, &ticket, &data).await?;
+    Ok(ticket)
+}
+
+/// Consume a `/ws` ticket.  It is deleted whether or not the origin matches.
+pub async fn redeem_ws_ticket(db: &DbPool, ticket: &str, origin: Option<&str>) -> Result<WsTicket> {
+    let mut redis = db.redis.clone();
+    let data = redisdb::take_ws_ticket(&mut redis, ticket).await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired ticket".into()))?;
+    if data.origin.as_deref() != origin {
+        return Err(AppError::Unauthorized("Ticket was issued for a different origin".into()));
+    }
+    Ok(data)
 }
 
 pub async fn get_current_user(db: &DbPool, user_id: Uuid) -> Result<UserResponse> {
@@ -0,0 +1,79 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::invite::{CreateInviteRequest, InviteCode};
+use crate::models::user::User;
+use crate::models::room::Permissions;
+use crate::repositories::{invite_repo, user_repo};
+use crate::services::room_service;
+use crate::utils::token;
+
+/// Characters of the code kept in clear for listings.
+const DISPLAY_PREFIX_LEN: usize = 8;
+const MAX_ROOMS: usize = 20;
+const MAX_EXPIRY_HOURS: i64 = 24 * 90;
+
+/// Codes are typed by hand, so tolerate stray whitespace and case.
+pub fn hash_code(code: &str) -> String {
+    token::hash_token(&code.trim().to_ascii_lowercase())
+}
+
+async fn caller(db: &DbPool, user_id: Uuid) -> Result<User> {
+    user_repo::get_user_by_id(&db.pg, user_id).await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))
+}
+
+/// Returns the plaintext code, which is shown once and never stored.
+pub async fn create_invite(db: &DbPool, cfg: &Config, user_id: Uuid, req: &CreateInviteRequest) -> Result<(String, InviteCode)> {
+    let user = caller(db, user_id).await?;
+    if !user.is_admin && (!cfg.users_can_invite || user.is_bot) {
+        return Err(AppError::Forbidden("You can't create invite codes".into()));
+    }
+    if req.max_uses.is_some_and(|n| n < 1) {
+        return Err(AppError::BadRequest("max_uses must be at least 1".into()));
+    }
+    let expires_at = match req.expires_in_hours {
+        Some(hours) if !(1..=MAX_EXPIRY_HOURS).contains(&hours) => {
+            return Err(AppError::BadRequest(format!("expires_in_hours must be 1-{MAX_EXPIRY_HOURS}")));
+        }
+        Some(hours) => Some(Utc::now() + Duration::hours(hours)),
+        None => None,
+    };
+
+    let mut room_ids = req.room_ids.clone();
+    room_ids.sort();
+    room_ids.dedup();
+    if room_ids.len() > MAX_ROOMS {
+        return Err(AppError::BadRequest(format!("An invite can list at most {MAX_ROOMS} rooms")));
+    }
+    for &room_id in &room_ids {
+        // Otherwise an invite would be a way into rooms the inviter can't bring people into.
+        let access = room_service::room_access(db, room_id, user.id).await?;
+        if !user.is_admin {
+            access.require(Permissions::INVITE)?;
+        }
+    }
+
+    let plain = token::generate_token(12);
+    let invite = invite_repo::create(
+        &db.pg, user.id, &plain[..DISPLAY_PREFIX_LEN], &hash_code(&plain), req.max_uses, &room_ids, expires_at,
+    ).await?;
+    Ok((plain, invite))
+}
+
+/// Admins see every invite, everyone else their own.
+pub async fn list_invites(db: &DbPool, user_id: Uuid) -> Result<Vec<InviteCode>> {
+    let user = caller(db, user_id).await?;
+    invite_repo::list(&db.pg, (!user.is_admin).then_some(user.id)).await
+}
+
+pub async fn revoke_invite(db: &DbPool, user_id: Uuid, invite_id: Uuid) -> Result<()> {
+    let user = caller(db, user_id).await?;
+    match invite_repo::get(&db.pg, invite_id).await? {
+        Some(invite) if user.is_admin || invite.created_by == user.id => invite_repo::revoke(&db.pg, invite.id).await,
+        _ => Err(AppError::NotFound("Invite not found".into())),
+    }
+}
@@ -0,0 +1,138 @@
+/// Brute-force protection for password login.
+///
+/// Failures are counted in Redis per account (by normalised email, whether or not it
+/// exists) and per client address, each within `LOGIN_FAILURE_WINDOW_SECS`.  After a few
+/// failures every further attempt is slowed down; once a counter reaches its limit the
+/// account or address is locked for `LOGIN_LOCKOUT_SECS` and an audit event is written.
+use std::time::Duration;
+use redis::AsyncCommands;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::repositories::audit_repo;
+
+/// Failures allowed before attempts start being delayed.
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_DELAY_MS: u64 = 250;
+const MAX_DELAY_MS: u64 = 4_000;
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn fail_key(kind: &str, id: &str) -> String {
+    format!("login_fail:{kind}:{id}")
+}
+
+fn lock_key(kind: &str, id: &str) -> String {
+    format!("login_lock:{kind}:{id}")
+}
+
+/// 250ms, 500ms, 1s, ... after the free attempts, capped at `MAX_DELAY_MS`.
+fn delay_for(failures: u32) -> Duration {
+    if failures < FREE_ATTEMPTS {
+        return Duration::ZERO;
+    }
+    let exp = (failures - FREE_ATTEMPTS).min(16);
+    Duration::from_millis((BASE_DELAY_MS << exp).min(MAX_DELAY_MS))
+}
+
+/// Run before checking the password.  Refuses locked accounts and addresses, and
+/// otherwise waits out the progressive delay earned by earlier failures.
+pub async fn check(db: &DbPool, email: &str, ip: Option<&str>) -> Result<()> {
+    let mut redis = db.redis.clone();
+    let email = normalize_email(email);
+
+    let mut locks = vec![lock_key("acct", &email)];
+    let mut counters = vec![fail_key("acct", &email)];
+    if let Some(ip) = ip {
+        locks.push(lock_key("ip", ip));
+        counters.push(fail_key("ip", ip));
+    }
+    let locked: u32 = redis.exists(&locks).await?;
+    if locked > 0 {
+        return Err(AppError::RateLimited);
+    }
+    let failures: Vec<Option<u32>> = redis.mget(&counters).await?;
+    let worst = failures.into_iter().flatten().max().unwrap_or(0);
+    let delay = delay_for(worst);
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    Ok(())
+}
+
+/// Count one failure against `key`; returns the count in the current window.
+async fn bump(redis: &mut redis::aio::ConnectionManager, key: &str, window_secs: u64) -> Result<u32> {
+    let count: u32 = redis.incr(key, 1).await?;
+    if count == 1 {
+        redis.expire::<_, ()>(key, window_secs as i64).await?;
+    }
+    Ok(count)
+}
+
+/// Record a failed attempt.  `user_id` is the account the email belongs to, if any,
+/// and only ends up in the audit trail.
+pub async fn record_failure(db: &DbPool, cfg: &Config, email: &str, user_id: Option<Uuid>, ip: Option<&str>) -> Result<()> {
+    let mut redis = db.redis.clone();
+    let email = normalize_email(email);
+
+    let failures = bump(&mut redis, &fail_key("acct", &email), cfg.login_failure_window_secs).await?;
+    if failures >= cfg.login_max_account_failures {
+        lock(&mut redis, cfg, "acct", &email).await?;
+        tracing::warn!("Locked login for {email} after {failures} failures");
+        audit_repo::record(
+            &db.pg, "account_locked", None, user_id, ip,
+            json!({ "email": email, "failures": failures, "lockout_secs": cfg.login_lockout_secs }),
+        ).await?;
+    }
+
+    if let Some(ip) = ip {
+        let failures = bump(&mut redis, &fail_key("ip", ip), cfg.login_failure_window_secs).await?;
+        if failures >= cfg.login_max_ip_failures {
+            lock(&mut redis, cfg, "ip", ip).await?;
+            tracing::warn!("Locked login from {ip} after {failures} failures");
+            audit_repo::record(
+                &db.pg, "ip_locked", None, None, Some(ip),
+                json!({ "failures": failures, "lockout_secs": cfg.login_lockout_secs }),
+            ).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn lock(redis: &mut redis::aio::ConnectionManager, cfg: &Config, kind: &str, id: &str) -> Result<()> {
+    redis.set_ex::<_, _, ()>(lock_key(kind, id), 1, cfg.login_lockout_secs).await?;
+    redis.del::<_, ()>(fail_key(kind, id)).await?;
+    Ok(())
+}
+
+/// A correct password resets the account's counter.  The address keeps its count:
+/// one valid account must not launder guesses against others.
+pub async fn record_success(db: &DbPool, email: &str) -> Result<()> {
+    let mut redis = db.redis.clone();
+    redis.del::<_, ()>(fail_key("acct", &normalize_email(email))).await?;
+    Ok(())
+}
+
+/// Lift a lockout and forget the failures behind it.
+pub async fn clear(db: &DbPool, email: Option<&str>, ip: Option<&str>) -> Result<()> {
+    let mut redis = db.redis.clone();
+    let mut keys = Vec::new();
+    if let Some(email) = email {
+        let email = normalize_email(email);
+        keys.push(lock_key("acct", &email));
+        keys.push(fail_key("acct", &email));
+    }
+    if let Some(ip) = ip {
+        keys.push(lock_key("ip", ip));
+        keys.push(fail_key("ip", ip));
+    }
+    if !keys.is_empty() {
+        redis.del::<_, ()>(keys).await?;
+    }
+    Ok(())
+}
@@ -1,35 +1,63 @@
 use uuid::Uuid;
 
 use crate::db::DbPool;
-use crate::repositories::{message_repo, room_repo, user_repo};
+use crate::repositories::{message_repo, user_repo};
+use crate::services::{moderation_service, posting_policy_service, room_service, workspace_service};
+use crate::services::room_service::RoomAccess;
+use crate::models::room::Permissions;
 use crate::models::message::{Message, DirectMessage, MessageEvent, MessageUser, PaginationParams};
 use crate::error::{AppError, Result};
+use crate::utils::search;
+
+/// Longest message / DM body accepted, in bytes.
+pub const MAX_CONTENT_LENGTH: usize = 10_000;
 
 pub async fn send_message(pool: &DbPool, user_id: Uuid, room_id: Uuid, content: &str) -> Result<Message> {
-    if content.trim().is_empty() || content.len() > 10_000 {
+    if content.trim().is_empty() || content.len() > MAX_CONTENT_LENGTH {
         return Err(AppError::BadRequest("Message content cannot be empty".into()))
     }
-    if !room_repo::is_room_member(&pool.pg, room_id, user_id).await? {
-        return Err(AppError::Forbidden("You are not a member of this room".into()));
-    }
+    let access = posting_access(pool, user_id, room_id).await?;
+    posting_policy_service::check_post(pool, &access, user_id, content).await?;
     message_repo::create_message(&pool.pg, room_id, user_id, content).await
 
 }
 
+/// Typing indicators are only relayed for people who could post the message.
+pub async fn ensure_can_type(pool: &DbPool, user_id: Uuid, room_id: Uuid) -> Result<()> {
+    posting_access(pool, user_id, room_id).await.map(|_| ())
+}
+
+/// Membership, archive, permission and mute checks shared by posting and typing.
+async fn posting_access(pool: &DbPool, user_id: Uuid, room_id: Uuid) -> Result<RoomAccess> {
+    let access = room_service::room_access(pool, room_id, user_id).await?;
+    if !access.is_member() {
+        return Err(AppError::Forbidden("You are not a member of this room".into()));
+    }
+    access.ensure_active()?;
+    access.require(Permissions::SEND)?;
+    moderation_service::ensure_not_muted(pool, room_id, user_id).await?;
+    Ok(access)
+}
 
-pub async fn get_messages(pool: &DbPool, room_id: Uuid, params: &PaginationParams) -> Result<Vec<Message>> {
+
+pub async fn get_messages(pool: &DbPool, room_id: Uuid, user_id: Uuid, params: &PaginationParams) -> Result<Vec<Message>> {
+    room_service::visible_room(pool, room_id, user_id).await?;
     let limit  = params.limit.unwrap_or(50).min(200) as i64;
     let offset = params.offset.unwrap_or(0).max(0) as i64;
-    message_repo::get_room_messages(&pool.pg, room_id, limit, offset).await
+    let pattern = search::contains_pattern(params.q.as_deref());
+    message_repo::get_room_messages(&pool.pg, room_id, limit, offset, pattern.as_deref()).await
 }
 
 pub async fn delete_message(pool: &DbPool, message_id: Uuid, user_id: Uuid) -> Result<()> {
     let msg = message_repo::get_message(&pool.pg, message_id)
         .await?
         .ok_or_else(|| AppError::NotFound("Message not found".into()))?;
-    if msg.sender_id != user_id {
-        return Err(AppError::Forbidden("You can only delete your own messages".into()));
-    }
+    let access = if msg.sender_id == user_id {
+        room_service::room_access(pool, msg.room_id, user_id).await?
+    } else {
+        room_service::require_permission(pool, msg.room_id, user_id, Permissions::DELETE_OTHERS).await?
+    };
+    access.ensure_active()?;
     message_repo::delete_message(&pool.pg, message_id).await?;
     Ok(())
 }
@@ -53,8 +81,9 @@ pub async fn build_message_event(pool: &DbPool, msg: &Message) -> Result<Message
 }
 
 
-pub async fn send_dm(pool: &DbPool, sender_id: Uuid, recipient_id: Uuid, content: &str) -> Result<DirectMessage> {
-    if content.is_empty() || content.len() > 10_000 {
+/// Send a DM from the sender's active workspace; the recipient must belong to it.
+pub async fn send_dm(pool: &DbPool, sender_id: Uuid, recipient_id: Uuid, workspace_id: Uuid, content: &str) -> Result<DirectMessage> {
+    if content.is_empty() || content.len() > MAX_CONTENT_LENGTH {
         return Err(AppError::BadRequest("Message must be 1-10 000 characters".into()));
     }
     if sender_id == recipient_id {
@@ -63,6 +92,9 @@ pub async fn send_dm(pool: &DbPool, sender_id: Uuid, recipient_id: Uuid, content
     user_repo::get_user_by_id(&pool.pg, recipient_id)
         .await?
         .ok_or_else(|| AppError::NotFound("Recipient not found".into()))?;
+    // DMs never cross tenants.
+    workspace_service::ensure_shared(pool, workspace_id, sender_id, recipient_id).await
+        .map_err(|_| AppError::NotFound("Recipient not found".into()))?;
     message_repo::create_direct_message(&pool.pg, sender_id, recipient_id, content).await
 }
 
@@ -0,0 +1,196 @@
+use redis::AsyncCommands;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::models::user::{TotpEnrollment, User, UserResponse};
+use crate::models::session::AuthTokens;
+use crate::error::{AppError, Result};
+use crate::repositories::user_repo;
+use crate::services::auth_service;
+use crate::utils::{jwt, password, token};
+use crate::utils::jwt::JwtKeys;
+
+const TOTP_ISSUER: &str = "rust-chat-server";
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECS: u64 = 30;
+/// Accept one step either side of now for clock drift.
+const TOTP_SKEW: u8 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// 96 bits per code: far beyond offline guessing, so a plain SHA-256 of it is safe to store.
+const RECOVERY_CODE_BYTES: usize = 12;
+/// Hex characters per dash-separated group when showing a code.
+const RECOVERY_CODE_GROUP: usize = 6;
+
+/// Lifetime of the `mfa_pending` token handed out by the first login step.
+pub const MFA_TOKEN_TTL_SECS: i64 = 300;
+/// Wrong second-factor attempts allowed per user within `MFA_TOKEN_TTL_SECS`.
+const MFA_MAX_ATTEMPTS: i64 = 5;
+
+fn totp_for(secret_b32: &str, account: &str) -> Result<TOTP> {
+    let bytes = Secret::Encoded(secret_b32.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::Internal(format!("Invalid TOTP secret: {e:?}")))?;
+    TOTP::new(Algorithm::SHA1, TOTP_DIGITS, TOTP_SKEW, TOTP_STEP_SECS, bytes, Some(TOTP_ISSUER.into()), account.into())
+        .map_err(|e| AppError::Internal(format!("TOTP: {e}")))
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+async fn load_user(db: &DbPool, user_id: Uuid) -> Result<User> {
+    user_repo::get_user_by_id(&db.pg, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))
+}
+
+/// Start (or restart) enrollment: store a fresh secret, not yet active.
+pub async fn begin_enrollment(db: &DbPool, user_id: Uuid) -> Result<TotpEnrollment> {
+    let user = load_user(db, user_id).await?;
+    if user.has_totp() {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".into()));
+    }
+    let raw: [u8; 20] = rand::random();
+    let secret = Secret::Raw(raw.to_vec()).to_encoded().to_string();
+    let totp = totp_for(&secret, &user.email)?;
+    user_repo::set_totp_secret(&db.pg, user_id, &secret).await?;
+    Ok(TotpEnrollment { secret, otpauth_uri: totp.get_url() })
+}
+
+/// Activate 2FA after the user proves their authenticator works.
+/// Returns the plaintext recovery codes; only their hashes are kept.
+pub async fn confirm_enrollment(db: &DbPool, user_id: Uuid, code: &str) -> Result<Vec<String>> {
+    let user = load_user(db, user_id).await?;
+    if user.has_totp() {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".into()));
+    }
+    let secret = user.totp_secret.as_deref()
+        .ok_or_else(|| AppError::BadRequest("Start enrollment first".into()))?;
+    if !check_totp(db, &user, secret, code).await? {
+        return Err(AppError::Unauthorized("Invalid authentication code".into()));
+    }
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = token::generate_token(RECOVERY_CODE_BYTES);
+            raw.as_bytes()
+                .chunks(RECOVERY_CODE_GROUP)
+                .map(|group| std::str::from_utf8(group).expect("hex is ASCII"))
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect();
+    let hashes: Vec<String> = codes.iter()
+        .map(|c| token::hash_token(&normalize_recovery_code(c)))
+        .collect();
+    user_repo::enable_totp(&db.pg, user_id, &hashes).await?;
+    Ok(codes)
+}
+
+/// Short-lived token proving the password step succeeded for a 2FA user.
+pub fn issue_mfa_token(keys: &JwtKeys, user: &User) -> Result<String> {
+    jwt::create_token(keys, user.id, &user.username, None, MFA_TOKEN_TTL_SECS, "mfa_pending")
+}
+
+/// Second login step: exchange an `mfa_pending` token and a code for real tokens.
+pub async fn verify_login(
+    db: &DbPool,
+    keys: &JwtKeys,
+    mfa_token: &str,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<(UserResponse, AuthTokens)> {
+    let claims = jwt::verify_token(keys, mfa_token)?;
+    if claims.token_type != "mfa_pending" {
+        return Err(AppError::Unauthorized("Not a valid MFA token".into()));
+    }
+    auth_service::ensure_not_revoked(db, &claims).await?;
+    let user = load_user(db, claims.user_id()?).await?;
+    verify_second_factor(db, &user, code, recovery_code).await?;
+    let tokens = auth_service::start_session(db, keys, user.id, &user.username).await?;
+    Ok((user.into(), tokens))
+}
+
+/// Turn 2FA off.  Requires the password and a current code, not just a valid session.
+pub async fn disable(
+    db: &DbPool,
+    user_id: Uuid,
+    plain_password: &str,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<()> {
+    let user = load_user(db, user_id).await?;
+    if !user.has_totp() {
+        return Err(AppError::BadRequest("Two-factor authentication is not enabled".into()));
+    }
+    let Some(password_hash) = user.password_hash.as_deref() else {
+        return Err(AppError::BadRequest("Set a password before changing two-factor settings".into()));
+    };
+    if !password::verify_password(plain_password, password_hash).await? {
+        return Err(AppError::Unauthorized("Invalid password".into()));
+    }
+    verify_second_factor(db, &user, code, recovery_code).await?;
+    user_repo::disable_totp(&db.pg, user_id).await
+}
+
+async fn verify_second_factor(
+    db: &DbPool,
+    user: &User,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<()> {
+    let secret = match (&user.totp_secret, user.has_totp()) {
+        (Some(secret), true) => secret,
+        _ => return Err(AppError::BadRequest("Two-factor authentication is not enabled".into())),
+    };
+
+    let mut redis = db.redis.clone();
+    let attempts_key = format!("mfa_attempts:{}", user.id);
+    let attempts: i64 = redis.incr(&attempts_key, 1).await?;
+    if attempts == 1 {
+        redis.expire::<_, ()>(&attempts_key, MFA_TOKEN_TTL_SECS).await?;
+    }
+    if attempts > MFA_MAX_ATTEMPTS {
+        return Err(AppError::RateLimited);
+    }
+
+    let ok = match (code, recovery_code) {
+        (Some(code), _) => check_totp(db, user, secret, code).await?,
+        (None, Some(recovery)) => {
+            let hash = token::hash_token(&normalize_recovery_code(recovery));
+            user_repo::consume_recovery_code(&db.pg, user.id, &hash).await?
+        }
+        (None, None) => return Err(AppError::BadRequest("A code or recovery code is required".into())),
+    };
+    if !ok {
+        return Err(AppError::Unauthorized("Invalid authentication code".into()));
+    }
+    redis.del::<_, ()>(&attempts_key).await?;
+    Ok(())
+}
+
+/// Check a TOTP code and refuse to accept the same code twice within its window.
+async fn check_totp(db: &DbPool, user: &User, secret: &str, code: &str) -> Result<bool> {
+    let totp = totp_for(secret, &user.email)?;
+    let valid = totp.check_current(code.trim())
+        .map_err(|e| AppError::Internal(format!("Clock error: {e}")))?;
+    if !valid {
+        return Ok(false);
+    }
+    let mut redis = db.redis.clone();
+    let replay_window = TOTP_STEP_SECS * (2 * TOTP_SKEW as u64 + 1);
+    let fresh: bool = redis::cmd("SET")
+        .arg(format!("totp_used:{}:{}", user.id, code.trim()))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(replay_window)
+        .query_async::<_, Option<String>>(&mut redis)
+        .await?
+        .is_some();
+    Ok(fresh)
+}
@@ -1,3 +1,14 @@
 pub mod auth_service;
 pub mod room_service;
-pub mod message_service;
\ No newline at end of file
+pub mod message_service;
+pub mod mfa_service;
+pub mod account_service;
+pub mod oidc_service;
+pub mod access_token_service;
+pub mod lockout_service;
+pub mod admin_service;
+pub mod invite_service;
+pub mod moderation_service;
+pub mod room_invite_service;pub mod pin_service;
+pub mod posting_policy_service;
+pub mod workspace_service;
@@ -0,0 +1,127 @@
+/// Room moderation: kicks, bans and mutes.  Each needs the matching room permission
+/// (`kick` also covers mutes) and only works on members ranked below the moderator.
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::moderation::{KickRequest, RoomBan, RoomMute, SanctionRequest};
+use crate::models::room::{Permissions, RoomRole};
+use crate::repositories::{moderation_repo, room_repo};
+use crate::services::room_service;
+
+const MAX_REASON_LENGTH: usize = 500;
+const MAX_DURATION_SECS: i64 = 365 * 24 * 3600;
+
+fn reason(reason: Option<&str>) -> Result<Option<&str>> {
+    let reason = reason.map(str::trim).filter(|r| !r.is_empty());
+    if reason.is_some_and(|r| r.len() > MAX_REASON_LENGTH) {
+        return Err(AppError::BadRequest(format!("Reason must be at most {MAX_REASON_LENGTH} characters")));
+    }
+    Ok(reason)
+}
+
+fn expiry(duration_secs: Option<i64>) -> Result<Option<DateTime<Utc>>> {
+    match duration_secs {
+        Some(secs) if !(1..=MAX_DURATION_SECS).contains(&secs) => {
+            Err(AppError::BadRequest(format!("duration_secs must be 1-{MAX_DURATION_SECS}")))
+        }
+        Some(secs) => Ok(Some(Utc::now() + Duration::seconds(secs))),
+        None => Ok(None),
+    }
+}
+
+/// Check the moderator may act on `target_id`; returns the target's role if they're a member.
+async fn authorize(
+    db: &DbPool,
+    room_id: Uuid,
+    moderator_id: Uuid,
+    target_id: Uuid,
+    permission: Permissions,
+) -> Result<Option<RoomRole>> {
+    let access = room_service::require_permission(db, room_id, moderator_id, permission).await?;
+    access.ensure_active()?;
+    if moderator_id == target_id {
+        return Err(AppError::BadRequest("You can't moderate yourself".into()));
+    }
+    let target = room_repo::get_member(&db.pg, room_id, target_id).await?.map(|m| m.role());
+    let actor = access.role.unwrap_or(RoomRole::Guest);
+    if target.is_some_and(|role| role.rank() >= actor.rank()) {
+        return Err(AppError::Forbidden("You can't moderate a member ranked at or above you".into()));
+    }
+    Ok(target)
+}
+
+/// Remove a member.  They may rejoin unless the room is private or they're banned.
+pub async fn kick(db: &DbPool, room_id: Uuid, moderator_id: Uuid, target_id: Uuid, req: &KickRequest) -> Result<Option<String>> {
+    let reason = reason(req.reason.as_deref())?;
+    if authorize(db, room_id, moderator_id, target_id, Permissions::KICK).await?.is_none() {
+        return Err(AppError::NotFound("User is not a member of this room".into()));
+    }
+    room_repo::remove_room_member(&db.pg, room_id, target_id).await?;
+    Ok(reason.map(str::to_string))
+}
+
+/// Ban a member or anyone else; members are removed from the room.
+pub async fn ban(db: &DbPool, room_id: Uuid, moderator_id: Uuid, target_id: Uuid, req: &SanctionRequest) -> Result<RoomBan> {
+    let reason = reason(req.reason.as_deref())?;
+    let expires_at = expiry(req.duration_secs)?;
+    authorize(db, room_id, moderator_id, target_id, Permissions::BAN).await?;
+    moderation_repo::ban(&db.pg, room_id, target_id, moderator_id, reason, expires_at).await
+}
+
+pub async fn unban(db: &DbPool, room_id: Uuid, moderator_id: Uuid, target_id: Uuid) -> Result<()> {
+    room_service::require_permission(db, room_id, moderator_id, Permissions::BAN).await?.ensure_active()?;
+    if moderation_repo::unban(&db.pg, room_id, target_id).await? == 0 {
+        return Err(AppError::NotFound("User is not banned from this room".into()));
+    }
+    Ok(())
+}
+
+pub async fn list_bans(db: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Vec<RoomBan>> {
+    room_service::require_permission(db, room_id, user_id, Permissions::BAN).await?;
+    moderation_repo::list_bans(&db.pg, room_id).await
+}
+
+pub async fn mute(db: &DbPool, room_id: Uuid, moderator_id: Uuid, target_id: Uuid, req: &SanctionRequest) -> Result<RoomMute> {
+    let reason = reason(req.reason.as_deref())?;
+    let expires_at = expiry(req.duration_secs)?;
+    if authorize(db, room_id, moderator_id, target_id, Permissions::KICK).await?.is_none() {
+        return Err(AppError::NotFound("User is not a member of this room".into()));
+    }
+    moderation_repo::mute(&db.pg, room_id, target_id, moderator_id, reason, expires_at).await
+}
+
+pub async fn unmute(db: &DbPool, room_id: Uuid, moderator_id: Uuid, target_id: Uuid) -> Result<()> {
+    room_service::require_permission(db, room_id, moderator_id, Permissions::KICK).await?.ensure_active()?;
+    if moderation_repo::unmute(&db.pg, room_id, target_id).await? == 0 {
+        return Err(AppError::NotFound("User is not muted in this room".into()));
+    }
+    Ok(())
+}
+
+pub async fn list_mutes(db: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Vec<RoomMute>> {
+    room_service::require_permission(db, room_id, user_id, Permissions::KICK).await?;
+    moderation_repo::list_mutes(&db.pg, room_id).await
+}
+
+/// Refuse banned users; used wherever someone enters a room.
+pub async fn ensure_not_banned(db: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<()> {
+    match moderation_repo::active_ban(&db.pg, room_id, user_id).await? {
+        Some(ban) => Err(AppError::Forbidden(match ban.expires_at {
+            Some(until) => format!("You are banned from this room until {}", until.to_rfc3339()),
+            None => "You are banned from this room".into(),
+        })),
+        None => Ok(()),
+    }
+}
+
+pub async fn ensure_not_muted(db: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<()> {
+    match moderation_repo::active_mute(&db.pg, room_id, user_id).await? {
+        Some(mute) => Err(AppError::Forbidden(match mute.expires_at {
+            Some(until) => format!("You are muted in this room until {}", until.to_rfc3339()),
+            None => "You are muted in this room".into(),
+        })),
+        None => Ok(()),
+    }
+}
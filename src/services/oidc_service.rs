@@ -0,0 +1,123 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+
+use crate::config::{Config, RegistrationMode};
+use crate::db::{DbPool, redisdb};
+use crate::error::{AppError, Result};
+use crate::models::session::OidcPending;
+use crate::models::user::User;
+use crate::oidc::{IdTokenClaims, OidcProviders};
+use crate::repositories::{identity_repo, user_repo};
+use crate::services::{auth_service, workspace_service};
+use crate::services::auth_service::LoginOutcome;
+use crate::utils::jwt::JwtKeys;
+use crate::utils::token;
+
+/// Username attempts before giving up on a free JIT username.
+const USERNAME_ATTEMPTS: usize = 5;
+
+/// The callback URL registered with every provider.
+fn redirect_uri(cfg: &Config, provider: &str) -> String {
+    format!("{}/api/auth/oidc/{provider}/callback", cfg.app_base_url.trim_end_matches('/'))
+}
+
+/// Start an authorization-code + PKCE sign-in; returns the provider URL to redirect to.
+pub async fn begin(db: &DbPool, oidc: &OidcProviders, cfg: &Config, provider: &str) -> Result<String> {
+    let state = token::generate_token(32);
+    let pending = OidcPending {
+        provider:      provider.to_string(),
+        code_verifier: URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>()),
+        nonce:         token::generate_token(16),
+    };
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(pending.code_verifier.as_bytes()));
+    let url = oidc.authorization_url(provider, &redirect_uri(cfg, provider), &state, &pending.nonce, &challenge).await?;
+    let mut redis = db.redis.clone();
+    redisdb::set_oidc_pending(&mut redis, &state, &pending).await?;
+    Ok(url)
+}
+
+/// Finish sign-in: redeem the code, find or create the linked user, then continue like
+/// a password login, including the second factor when the account has 2FA on.
+pub async fn complete(
+    db: &DbPool,
+    oidc: &OidcProviders,
+    keys: &JwtKeys,
+    cfg: &Config,
+    provider: &str,
+    code: &str,
+    state: &str,
+) -> Result<LoginOutcome> {
+    let mut redis = db.redis.clone();
+    let pending = redisdb::take_oidc_pending(&mut redis, state).await?
+        .filter(|p| p.provider == provider)
+        .ok_or_else(|| AppError::BadRequest("Sign-in expired or was already used; start again".into()))?;
+    let claims = oidc.exchange_code(provider, code, &redirect_uri(cfg, provider), &pending.code_verifier, &pending.nonce).await?;
+
+    let user = match identity_repo::find_user(&db.pg, provider, &claims.sub).await? {
+        Some(user) => {
+            identity_repo::touch(&db.pg, provider, &claims.sub, claims.email.as_deref()).await?;
+            user
+        }
+        None => link_or_create(db, cfg, provider, &claims).await?,
+    };
+    auth_service::finish_login(db, keys, user).await
+}
+
+/// First sign-in with this identity.  An existing account is linked only when both
+/// sides have proven the address: the provider vouches for it, otherwise anyone able to
+/// set that address at the provider could take the account over; and the local account
+/// verified it, otherwise whoever registered it first with a password would keep access
+/// to the owner's account.
+async fn link_or_create(db: &DbPool, cfg: &Config, provider: &str, claims: &IdTokenClaims) -> Result<User> {
+    let email = claims.email.as_deref()
+        .ok_or_else(|| AppError::BadRequest("The provider did not share an email address".into()))?;
+
+    if let Some(existing) = user_repo::get_user_by_email(&db.pg, email).await? {
+        if !claims.email_verified() {
+            return Err(AppError::Conflict(
+                "An account with this email already exists; sign in with your password instead".into(),
+            ));
+        }
+        if !existing.is_email_verified() {
+            return Err(AppError::Conflict(
+                "An account with this email exists but its address was never verified; reset its password to claim it, then sign in again".into(),
+            ));
+        }
+        identity_repo::link(&db.pg, existing.id, provider, &claims.sub, Some(email)).await?;
+        tracing::info!("Linked {provider} identity to existing user {}", existing.id);
+        return Ok(existing);
+    }
+
+    // Sign-in can't carry an invite code, so new accounts only appear when registration is open.
+    if cfg.registration_mode != RegistrationMode::Open {
+        return Err(AppError::Forbidden("No account is linked to this sign-in and registration is not open".into()));
+    }
+    let base = username_base(claims.preferred_username.as_deref().unwrap_or(email));
+    for attempt in 0..USERNAME_ATTEMPTS {
+        let username = if attempt == 0 { base.clone() } else { format!("{base}-{}", token::generate_token(2)) };
+        if user_repo::get_user_by_username(&db.pg, &username).await?.is_some() {
+            continue;
+        }
+        let user = identity_repo::create_user_with_identity(
+            &db.pg, &username, email, claims.name.as_deref(), claims.email_verified(), provider, &claims.sub,
+        ).await?;
+        tracing::info!("Created user {} from {provider} sign-in", user.id);
+        workspace_service::join_default(db, cfg, user.id).await?;
+        return Ok(user);
+    }
+    Err(AppError::Conflict("Could not pick a free username; try again".into()))
+}
+
+/// A valid local username (3–50 chars of `[a-z0-9_.-]`) from a preferred username or email.
+fn username_base(source: &str) -> String {
+    let local = source.split('@').next().unwrap_or(source);
+    let mut name: String = local.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        .map(|c| c.to_ascii_lowercase())
+        .take(40)
+        .collect();
+    if name.len() < 3 {
+        name = format!("user-{name}");
+    }
+    name
+}
@@ -0,0 +1,45 @@
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::message::Message;
+use crate::models::pin::{PinnedMessage, RoomPin};
+use crate::models::room::Permissions;
+use crate::repositories::{message_repo, pin_repo};
+use crate::repositories::pin_repo::PinOutcome;
+use crate::services::room_service;
+
+/// The message, provided it belongs to `room_id`.
+async fn room_message(pool: &DbPool, room_id: Uuid, message_id: Uuid) -> Result<Message> {
+    message_repo::get_message(&pool.pg, message_id)
+        .await?
+        .filter(|m| m.room_id == room_id)
+        .ok_or_else(|| AppError::NotFound("Message not found".into()))
+}
+
+pub async fn pin_message(pool: &DbPool, cfg: &Config, room_id: Uuid, message_id: Uuid, user_id: Uuid) -> Result<RoomPin> {
+    room_service::require_permission(pool, room_id, user_id, Permissions::PIN).await?.ensure_active()?;
+    room_message(pool, room_id, message_id).await?;
+    match pin_repo::pin(&pool.pg, room_id, message_id, user_id, cfg.max_pins_per_room).await? {
+        PinOutcome::Pinned(pin) => Ok(pin),
+        PinOutcome::AlreadyPinned => Err(AppError::Conflict("Message is already pinned".into())),
+        PinOutcome::Full => Err(AppError::Conflict(format!(
+            "A room can have at most {} pinned messages; unpin one first", cfg.max_pins_per_room,
+        ))),
+    }
+}
+
+pub async fn unpin_message(pool: &DbPool, room_id: Uuid, message_id: Uuid, user_id: Uuid) -> Result<()> {
+    room_service::require_permission(pool, room_id, user_id, Permissions::PIN).await?.ensure_active()?;
+    room_message(pool, room_id, message_id).await?;
+    if pin_repo::unpin(&pool.pg, message_id).await? == 0 {
+        return Err(AppError::NotFound("Message is not pinned".into()));
+    }
+    Ok(())
+}
+
+pub async fn list_pins(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Vec<PinnedMessage>> {
+    room_service::visible_room(pool, room_id, user_id).await?;
+    pin_repo::list_pins(&pool.pg, room_id).await
+}
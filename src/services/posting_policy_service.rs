@@ -0,0 +1,98 @@
+/// Per-room posting rules, checked by `message_service::send_message` after the
+/// membership, SEND and mute checks.
+///
+/// Members holding KICK moderate the room and are exempt from slow mode and the
+/// content filters, though not from announcement mode.  Slow mode is tracked in Redis
+/// with one expiring key per member, set only once a message has passed every other check.
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::posting_policy::PostingPolicy;
+use crate::models::room::{Permissions, RoomRole};
+use crate::repositories::posting_policy_repo;
+use crate::services::message_service::MAX_CONTENT_LENGTH;
+use crate::services::room_service::{self, RoomAccess};
+
+fn slow_mode_key(room_id: Uuid, user_id: Uuid) -> String {
+    format!("slow_mode:{room_id}:{user_id}")
+}
+
+pub async fn policy(pool: &DbPool, room_id: Uuid) -> Result<PostingPolicy> {
+    Ok(posting_policy_repo::get_policy(&pool.pg, room_id).await?.unwrap_or_default())
+}
+
+pub async fn get_policy(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<PostingPolicy> {
+    room_service::visible_room(pool, room_id, user_id).await?;
+    policy(pool, room_id).await
+}
+
+pub async fn set_policy(pool: &DbPool, room_id: Uuid, user_id: Uuid, policy: &PostingPolicy) -> Result<PostingPolicy> {
+    room_service::require_permission(pool, room_id, user_id, Permissions::MANAGE_ROOM).await?.ensure_active()?;
+    if !(0..=86_400).contains(&policy.slow_mode_secs) {
+        return Err(AppError::BadRequest("slow_mode_secs must be between 0 and 86400".into()));
+    }
+    if let Some(role) = policy.min_post_role.as_deref() {
+        if RoomRole::from_name(role).is_none() {
+            return Err(AppError::BadRequest(format!("Unknown role: {role}")));
+        }
+    }
+    if let Some(max) = policy.max_message_length {
+        if max < 1 || max as usize > MAX_CONTENT_LENGTH {
+            return Err(AppError::BadRequest(format!("max_message_length must be between 1 and {MAX_CONTENT_LENGTH}")));
+        }
+    }
+    posting_policy_repo::set_policy(&pool.pg, room_id, policy, user_id).await
+}
+
+fn contains_link(content: &str) -> bool {
+    let lower = content.to_ascii_lowercase();
+    ["http://", "https://", "www."].iter().any(|p| lower.contains(p))
+}
+
+/// Fail with a policy error if `content` may not be posted right now.
+pub async fn check_post(pool: &DbPool, access: &RoomAccess, user_id: Uuid, content: &str) -> Result<()> {
+    let room_id = access.room.id;
+    let policy = policy(pool, room_id).await?;
+    let role = access.role.unwrap_or(RoomRole::Guest);
+
+    if let Some(min) = policy.min_post_role() {
+        if role.rank() < min.rank() {
+            return Err(AppError::PostingRestricted(
+                "READ_ONLY", format!("Only {}s and above can post in this room", min.name()),
+            ));
+        }
+    }
+    if access.permissions.contains(Permissions::KICK) {
+        return Ok(());
+    }
+    if let Some(max) = policy.max_message_length {
+        if content.chars().count() > max as usize {
+            return Err(AppError::PostingRestricted(
+                "MESSAGE_TOO_LONG", format!("Messages in this room are limited to {max} characters"),
+            ));
+        }
+    }
+    if !policy.allow_links && contains_link(content) {
+        return Err(AppError::PostingRestricted("LINKS_NOT_ALLOWED", "Links are not allowed in this room".into()));
+    }
+    if policy.slow_mode_secs > 0 {
+        let mut redis = pool.redis.clone();
+        let key = slow_mode_key(room_id, user_id);
+        let acquired: bool = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(policy.slow_mode_secs)
+            .query_async::<_, Option<String>>(&mut redis)
+            .await?
+            .is_some();
+        if !acquired {
+            let ttl: i64 = redis.ttl(&key).await?;
+            return Err(AppError::SlowMode(ttl.max(1) as u64));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,182 @@
+/// Ways into a private room: direct invitations answered by the invitee, shareable
+/// links, and join requests approved by the room's admins.  Bans apply to all three.
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::room::{Permissions, Room};
+use crate::models::room_invite::{CreateInviteLinkRequest, RoomInvitation, RoomInviteLink, RoomJoinRequest};
+use crate::repositories::{room_invite_repo, room_repo, user_repo, workspace_repo};
+use crate::services::{invite_service, moderation_service, room_service};
+use crate::utils::token;
+
+const DISPLAY_PREFIX_LEN: usize = 8;
+const MAX_LINK_EXPIRY_HOURS: i64 = 24 * 30;
+const MAX_REQUEST_MESSAGE_LENGTH: usize = 500;
+
+/// None of the ways in reach across workspaces, where outsiders see the room as
+/// missing, or into an archived room.
+async fn ensure_can_enter(db: &DbPool, room: &Room, user_id: Uuid) -> Result<()> {
+    if !workspace_repo::is_member(&db.pg, room.workspace_id, user_id).await? {
+        return Err(AppError::NotFound("Room not found".into()));
+    }
+    if room.is_archived() {
+        return Err(AppError::PostingRestricted("ROOM_ARCHIVED", "This room is archived".into()));
+    }
+    Ok(())
+}
+
+pub async fn invite_user(db: &DbPool, room_id: Uuid, inviter_id: Uuid, invitee_id: Uuid) -> Result<(RoomInvitation, Room)> {
+    let access = room_service::require_permission(db, room_id, inviter_id, Permissions::INVITE).await?;
+    access.ensure_active()?;
+    user_repo::get_user_by_id(&db.pg, invitee_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+    if !workspace_repo::is_member(&db.pg, access.room.workspace_id, invitee_id).await? {
+        return Err(AppError::NotFound("User not found".into()));
+    }
+    if room_repo::is_room_member(&db.pg, room_id, invitee_id).await? {
+        return Err(AppError::Conflict("User is already a member of this room".into()));
+    }
+    if moderation_service::ensure_not_banned(db, room_id, invitee_id).await.is_err() {
+        return Err(AppError::Conflict("User is banned from this room".into()));
+    }
+    let invitation = room_invite_repo::create_invitation(&db.pg, room_id, invitee_id, inviter_id)
+        .await?
+        .ok_or_else(|| AppError::Conflict("User already has a pending invitation to this room".into()))?;
+    Ok((invitation, access.room))
+}
+
+pub async fn list_room_invitations(db: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Vec<RoomInvitation>> {
+    room_service::require_permission(db, room_id, user_id, Permissions::INVITE).await?;
+    room_invite_repo::pending_for_room(&db.pg, room_id).await
+}
+
+pub async fn list_my_invitations(db: &DbPool, user_id: Uuid) -> Result<Vec<RoomInvitation>> {
+    room_invite_repo::pending_for_user(&db.pg, user_id).await
+}
+
+/// The inviter, or anyone who manages the room, may withdraw a pending invitation.
+pub async fn revoke_invitation(db: &DbPool, room_id: Uuid, user_id: Uuid, invitation_id: Uuid) -> Result<()> {
+    let not_found = || AppError::NotFound("Invitation not found".into());
+    let invitation = room_invite_repo::get_invitation(&db.pg, invitation_id)
+        .await?
+        .filter(|i| i.room_id == room_id)
+        .ok_or_else(not_found)?;
+    if invitation.inviter_id != Some(user_id) {
+        room_service::require_permission(db, room_id, user_id, Permissions::MANAGE_ROOM).await?;
+    }
+    room_invite_repo::resolve_invitation(&db.pg, invitation.id, "revoked")
+        .await?
+        .map(|_| ())
+        .ok_or_else(not_found)
+}
+
+/// Accept or decline one of your own invitations.
+pub async fn respond_to_invitation(db: &DbPool, user_id: Uuid, invitation_id: Uuid, accept: bool) -> Result<RoomInvitation> {
+    let not_found = || AppError::NotFound("Invitation not found".into());
+    let invitation = room_invite_repo::get_invitation(&db.pg, invitation_id)
+        .await?
+        .filter(|i| i.invitee_id == user_id && i.status == "pending")
+        .ok_or_else(not_found)?;
+    if accept {
+        let room = room_repo::get_room(&db.pg, invitation.room_id).await?.ok_or_else(not_found)?;
+        ensure_can_enter(db, &room, user_id).await?;
+        moderation_service::ensure_not_banned(db, invitation.room_id, user_id).await?;
+    }
+    room_invite_repo::resolve_invitation(&db.pg, invitation.id, if accept { "accepted" } else { "declined" })
+        .await?
+        .ok_or_else(not_found)
+}
+
+/// Returns the plaintext code, which is shown once and never stored.
+pub async fn create_link(db: &DbPool, room_id: Uuid, user_id: Uuid, req: &CreateInviteLinkRequest) -> Result<(String, RoomInviteLink)> {
+    room_service::require_permission(db, room_id, user_id, Permissions::MANAGE_ROOM).await?.ensure_active()?;
+    if req.max_uses.is_some_and(|n| n < 1) {
+        return Err(AppError::BadRequest("max_uses must be at least 1".into()));
+    }
+    let expires_at = match req.expires_in_hours {
+        Some(hours) if !(1..=MAX_LINK_EXPIRY_HOURS).contains(&hours) => {
+            return Err(AppError::BadRequest(format!("expires_in_hours must be 1-{MAX_LINK_EXPIRY_HOURS}")));
+        }
+        Some(hours) => Some(Utc::now() + Duration::hours(hours)),
+        None => None,
+    };
+    let plain = token::generate_token(12);
+    let link = room_invite_repo::create_link(
+        &db.pg, room_id, user_id, &plain[..DISPLAY_PREFIX_LEN], &invite_service::hash_code(&plain), req.max_uses, expires_at,
+    ).await?;
+    Ok((plain, link))
+}
+
+pub async fn list_links(db: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Vec<RoomInviteLink>> {
+    room_service::require_permission(db, room_id, user_id, Permissions::MANAGE_ROOM).await?;
+    room_invite_repo::list_links(&db.pg, room_id).await
+}
+
+pub async fn revoke_link(db: &DbPool, room_id: Uuid, user_id: Uuid, link_id: Uuid) -> Result<()> {
+    room_service::require_permission(db, room_id, user_id, Permissions::MANAGE_ROOM).await?;
+    if room_invite_repo::revoke_link(&db.pg, room_id, link_id).await? == 0 {
+        return Err(AppError::NotFound("Invite link not found".into()));
+    }
+    Ok(())
+}
+
+/// Join the link's room.  Members following a link again don't use it up.
+pub async fn redeem_link(db: &DbPool, user_id: Uuid, code: &str) -> Result<Room> {
+    let invalid = || AppError::BadRequest("Invalid or expired invite link".into());
+    let link = room_invite_repo::find_usable_link(&db.pg, &invite_service::hash_code(code))
+        .await?
+        .ok_or_else(invalid)?;
+    let room = room_repo::get_room(&db.pg, link.room_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Room not found".into()))?;
+    ensure_can_enter(db, &room, user_id).await?;
+    if room_repo::is_room_member(&db.pg, room.id, user_id).await? {
+        return Ok(room);
+    }
+    moderation_service::ensure_not_banned(db, room.id, user_id).await?;
+    room_invite_repo::redeem_link(&db.pg, link.id, user_id).await?.ok_or_else(invalid)?;
+    Ok(room)
+}
+
+pub async fn request_to_join(db: &DbPool, room_id: Uuid, user_id: Uuid, message: Option<&str>) -> Result<RoomJoinRequest> {
+    let room = room_repo::get_room(&db.pg, room_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Room not found".into()))?;
+    ensure_can_enter(db, &room, user_id).await?;
+    if !room.is_private {
+        return Err(AppError::BadRequest("This room is public; join it directly".into()));
+    }
+    if room_repo::is_room_member(&db.pg, room_id, user_id).await? {
+        return Err(AppError::Conflict("You are already a member of this room".into()));
+    }
+    moderation_service::ensure_not_banned(db, room_id, user_id).await?;
+    let message = message.map(str::trim).filter(|m| !m.is_empty());
+    if message.is_some_and(|m| m.len() > MAX_REQUEST_MESSAGE_LENGTH) {
+        return Err(AppError::BadRequest(format!("Message must be at most {MAX_REQUEST_MESSAGE_LENGTH} characters")));
+    }
+    room_invite_repo::create_join_request(&db.pg, room_id, user_id, message)
+        .await?
+        .ok_or_else(|| AppError::Conflict("You already asked to join this room".into()))
+}
+
+pub async fn list_join_requests(db: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Vec<RoomJoinRequest>> {
+    room_service::require_permission(db, room_id, user_id, Permissions::MANAGE_ROOM).await?;
+    room_invite_repo::pending_join_requests(&db.pg, room_id).await
+}
+
+pub async fn review_join_request(db: &DbPool, room_id: Uuid, reviewer_id: Uuid, request_id: Uuid, approve: bool) -> Result<RoomJoinRequest> {
+    let access = room_service::require_permission(db, room_id, reviewer_id, Permissions::MANAGE_ROOM).await?;
+    if approve {
+        access.ensure_active()?;
+        if let Some(request) = room_invite_repo::get_join_request(&db.pg, request_id).await? {
+            moderation_service::ensure_not_banned(db, room_id, request.user_id).await
+                .map_err(|_| AppError::Conflict("User is banned from this room".into()))?;
+        }
+    }
+    room_invite_repo::review_join_request(&db.pg, room_id, request_id, reviewer_id, approve)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Join request not found".into()))
+}
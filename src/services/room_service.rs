@@ -1,88 +1,550 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
 use uuid::Uuid;
 
 use crate::db::DbPool;
-use crate::models::room::{Room, CreateRoomRequest, RoomMember, UpdateRoomRequest};
+use crate::models::message::Message;
+use crate::models::room::{
+    Room, CreateRoomRequest, ListRoomsParams, RoomDirectoryEntry, RoomMember, RoomRole, RoomSort, Permissions,
+    UpdateRoomRequest,
+};
 use crate::error::{Result, AppError};
-use crate::repositories::room_repo;
+use crate::repositories::{message_repo, room_repo, user_repo, workspace_repo};
+use crate::services::{moderation_service, workspace_service};
+use crate::utils::search;
 
+/// The caller's standing in a room, as used by permission checks.
+pub struct RoomAccess {
+    pub room:        Room,
+    /// `None` when the caller isn't a member.
+    pub role:        Option<RoomRole>,
+    pub permissions: Permissions,
+}
+
+impl RoomAccess {
+    pub fn is_member(&self) -> bool {
+        self.role.is_some()
+    }
+
+    /// Archived rooms are read-only.
+    pub fn ensure_active(&self) -> Result<()> {
+        if self.room.is_archived() {
+            return Err(AppError::PostingRestricted("ROOM_ARCHIVED", "This room is archived and read-only".into()));
+        }
+        Ok(())
+    }
+
+    /// Fail with 403 unless the caller is a member holding `permission`.
+    pub fn require(&self, permission: Permissions) -> Result<()> {
+        if self.is_member() && self.permissions.contains(permission) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(format!(
+                "You need the {} permission in this room", permission.names().join(", "),
+            )))
+        }
+    }
+}
+
+/// What `role` may do in this room: the room's override if it has one, else the
+/// default.  Owners always hold every permission so a room can't lock itself out.
+pub async fn role_permissions(pool: &DbPool, room_id: Uuid, role: RoomRole) -> Result<Permissions> {
+    if role == RoomRole::Owner {
+        return Ok(Permissions::all());
+    }
+    Ok(room_repo::get_permission_override(&pool.pg, room_id, role)
+        .await?
+        .unwrap_or_else(|| role.default_permissions()))
+}
+
+/// The room, provided `user_id` belongs to its workspace; other tenants' rooms look
+/// the same as missing ones.
+async fn workspace_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Room> {
+    let room = room_repo::get_room(&pool.pg, room_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Room not found".into()))?;
+    if !workspace_repo::is_member(&pool.pg, room.workspace_id, user_id).await? {
+        return Err(AppError::NotFound("Room not found".into()));
+    }
+    Ok(room)
+}
+
+/// The workspace a room belongs to, for addressing per-user notices about it.
+pub async fn workspace_of(pool: &DbPool, room_id: Uuid) -> Result<Uuid> {
+    room_repo::get_room(&pool.pg, room_id)
+        .await?
+        .map(|room| room.workspace_id)
+        .ok_or_else(|| AppError::NotFound("Room not found".into()))
+}
+
+pub async fn room_access(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<RoomAccess> {
+    let room = workspace_room(pool, room_id, user_id).await?;
+    let role = room_repo::get_member(&pool.pg, room_id, user_id).await?.map(|m| m.role());
+    let permissions = match role {
+        Some(role) => role_permissions(pool, room_id, role).await?,
+        None => Permissions::empty(),
+    };
+    Ok(RoomAccess { room, role, permissions })
+}
+
+/// Load the caller's access to a room that must be visible to them.  Private rooms
+/// look the same as missing ones to non-members.
+pub async fn visible_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<RoomAccess> {
+    let access = room_access(pool, room_id, user_id).await?;
+    if access.room.is_private && !access.is_member() {
+        return Err(AppError::NotFound("Room not found".into()));
+    }
+    Ok(access)
+}
+
+pub async fn require_permission(pool: &DbPool, room_id: Uuid, user_id: Uuid, permission: Permissions) -> Result<RoomAccess> {
+    let access = visible_room(pool, room_id, user_id).await?;
+    access.require(permission)?;
+    Ok(access)
+}
 
-pub async fn create_room(pool: &DbPool, req: &CreateRoomRequest, user_id: Uuid) -> Result<Room> {
+pub async fn create_room(pool: &DbPool, req: &CreateRoomRequest, user_id: Uuid, workspace_id: Uuid) -> Result<Room> {
     if req.name.is_empty() || req.name.len() > 100 {
         return Err(AppError::BadRequest("Room name must be 1-100 characters".into()));
     }
+    workspace_service::ensure_member(pool, workspace_id, user_id).await?;
     let room = room_repo::create_room(
         &pool.pg,
         &req.name,
         req.description.as_deref(),
         req.is_private.unwrap_or(false),
         user_id,
+        workspace_id,
     )
     .await?;
-    room_repo::add_room_member(&pool.pg, room.id, user_id, "admin").await?;
+    room_repo::add_room_member(&pool.pg, room.id, user_id, RoomRole::Owner.name()).await?;
     Ok(room)
 }
 
-pub async fn get_room(pool: &DbPool, room_id: Uuid) -> Result<Room> {
-    room_repo::get_room(&pool.pg, room_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Room not found".into()))
+pub async fn get_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Room> {
+    Ok(visible_room(pool, room_id, user_id).await?.room)
 }
 
-pub async fn list_room(pool: &DbPool, limit: Option<i32>) -> Result<Vec<Room>> {
-    room_repo::list_room(&pool.pg, false, limit.unwrap_or(10))
-        .await
-        .map_err(|e| AppError::from(e))
+const MAX_TAGS: usize = 10;
+const MAX_TAG_LENGTH: usize = 32;
+
+/// `sort:key:id`, so a cursor can't be replayed against a different order.
+fn encode_cursor(sort: RoomSort, entry: &RoomDirectoryEntry) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}:{}", sort.name(), entry.sort_key, entry.room.id))
 }
 
-pub async fn update_room(pool: &DbPool, room_id: Uuid, user_id: Uuid, req: &UpdateRoomRequest) -> Result<Room> {
-    ensure_user_admin_or_creator(pool, room_id, user_id).await?;
-    room_repo::update_room(&pool.pg, room_id, req.name.as_deref(), req.description.as_deref(), req.is_private)
-        .await
-        .map_err(|e| AppError::from(e))
+fn decode_cursor(sort: RoomSort, cursor: &str) -> Result<(i64, Uuid)> {
+    let invalid = || AppError::BadRequest("Invalid cursor".into());
+    let raw = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+    let mut parts = raw.splitn(3, ':');
+    if parts.next() != Some(sort.name()) {
+        return Err(invalid());
+    }
+    let key = parts.next().and_then(|k| k.parse().ok()).ok_or_else(invalid)?;
+    let id = parts.next().and_then(|id| id.parse().ok()).ok_or_else(invalid)?;
+    Ok((key, id))
 }
 
-pub async fn delete_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<()> {
-    ensure_user_admin_or_creator(pool, room_id, user_id).await?;
-    let affected = room_repo::delete_room(&pool.pg, room_id)
-        .await?;
-    if affected == 0 {
-        return Err(AppError::NotFound("Room not found".into()));
+/// One page of the room directory and the cursor for the next, if any.  With
+/// `member_id` it lists that user's rooms, private ones included.
+pub async fn directory(
+    pool: &DbPool,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    member_id: Option<Uuid>,
+    params: &ListRoomsParams,
+) -> Result<(Vec<RoomDirectoryEntry>, Option<String>)> {
+    workspace_service::ensure_member(pool, workspace_id, user_id).await?;
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let pattern = search::contains_pattern(params.q.as_deref());
+    let tag = params.tag.as_deref().map(|t| t.trim().to_lowercase());
+    let query = room_repo::DirectoryQuery {
+        workspace_id,
+        member_id,
+        include_archived: params.include_archived.unwrap_or(false),
+        pattern:          pattern.as_deref(),
+        tag:              tag.as_deref(),
+        sort:             params.sort,
+        after:            params.cursor.as_deref().map(|c| decode_cursor(params.sort, c)).transpose()?,
+        // One extra row tells us whether there is another page.
+        limit:            limit + 1,
+    };
+    let mut rooms = room_repo::list_directory(&pool.pg, &query).await?;
+    let next_cursor = if rooms.len() as i64 > limit {
+        rooms.truncate(limit as usize);
+        rooms.last().map(|last| encode_cursor(params.sort, last))
+    } else {
+        None
+    };
+    Ok((rooms, next_cursor))
+}
+
+/// Lowercase, de-duplicate and validate directory tags.
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || tag.len() > MAX_TAG_LENGTH
+            || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(AppError::BadRequest(format!(
+                "Tags must be 1-{MAX_TAG_LENGTH} letters, digits or dashes: {tag:?}"
+            )));
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(AppError::BadRequest(format!("A room can have at most {MAX_TAGS} tags")));
+    }
+    Ok(normalized)
+}
+
+pub async fn set_tags(pool: &DbPool, room_id: Uuid, user_id: Uuid, tags: &[String]) -> Result<Vec<String>> {
+    require_permission(pool, room_id, user_id, Permissions::MANAGE_ROOM).await?.ensure_active()?;
+    let tags = normalize_tags(tags)?;
+    room_repo::set_tags(&pool.pg, room_id, &tags).await?;
+    Ok(tags)
+}
+
+pub async fn tag_counts(pool: &DbPool, workspace_id: Uuid, user_id: Uuid) -> Result<Vec<(String, i64)>> {
+    workspace_service::ensure_member(pool, workspace_id, user_id).await?;
+    room_repo::tag_counts(&pool.pg, workspace_id, 100).await
+}
+
+/// Result of `update_room`: the room as stored, which fields actually changed, and the
+/// system message recording them (`None` when nothing changed).
+pub struct RoomUpdate {
+    pub room:    Room,
+    pub changed: Vec<&'static str>,
+    pub message: Option<Message>,
+}
+
+const MAX_TOPIC_LENGTH: usize = 250;
+const MAX_RULES_LENGTH: usize = 4_000;
+const MAX_SETTINGS_BYTES: usize = 16 * 1024;
+
+fn validate_update(req: &UpdateRoomRequest) -> Result<()> {
+    if let Some(name) = &req.name {
+        if name.is_empty() || name.len() > 100 {
+            return Err(AppError::BadRequest("Room name must be 1-100 characters".into()));
+        }
+    }
+    if req.topic.as_ref().is_some_and(|t| t.chars().count() > MAX_TOPIC_LENGTH) {
+        return Err(AppError::BadRequest(format!("Topic must be at most {MAX_TOPIC_LENGTH} characters")));
+    }
+    if req.rules.as_ref().is_some_and(|r| r.len() > MAX_RULES_LENGTH) {
+        return Err(AppError::BadRequest(format!("Rules must be at most {MAX_RULES_LENGTH} bytes")));
+    }
+    if let Some(url) = req.avatar_url.as_deref().filter(|u| !u.is_empty()) {
+        if !(url.starts_with("https://") || url.starts_with("http://")) || url.len() > 2048 {
+            return Err(AppError::BadRequest("Avatar must be an http(s) URL".into()));
+        }
+    }
+    if let Some(settings) = &req.settings {
+        if !settings.is_object() {
+            return Err(AppError::BadRequest("Settings must be a JSON object".into()));
+        }
+        if settings.to_string().len() > MAX_SETTINGS_BYTES {
+            return Err(AppError::BadRequest(format!("Settings must be at most {MAX_SETTINGS_BYTES} bytes")));
+        }
     }
     Ok(())
 }
 
-pub async fn join_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<()> {
-    let room = room_repo::get_room(&pool.pg, room_id)
+/// Fields that differ between `old` and `new`, each with a phrase for the system message.
+fn room_changes(old: &Room, new: &Room) -> Vec<(&'static str, String)> {
+    let text = |what: &str, value: &Option<String>| match value {
+        Some(v) => format!("changed the {what} to \"{v}\""),
+        None => format!("cleared the {what}"),
+    };
+    let mut changes = Vec::new();
+    if old.name != new.name {
+        changes.push(("name", format!("renamed the room to \"{}\"", new.name)));
+    }
+    if old.topic != new.topic {
+        changes.push(("topic", text("topic", &new.topic)));
+    }
+    if old.description != new.description {
+        changes.push(("description", text("description", &new.description)));
+    }
+    if old.avatar_url != new.avatar_url {
+        let phrase = if new.avatar_url.is_some() { "changed the room avatar" } else { "removed the room avatar" };
+        changes.push(("avatar_url", phrase.to_string()));
+    }
+    if old.rules != new.rules {
+        let phrase = if new.rules.is_some() { "updated the room rules" } else { "removed the room rules" };
+        changes.push(("rules", phrase.to_string()));
+    }
+    if old.is_private != new.is_private {
+        let phrase = if new.is_private { "made the room private" } else { "made the room public" };
+        changes.push(("is_private", phrase.to_string()));
+    }
+    if old.settings != new.settings {
+        changes.push(("settings", "updated the room settings".to_string()));
+    }
+    changes
+}
+
+/// Apply `req` and, if anything changed, record it in the room's history.
+pub async fn update_room(pool: &DbPool, room_id: Uuid, user_id: Uuid, req: &UpdateRoomRequest) -> Result<RoomUpdate> {
+    let access = require_permission(pool, room_id, user_id, Permissions::MANAGE_ROOM).await?;
+    access.ensure_active()?;
+    validate_update(req)?;
+    let room = room_repo::update_room(&pool.pg, room_id, req).await?;
+    let changes = room_changes(&access.room, &room);
+    if changes.is_empty() {
+        return Ok(RoomUpdate { room, changed: Vec::new(), message: None });
+    }
+
+    let phrases: Vec<&str> = changes.iter().map(|(_, phrase)| phrase.as_str()).collect();
+    let changed: Vec<&'static str> = changes.iter().map(|&(field, _)| field).collect();
+    let message = record_event(
+        pool, room_id, user_id, &phrases.join(", "),
+        serde_json::json!({ "event": "room_updated", "changed": changed }),
+    ).await?;
+    Ok(RoomUpdate { room, changed, message: Some(message) })
+}
+
+/// Write a system message "<username> <what>" into the room's history.
+async fn record_event(pool: &DbPool, room_id: Uuid, user_id: Uuid, what: &str, metadata: serde_json::Value) -> Result<Message> {
+    let user = user_repo::get_user_by_id(&pool.pg, user_id)
         .await?
-        .ok_or_else(|| AppError::NotFound("Room not found".into()))?;
+        .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+    let content = format!("{} {what}", user.username);
+    message_repo::create_system_message(&pool.pg, room_id, user_id, &content, &metadata).await
+}
+
+pub async fn archive_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Room> {
+    let access = require_permission(pool, room_id, user_id, Permissions::MANAGE_ROOM).await?;
+    if access.room.is_archived() {
+        return Err(AppError::Conflict("Room is already archived".into()));
+    }
+    let room = room_repo::archive_room(&pool.pg, room_id, user_id, None).await?;
+    record_event(pool, room_id, user_id, "archived the room", serde_json::json!({ "event": "room_archived" })).await?;
+    Ok(room)
+}
+
+/// Restore an archived room.  This also cancels a pending deletion.
+pub async fn unarchive_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Room> {
+    let access = require_permission(pool, room_id, user_id, Permissions::MANAGE_ROOM).await?;
+    if !access.room.is_archived() {
+        return Err(AppError::Conflict("Room is not archived".into()));
+    }
+    let room = room_repo::unarchive_room(&pool.pg, room_id).await?;
+    record_event(pool, room_id, user_id, "restored the room", serde_json::json!({ "event": "room_unarchived" })).await?;
+    Ok(room)
+}
 
+/// Deleting takes the whole history with it, so only the owner may, and it doesn't
+/// happen at once: the room is archived and purged `delay` later unless restored.
+pub async fn delete_room(pool: &DbPool, room_id: Uuid, user_id: Uuid, delay: Duration) -> Result<Room> {
+    let access = visible_room(pool, room_id, user_id).await?;
+    if access.role != Some(RoomRole::Owner) {
+        return Err(AppError::Forbidden("Only the room owner can delete it".into()));
+    }
+    if access.room.purge_after.is_some() {
+        return Err(AppError::Conflict("Room is already scheduled for deletion".into()));
+    }
+    let purge_after = Utc::now() + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero());
+    room_repo::archive_room(&pool.pg, room_id, user_id, Some(purge_after)).await
+}
+
+/// Rooms purged per pass of the purge job.
+const PURGE_BATCH: i64 = 100;
+
+/// Hard-delete rooms whose deletion delay has run out.  Returns how many went.
+pub async fn purge_due_rooms(pool: &DbPool) -> Result<u64> {
+    let mut purged = 0;
+    for room_id in room_repo::rooms_due_for_purge(&pool.pg, PURGE_BATCH).await? {
+        purged += room_repo::delete_room(&pool.pg, room_id).await?;
+        tracing::info!("Purged room {room_id}");
+    }
+    Ok(purged)
+}
+
+/// Returns the room so the caller can greet the new member with its rules.
+/// Join a room from a connection in `active_workspace`; rooms in the user's other
+/// workspaces are refused before anything is written.
+pub async fn join_room(pool: &DbPool, room_id: Uuid, user_id: Uuid, active_workspace: Option<Uuid>) -> Result<Room> {
+    let room = workspace_room(pool, room_id, user_id).await?;
+    if active_workspace != Some(room.workspace_id) {
+        return Err(AppError::Forbidden("Switch to this room's workspace to join it".into()));
+    }
+
+    if room.is_archived() {
+        return Err(AppError::PostingRestricted("ROOM_ARCHIVED", "This room is archived".into()));
+    }
     if room.is_private && !room_repo::is_room_member(&pool.pg, room_id, user_id).await? {
-        return Err(AppError::Forbidden("Room is private".into()));
+        return Err(AppError::Forbidden("Room is private; ask for an invitation or request to join".into()));
     }
-    room_repo::add_room_member(&pool.pg, room_id, user_id, "member").await
+    moderation_service::ensure_not_banned(pool, room_id, user_id).await?;
+    room_repo::add_room_member(&pool.pg, room_id, user_id, RoomRole::Member.name()).await?;
+    Ok(room)
 }
 
-pub async fn leave_room(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<()> {
-    room_repo::remove_room_member(&pool.pg, room_id, user_id).await
+/// Ownership moved from `previous` to `new`; callers announce it to the room.
+#[derive(Debug, Clone, Copy)]
+pub struct OwnerChange {
+    pub previous: Uuid,
+    pub new:      Uuid,
 }
 
-pub async fn get_room_members(pool: &DbPool, room_id: Uuid) -> Result<Vec<RoomMember>> {
-    room_repo::get_room(&pool.pg, room_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Room not found".into()))?;
+/// Leave a room.  The owner can't just walk away: ownership goes to `successor`, or
+/// to the longest-standing admin when none is named.
+pub async fn leave_room(pool: &DbPool, room_id: Uuid, user_id: Uuid, successor: Option<Uuid>) -> Result<Option<OwnerChange>> {
+    let Some(member) = room_repo::get_member(&pool.pg, room_id, user_id).await? else {
+        return Ok(None);
+    };
+    if member.role() != RoomRole::Owner {
+        room_repo::remove_room_member(&pool.pg, room_id, user_id).await?;
+        return Ok(None);
+    }
+    if room_repo::get_room_members(&pool.pg, room_id).await?.len() <= 1 {
+        return Err(AppError::Conflict("You are the last member; delete the room instead".into()));
+    }
+    match room_repo::leave_as_owner(&pool.pg, room_id, user_id, successor).await? {
+        Some(new) => Ok(Some(OwnerChange { previous: user_id, new })),
+        None if successor.is_some() => Err(AppError::BadRequest("The successor must be another member of this room".into())),
+        None => Err(AppError::Conflict("There is no admin to take over; choose a successor".into())),
+    }
+}
+
+/// The owner is being removed from the workspace, so they can't name a successor or be
+/// refused: each room they own there goes to the longest-standing member of the highest
+/// remaining role.  A room with nobody else in it is left as it is and loses its last
+/// member with them.
+pub async fn hand_over_rooms(pool: &DbPool, workspace_id: Uuid, owner_id: Uuid) -> Result<Vec<(Uuid, OwnerChange)>> {
+    let mut changes = Vec::new();
+    for room_id in room_repo::owned_in_workspace(&pool.pg, workspace_id, owner_id).await? {
+        let Some(successor) = room_repo::next_owner(&pool.pg, room_id, owner_id).await? else { continue };
+        if let Some(new) = room_repo::leave_as_owner(&pool.pg, room_id, owner_id, Some(successor)).await? {
+            changes.push((room_id, OwnerChange { previous: owner_id, new }));
+        }
+    }
+    Ok(changes)
+}
+
+pub async fn transfer_ownership(pool: &DbPool, room_id: Uuid, owner_id: Uuid, new_owner_id: Uuid) -> Result<OwnerChange> {
+    let access = visible_room(pool, room_id, owner_id).await?;
+    if access.role != Some(RoomRole::Owner) {
+        return Err(AppError::Forbidden("Only the room owner can transfer ownership".into()));
+    }
+    if owner_id == new_owner_id {
+        return Err(AppError::BadRequest("You already own this room".into()));
+    }
+    if !room_repo::transfer_ownership(&pool.pg, room_id, owner_id, new_owner_id).await? {
+        return Err(AppError::NotFound("The new owner must be a member of this room".into()));
+    }
+    Ok(OwnerChange { previous: owner_id, new: new_owner_id })
+}
+
+pub async fn get_room_members(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<Vec<RoomMember>> {
+    visible_room(pool, room_id, user_id).await?;
     room_repo::get_room_members(&pool.pg, room_id).await
 }
 
+/// Members currently holding `permission`, e.g. to notify whoever can act on something.
+pub async fn members_with_permission(pool: &DbPool, room_id: Uuid, permission: Permissions) -> Result<Vec<Uuid>> {
+    let mut granted = HashMap::new();
+    let mut user_ids = Vec::new();
+    for member in room_repo::get_room_members(&pool.pg, room_id).await? {
+        let role = member.role();
+        let permissions = match granted.get(&role) {
+            Some(&p) => p,
+            None => {
+                let p = role_permissions(pool, room_id, role).await?;
+                granted.insert(role, p);
+                p
+            }
+        };
+        if permissions.contains(permission) {
+            user_ids.push(member.user_id);
+        }
+    }
+    Ok(user_ids)
+}
 
-async fn ensure_user_admin_or_creator(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<()> {
-    let room = room_repo::get_room(&pool.pg, room_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Room not found".into()))?;
-    if room.created_by == user_id {
-        return Ok(())
+/// Callers manage only what ranks strictly below them, and ownership is never handed
+/// out this way.
+fn ensure_outranks(actor: RoomRole, role: RoomRole) -> Result<()> {
+    if role == RoomRole::Owner {
+        return Err(AppError::Forbidden("Ownership can only be transferred by the owner".into()));
+    }
+    if actor.rank() <= role.rank() {
+        return Err(AppError::Forbidden(format!("Only roles above {} can manage it", role.name())));
     }
-    match room_repo::get_member_role(&pool.pg, room_id, user_id).await? {
-        Some(ref role) if role == "admin" => Ok(()),
-        _ => Err(AppError::Forbidden("Only room admin can perform this action".into())),
+    Ok(())
+}
+
+pub async fn change_member_role(
+    pool: &DbPool,
+    room_id: Uuid,
+    actor_id: Uuid,
+    target_id: Uuid,
+    role: RoomRole,
+) -> Result<RoomMember> {
+    let access = require_permission(pool, room_id, actor_id, Permissions::MANAGE_ROOM).await?;
+    access.ensure_active()?;
+    let actor_role = access.role.unwrap_or(RoomRole::Guest);
+    if actor_id == target_id {
+        return Err(AppError::BadRequest("You can't change your own role".into()));
     }
-}
\ No newline at end of file
+    let target = room_repo::get_member(&pool.pg, room_id, target_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User is not a member of this room".into()))?;
+    ensure_outranks(actor_role, target.role())?;
+    ensure_outranks(actor_role, role)?;
+
+    room_repo::set_member_role(&pool.pg, room_id, target_id, role).await?;
+    Ok(RoomMember { role: role.name().to_string(), ..target })
+}
+
+/// Effective permissions of every role in the room, which ones are overridden, and the
+/// caller's own.
+pub async fn get_permissions(pool: &DbPool, room_id: Uuid, user_id: Uuid) -> Result<serde_json::Value> {
+    let access = visible_room(pool, room_id, user_id).await?;
+    let overrides: BTreeMap<&str, Permissions> = room_repo::get_permission_overrides(&pool.pg, room_id)
+        .await?
+        .into_iter()
+        .map(|(role, permissions)| (role.name(), permissions))
+        .collect();
+    let roles: BTreeMap<&str, serde_json::Value> = RoomRole::ALL.iter()
+        .map(|&role| {
+            let overridden = overrides.get(role.name()).copied().filter(|_| role != RoomRole::Owner);
+            (role.name(), serde_json::json!({
+                "permissions": overridden.unwrap_or_else(|| role.default_permissions()),
+                "overridden":  overridden.is_some(),
+            }))
+        })
+        .collect();
+    Ok(serde_json::json!({
+        "roles":            roles,
+        "your_role":        access.role,
+        "your_permissions": access.permissions,
+    }))
+}
+
+pub async fn set_role_permissions(
+    pool: &DbPool,
+    room_id: Uuid,
+    actor_id: Uuid,
+    role: RoomRole,
+    permissions: Permissions,
+) -> Result<()> {
+    let access = require_permission(pool, room_id, actor_id, Permissions::MANAGE_ROOM).await?;
+    access.ensure_active()?;
+    ensure_outranks(access.role.unwrap_or(RoomRole::Guest), role)?;
+    room_repo::set_permission_override(&pool.pg, room_id, role, permissions).await
+}
+
+pub async fn reset_role_permissions(pool: &DbPool, room_id: Uuid, actor_id: Uuid, role: RoomRole) -> Result<()> {
+    let access = require_permission(pool, room_id, actor_id, Permissions::MANAGE_ROOM).await?;
+    access.ensure_active()?;
+    ensure_outranks(access.role.unwrap_or(RoomRole::Guest), role)?;
+    room_repo::delete_permission_override(&pool.pg, room_id, role).await
+}
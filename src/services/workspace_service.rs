@@ -0,0 +1,190 @@
+/// Workspaces partition the deployment between tenants.  Accounts are global, but a
+/// user only sees rooms and people in workspaces they belong to, and each session acts
+/// in one active workspace carried in its JWT.
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::error::{AppError, Result};
+use crate::models::session::AuthTokens;
+use crate::models::user::UserResponse;
+use crate::models::workspace::{Workspace, WorkspaceInvitation, WorkspaceMember, WorkspaceRole};
+use crate::repositories::{user_repo, workspace_repo};
+use crate::services::{auth_service, room_service};
+use crate::services::room_service::OwnerChange;
+use crate::utils::jwt::JwtKeys;
+use crate::utils::search;
+
+const MAX_SEARCH_RESULTS: i64 = 50;
+
+/// Fail unless `user_id` belongs to the workspace.  Outsiders get 404, so workspace
+/// ids can't be probed.
+pub async fn ensure_member(db: &DbPool, workspace_id: Uuid, user_id: Uuid) -> Result<WorkspaceMember> {
+    workspace_repo::get_member(&db.pg, workspace_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Workspace not found".into()))
+}
+
+async fn ensure_admin(db: &DbPool, workspace_id: Uuid, user_id: Uuid) -> Result<WorkspaceRole> {
+    let role = ensure_member(db, workspace_id, user_id).await?.role();
+    if role.rank() < WorkspaceRole::Admin.rank() {
+        return Err(AppError::Forbidden("Only workspace admins can manage members".into()));
+    }
+    Ok(role)
+}
+
+fn validate_slug(slug: &str) -> Result<()> {
+    let valid = (3..=50).contains(&slug.len())
+        && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-');
+    if !valid {
+        return Err(AppError::BadRequest("Slug must be 3-50 lowercase letters, digits or dashes".into()));
+    }
+    Ok(())
+}
+
+pub async fn create_workspace(db: &DbPool, user_id: Uuid, name: &str, slug: &str) -> Result<Workspace> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::BadRequest("Workspace name must be 1-100 characters".into()));
+    }
+    validate_slug(slug)?;
+    workspace_repo::create_workspace(&db.pg, slug, name, user_id)
+        .await?
+        .ok_or_else(|| AppError::Conflict("That slug is already taken".into()))
+}
+
+pub async fn list_my_workspaces(db: &DbPool, user_id: Uuid) -> Result<Vec<Workspace>> {
+    workspace_repo::list_for_user(&db.pg, user_id).await
+}
+
+pub async fn list_members(db: &DbPool, workspace_id: Uuid, user_id: Uuid) -> Result<Vec<WorkspaceMember>> {
+    ensure_member(db, workspace_id, user_id).await?;
+    workspace_repo::list_members(&db.pg, workspace_id).await
+}
+
+/// Admins invite existing accounts, at a role below their own.  Whether the username
+/// exists, is already a member or already has a pending invitation is not revealed, so
+/// this can't be used to discover accounts in other tenants.
+pub async fn invite_member(
+    db: &DbPool,
+    workspace_id: Uuid,
+    actor_id: Uuid,
+    username: &str,
+    role: WorkspaceRole,
+) -> Result<()> {
+    let actor_role = ensure_admin(db, workspace_id, actor_id).await?;
+    if role.rank() >= actor_role.rank() {
+        return Err(AppError::Forbidden(format!("You can't invite members as {}", role.name())));
+    }
+    let Some(user) = user_repo::get_user_by_username(&db.pg, username).await? else { return Ok(()) };
+    if !workspace_repo::is_member(&db.pg, workspace_id, user.id).await? {
+        workspace_repo::create_invitation(&db.pg, workspace_id, user.id, actor_id, role).await?;
+    }
+    Ok(())
+}
+
+pub async fn list_my_invitations(db: &DbPool, user_id: Uuid) -> Result<Vec<WorkspaceInvitation>> {
+    workspace_repo::pending_for_user(&db.pg, user_id).await
+}
+
+/// Accept or decline one of your own invitations.
+pub async fn respond_to_invitation(db: &DbPool, user_id: Uuid, invitation_id: Uuid, accept: bool) -> Result<WorkspaceInvitation> {
+    workspace_repo::resolve_invitation(&db.pg, invitation_id, user_id, accept)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Invitation not found".into()))
+}
+
+pub async fn change_member_role(
+    db: &DbPool,
+    workspace_id: Uuid,
+    actor_id: Uuid,
+    target_id: Uuid,
+    role: WorkspaceRole,
+) -> Result<()> {
+    let actor_role = ensure_admin(db, workspace_id, actor_id).await?;
+    if actor_id == target_id {
+        return Err(AppError::BadRequest("You can't change your own role".into()));
+    }
+    let target = workspace_repo::get_member(&db.pg, workspace_id, target_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User is not a member of this workspace".into()))?;
+    if target.role().rank() >= actor_role.rank() || role.rank() >= actor_role.rank() {
+        return Err(AppError::Forbidden("You can only manage roles below your own".into()));
+    }
+    workspace_repo::set_member_role(&db.pg, workspace_id, target_id, role).await
+}
+
+/// What removing a member changed, for the caller to announce.
+pub struct Removal {
+    /// Rooms the member was dropped from.
+    pub rooms:         Vec<Uuid>,
+    /// Rooms they owned, now handed to someone else.
+    pub owner_changes: Vec<(Uuid, OwnerChange)>,
+}
+
+/// Remove a member, or leave when `target_id` is the caller.  The owner can't leave or
+/// be removed.  Rooms the member owned are handed on first.
+pub async fn remove_member(db: &DbPool, workspace_id: Uuid, actor_id: Uuid, target_id: Uuid) -> Result<Removal> {
+    let target = workspace_repo::get_member(&db.pg, workspace_id, target_id).await?;
+    if actor_id == target_id {
+        let target = target.ok_or_else(|| AppError::NotFound("Workspace not found".into()))?;
+        if target.role() == WorkspaceRole::Owner {
+            return Err(AppError::Conflict("The owner can't leave the workspace".into()));
+        }
+    } else {
+        let actor_role = ensure_admin(db, workspace_id, actor_id).await?;
+        let target = target.ok_or_else(|| AppError::NotFound("User is not a member of this workspace".into()))?;
+        if target.role().rank() >= actor_role.rank() {
+            return Err(AppError::Forbidden("You can only remove members below your own role".into()));
+        }
+    }
+    let owner_changes = room_service::hand_over_rooms(db, workspace_id, target_id).await?;
+    let rooms = workspace_repo::remove_member(&db.pg, workspace_id, target_id).await?;
+    Ok(Removal { rooms, owner_changes })
+}
+
+/// New tokens with `workspace_id` as the active workspace.
+pub async fn switch_workspace(db: &DbPool, keys: &JwtKeys, user_id: Uuid, username: &str, workspace_id: Uuid) -> Result<AuthTokens> {
+    ensure_member(db, workspace_id, user_id).await?;
+    auth_service::make_tokens(keys, user_id, username, Some(workspace_id))
+}
+
+/// Put a newly created account into `DEFAULT_WORKSPACE`, if that workspace exists.
+pub async fn join_default(db: &DbPool, cfg: &Config, user_id: Uuid) -> Result<()> {
+    if cfg.default_workspace.is_empty() {
+        return Ok(());
+    }
+    if let Some(workspace) = workspace_repo::get_workspace_by_slug(&db.pg, &cfg.default_workspace).await? {
+        workspace_repo::add_member(&db.pg, workspace.id, user_id, WorkspaceRole::Member).await?;
+    }
+    Ok(())
+}
+
+/// People in the caller's active workspace matching `query`.
+pub async fn search_users(db: &DbPool, workspace_id: Uuid, user_id: Uuid, query: &str, limit: Option<i64>) -> Result<Vec<UserResponse>> {
+    ensure_member(db, workspace_id, user_id).await?;
+    let Some(pattern) = search::contains_pattern(Some(query)) else { return Ok(Vec::new()) };
+    let limit = limit.unwrap_or(20).clamp(1, MAX_SEARCH_RESULTS);
+    let users = workspace_repo::search_members(&db.pg, workspace_id, &pattern, limit).await?;
+    Ok(users.into_iter().map(UserResponse::from).collect())
+}
+
+/// Another user's profile, visible only if you share a workspace.
+pub async fn lookup_user(db: &DbPool, user_id: Uuid, target_id: Uuid) -> Result<UserResponse> {
+    let not_found = || AppError::NotFound("User not found".into());
+    if user_id != target_id && !workspace_repo::shares_workspace(&db.pg, user_id, target_id).await? {
+        return Err(not_found());
+    }
+    Ok(user_repo::get_user_by_id(&db.pg, target_id).await?.ok_or_else(not_found)?.into())
+}
+
+/// Fail unless both users belong to `workspace_id`, e.g. before a DM sent from it.
+pub async fn ensure_shared(db: &DbPool, workspace_id: Uuid, user_id: Uuid, other_id: Uuid) -> Result<()> {
+    ensure_member(db, workspace_id, user_id).await?;
+    if !workspace_repo::is_member(&db.pg, workspace_id, other_id).await? {
+        return Err(AppError::NotFound("User not found".into()));
+    }
+    Ok(())
+}
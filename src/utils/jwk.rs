@@ -0,0 +1,99 @@
+/// Public verification keys: parsed from PEM, turned into JWKs for `/.well-known/jwks.json`.
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{Algorithm, DecodingKey};
+use serde_json::json;
+use simple_asn1::ASN1Block;
+use sha2::{Digest, Sha256};
+
+use crate::error::{AppError, Result};
+
+#[derive(Debug, Clone)]
+pub enum PublicKey {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ed25519 { x: Vec<u8> },
+}
+
+fn bad_key(why: &str) -> AppError {
+    AppError::Internal(format!("Unsupported public key: {why}"))
+}
+
+fn rsa_components(der: &[u8]) -> Result<PublicKey> {
+    match simple_asn1::from_der(der).map_err(|e| bad_key(&e.to_string()))?.as_slice() {
+        [ASN1Block::Sequence(_, fields)] => match fields.as_slice() {
+            [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => Ok(PublicKey::Rsa {
+                n: n.to_bytes_be().1,
+                e: e.to_bytes_be().1,
+            }),
+            _ => Err(bad_key("malformed RSA key")),
+        },
+        _ => Err(bad_key("malformed RSA key")),
+    }
+}
+
+impl PublicKey {
+    /// Accepts `PUBLIC KEY` (SubjectPublicKeyInfo, RSA or Ed25519) and
+    /// `RSA PUBLIC KEY` (PKCS#1) PEM blocks.
+    pub fn from_pem(pem_text: &str) -> Result<Self> {
+        let block = pem::parse(pem_text).map_err(|e| bad_key(&e.to_string()))?;
+        match block.tag() {
+            "RSA PUBLIC KEY" => rsa_components(block.contents()),
+            "PUBLIC KEY" => {
+                let blocks = simple_asn1::from_der(block.contents()).map_err(|e| bad_key(&e.to_string()))?;
+                let [ASN1Block::Sequence(_, spki)] = blocks.as_slice() else { return Err(bad_key("not SPKI")) };
+                let [ASN1Block::Sequence(_, alg), ASN1Block::BitString(_, _, key)] = spki.as_slice() else {
+                    return Err(bad_key("not SPKI"));
+                };
+                let Some(ASN1Block::ObjectIdentifier(_, oid)) = alg.first() else { return Err(bad_key("no algorithm")) };
+                let rsa_oid = simple_asn1::oid!(1, 2, 840, 113549, 1, 1, 1);
+                let ed25519_oid = simple_asn1::oid!(1, 3, 101, 112);
+                if *oid == rsa_oid {
+                    rsa_components(key)
+                } else if *oid == ed25519_oid && key.len() == 32 {
+                    Ok(PublicKey::Ed25519 { x: key.clone() })
+                } else {
+                    Err(bad_key("only RSA and Ed25519 are supported"))
+                }
+            }
+            other => Err(bad_key(&format!("unexpected PEM block {other}"))),
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            PublicKey::Rsa { .. } => Algorithm::RS256,
+            PublicKey::Ed25519 { .. } => Algorithm::EdDSA,
+        }
+    }
+
+    pub fn decoding_key(&self) -> Result<DecodingKey> {
+        match self {
+            PublicKey::Rsa { n, e } => DecodingKey::from_rsa_components(&b64(n), &b64(e)),
+            PublicKey::Ed25519 { x } => DecodingKey::from_ed_components(&b64(x)),
+        }
+        .map_err(AppError::from)
+    }
+
+    /// RFC 7638 thumbprint, used as the default `kid`.
+    pub fn thumbprint(&self) -> String {
+        let canonical = match self {
+            PublicKey::Rsa { n, e } => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, b64(e), b64(n)),
+            PublicKey::Ed25519 { x } => format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, b64(x)),
+        };
+        b64(&Sha256::digest(canonical.as_bytes()))
+    }
+
+    pub fn to_jwk(&self, kid: &str) -> serde_json::Value {
+        match self {
+            PublicKey::Rsa { n, e } => json!({
+                "kty": "RSA", "use": "sig", "alg": "RS256", "kid": kid, "n": b64(n), "e": b64(e),
+            }),
+            PublicKey::Ed25519 { x } => json!({
+                "kty": "OKP", "use": "sig", "alg": "EdDSA", "kid": kid, "crv": "Ed25519", "x": b64(x),
+            }),
+        }
+    }
+}
+
+fn b64(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -1,9 +1,11 @@
 use uuid::Uuid;
-use jsonwebtoken::{encode, decode, Header, EncodingKey, DecodingKey, Validation, Algorithm};    
+use jsonwebtoken::{encode, decode, decode_header, Header, EncodingKey, DecodingKey, Validation, Algorithm};
 use serde::{Deserialize, Serialize};
-use chrono::{Utc, DateTime};
+use chrono::Utc;
 
+use crate::config::Config;
 use crate::error::{AppError, Result};
+use crate::utils::jwk::PublicKey;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -11,7 +13,13 @@ pub struct Claims {
     pub username:     String,
     pub exp:          i64,      // expiry timestamp
     pub iat:          i64,      // issued-at timestamp
-    pub token_type:   String,   // "access" | "refresh"
+    /// Issued-at in milliseconds, so a revocation and a login in the same second can be
+    /// told apart.
+    pub iat_ms:       i64,
+    pub token_type:   String,   // "access" | "refresh" | "mfa_pending"
+    /// Active workspace; absent on tokens issued before workspaces existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<Uuid>,
 }
 
 
@@ -22,33 +30,162 @@ impl Claims {
     }
 }
 
+const MIN_SECRET_BYTES: usize = 32;
+const PLACEHOLDER_MARKERS: [&str; 5] = ["changeme", "change-me", "change_me", "your-secret", "example"];
+
+/// Refuse secrets that are short, repetitive or obviously copied from an example.
+pub fn check_secret_strength(secret: &str) -> Result<()> {
+    let weak = |why: &str| Err(AppError::Internal(format!("JWT_SECRET is too weak: {why}")));
+    if secret.len() < MIN_SECRET_BYTES {
+        return weak(&format!("must be at least {MIN_SECRET_BYTES} bytes"));
+    }
+    let mut distinct: Vec<u8> = secret.bytes().collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if distinct.len() < 8 {
+        return weak("too few distinct characters");
+    }
+    let lower = secret.to_ascii_lowercase();
+    if PLACEHOLDER_MARKERS.iter().any(|m| lower.contains(m)) {
+        return weak("looks like a placeholder value");
+    }
+    Ok(())
+}
+
+struct VerifyingKey {
+    kid:       String,
+    algorithm: Algorithm,
+    key:       DecodingKey,
+    /// Published in the JWKS; `None` for the HS256 secret, which is never published.
+    public:    Option<PublicKey>,
+}
+
+/// Keys for issuing and checking JWTs, built once at startup.
+///
+/// Tokens are signed with one key (HS256 secret, RS256 or EdDSA private key) and carry
+/// its `kid`.  Verification accepts the signing key plus any retired public keys listed
+/// in `JWT_VERIFY_KEYS`, so a new key can be rolled out while tokens signed by the old
+/// one are still live.  Tokens without a `kid` (issued before key ids existed) are
+/// checked against the signing key.
+pub struct JwtKeys {
+    signing_kid:          String,
+    algorithm:            Algorithm,
+    encoding:             EncodingKey,
+    verifying:            Vec<VerifyingKey>,
+    pub access_ttl_secs:  i64,
+    pub refresh_ttl_secs: i64,
+}
+
+fn read_file(var: &str, path: &str) -> Result<String> {
+    if path.is_empty() {
+        return Err(AppError::Internal(format!("{var} is required for the configured JWT_ALGORITHM")));
+    }
+    std::fs::read_to_string(path).map_err(|e| AppError::Internal(format!("{var} ({path}): {e}")))
+}
+
+impl JwtKeys {
+    pub fn from_config(cfg: &Config) -> Result<Self> {
+        // The secret also signs emailed tokens, so it's required whatever the JWT algorithm.
+        check_secret_strength(&cfg.jwt_secret)?;
+
+        let mut verifying = Vec::new();
+        let (algorithm, encoding, signing_kid) = match cfg.jwt_algorithm.as_str() {
+            "HS256" => {
+                let kid = if cfg.jwt_key_id.is_empty() { "hs256".to_string() } else { cfg.jwt_key_id.clone() };
+                verifying.push(VerifyingKey {
+                    kid:       kid.clone(),
+                    algorithm: Algorithm::HS256,
+                    key:       DecodingKey::from_secret(cfg.jwt_secret.as_bytes()),
+                    public:    None,
+                });
+                (Algorithm::HS256, EncodingKey::from_secret(cfg.jwt_secret.as_bytes()), kid)
+            }
+            name @ ("RS256" | "EdDSA") => {
+                let expected = if name == "RS256" { Algorithm::RS256 } else { Algorithm::EdDSA };
+                let private = read_file("JWT_PRIVATE_KEY_FILE", &cfg.jwt_private_key_file)?;
+                let public = PublicKey::from_pem(&read_file("JWT_PUBLIC_KEY_FILE", &cfg.jwt_public_key_file)?)?;
+                let algorithm = public.algorithm();
+                if algorithm != expected {
+                    return Err(AppError::Internal(format!("JWT_PUBLIC_KEY_FILE is not an {name} key")));
+                }
+                let encoding = match algorithm {
+                    Algorithm::RS256 => EncodingKey::from_rsa_pem(private.as_bytes())?,
+                    _ => EncodingKey::from_ed_pem(private.as_bytes())?,
+                };
+                let kid = if cfg.jwt_key_id.is_empty() { public.thumbprint() } else { cfg.jwt_key_id.clone() };
+                verifying.push(VerifyingKey { kid: kid.clone(), algorithm, key: public.decoding_key()?, public: Some(public) });
+                (algorithm, encoding, kid)
+            }
+            other => return Err(AppError::Internal(format!("Unsupported JWT_ALGORITHM {other}; use HS256, RS256 or EdDSA"))),
+        };
+
+        // Retired keys: `kid=path.pem,kid=path.pem`.  A bare path uses the key's thumbprint.
+        for entry in cfg.jwt_verify_keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (kid, path) = match entry.split_once('=') {
+                Some((kid, path)) => (Some(kid.trim().to_string()), path.trim()),
+                None => (None, entry),
+            };
+            let public = PublicKey::from_pem(&read_file("JWT_VERIFY_KEYS", path)?)?;
+            let kid = kid.unwrap_or_else(|| public.thumbprint());
+            if verifying.iter().any(|k| k.kid == kid) {
+                return Err(AppError::Internal(format!("Duplicate JWT key id {kid}")));
+            }
+            verifying.push(VerifyingKey { kid, algorithm: public.algorithm(), key: public.decoding_key()?, public: Some(public) });
+        }
+
+        tracing::info!("JWT: signing with {algorithm:?} key {signing_kid}, {} verification key(s)", verifying.len());
+        Ok(JwtKeys {
+            signing_kid,
+            algorithm,
+            encoding,
+            verifying,
+            access_ttl_secs:  cfg.jwt_expiry_secs,
+            refresh_ttl_secs: cfg.jwt_refresh_expiry_secs,
+        })
+    }
+
+    /// Public keys as a JWK Set.  Empty when signing with HS256.
+    pub fn jwks(&self) -> serde_json::Value {
+        let keys: Vec<_> = self.verifying.iter()
+            .filter_map(|k| k.public.as_ref().map(|p| p.to_jwk(&k.kid)))
+            .collect();
+        serde_json::json!({ "keys": keys })
+    }
+}
+
 pub fn create_token(
+    keys: &JwtKeys,
     user_id: Uuid,
     username: &str,
-    secret: &str,
+    workspace_id: Option<Uuid>,
     expiry_secs: i64,
     token_type: &str,
 ) -> Result<String> {
-     let now = Utc::now().timestamp();
+    let now_ms = Utc::now().timestamp_millis();
+    let now = now_ms / 1000;
     let claims = Claims {
         sub:        user_id.to_string(),
         username:   username.to_string(),
         exp:        now + expiry_secs,
         iat:        now,
+        iat_ms:     now_ms,
         token_type: token_type.into(),
+        workspace_id,
     };
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes()))
-        .map_err(AppError::from)
+    let mut header = Header::new(keys.algorithm);
+    header.kid = Some(keys.signing_kid.clone());
+    encode(&header, &claims, &keys.encoding).map_err(AppError::from)
 }
 
-pub fn verify_token(token: &str, secret: &str) -> Result<Claims> {
-    let claims = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::new(Algorithm::HS256),
-    )
-    .map_err(|_| AppError::Unauthorized("Invalid or expired token".into()))?
-    .claims;
+pub fn verify_token(keys: &JwtKeys, token: &str) -> Result<Claims> {
+    let invalid = || AppError::Unauthorized("Invalid or expired token".into());
+    let header = decode_header(token).map_err(|_| invalid())?;
+    let kid = header.kid.as_deref().unwrap_or(&keys.signing_kid);
+    let key = keys.verifying.iter().find(|k| k.kid == kid).ok_or_else(invalid)?;
+    // The algorithm comes from our key, never from the token header.
+    let claims = decode::<Claims>(token, &key.key, &Validation::new(key.algorithm))
+        .map_err(|_| invalid())?
+        .claims;
 
     Ok(claims)
-} 
\ No newline at end of file
+}
@@ -1,2 +1,5 @@
 pub mod password;
-pub mod jwt;
\ No newline at end of file
+pub mod jwt;
+pub mod jwk;
+pub mod token;
+pub mod search;
\ No newline at end of file
@@ -1,12 +1,154 @@
+/// Password hashing policy.  New hashes are Argon2id with the configured parameters;
+/// stored hashes are recognised by their PHC prefix, so bcrypt hashes from before the
+/// switch still verify and can be upgraded with `needs_rehash`.
+///
+/// Hashing is deliberately slow, so it never runs on the async executor: every call goes
+/// through `spawn_blocking`, gated by a semaphore so a burst of logins can occupy at most
+/// `max_concurrent` blocking threads.
+use std::sync::OnceLock;
+use argon2::{Algorithm, Argon2, Params, Version};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use bcrypt;
+use tokio::sync::Semaphore;
+
 use crate::error::{AppError, Result};
 
-const BCRYPT_COST: u32 = 12;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub memory_kib:  u32,
+    pub iterations:  u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordPolicy {
+    /// OWASP's baseline recommendation for Argon2id.
+    fn default() -> Self {
+        PasswordPolicy { memory_kib: 19_456, iterations: 2, parallelism: 1 }
+    }
+}
+
+impl PasswordPolicy {
+    fn argon2(&self) -> Result<Argon2<'static>> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| AppError::Internal(format!("Argon2 params: {e}")))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+/// Algorithm of a stored hash, from its PHC / modular-crypt prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Argon2id,
+    /// `argon2i` / `argon2d` — verifiable, but always rehashed.
+    Argon2Legacy,
+    Bcrypt,
+    Unknown,
+}
+
+pub fn detect(hashed: &str) -> HashAlgorithm {
+    if hashed.starts_with("$argon2id$") {
+        HashAlgorithm::Argon2id
+    } else if hashed.starts_with("$argon2i$") || hashed.starts_with("$argon2d$") {
+        HashAlgorithm::Argon2Legacy
+    } else if ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|p| hashed.starts_with(p)) {
+        HashAlgorithm::Bcrypt
+    } else {
+        HashAlgorithm::Unknown
+    }
+}
 
-pub fn hash_password(plain: &str) -> Result<String> {
-    bcrypt::hash(plain, BCRYPT_COST).map_err(AppError::from)
+struct Hasher {
+    policy:  PasswordPolicy,
+    permits: Semaphore,
 }
 
-pub fn verify_password(plain: &str, hashed: &str) -> Result<bool> {
-    bcrypt::verify(plain, hashed).map_err(AppError::from)
-}
\ No newline at end of file
+static HASHER: OnceLock<Hasher> = OnceLock::new();
+
+/// Install the policy.  Call once at startup; later calls are ignored.
+pub fn init(policy: PasswordPolicy, max_concurrent: usize) -> Result<()> {
+    policy.argon2()?;
+    let max_concurrent = if max_concurrent == 0 { default_concurrency() } else { max_concurrent };
+    if HASHER.set(Hasher { policy, permits: Semaphore::new(max_concurrent) }).is_err() {
+        tracing::warn!("Password policy already initialised");
+    }
+    Ok(())
+}
+
+fn default_concurrency() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+}
+
+fn hasher() -> &'static Hasher {
+    HASHER.get_or_init(|| Hasher {
+        policy:  PasswordPolicy::default(),
+        permits: Semaphore::new(default_concurrency()),
+    })
+}
+
+async fn run_blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(PasswordPolicy) -> Result<T> + Send + 'static,
+{
+    let hasher = hasher();
+    let _permit = hasher.permits.acquire().await
+        .map_err(|e| AppError::Internal(format!("Password hasher: {e}")))?;
+    let policy = hasher.policy;
+    tokio::task::spawn_blocking(move || f(policy))
+        .await
+        .map_err(|e| AppError::Internal(format!("Password hasher task: {e}")))?
+}
+
+pub async fn hash_password(plain: &str) -> Result<String> {
+    let plain = plain.to_string();
+    run_blocking(move |policy| {
+        let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+            .map_err(|e| AppError::Internal(format!("Salt: {e}")))?;
+        Ok(policy.argon2()?
+            .hash_password(plain.as_bytes(), &salt)
+            .map_err(|e| AppError::Internal(format!("Argon2: {e}")))?
+            .to_string())
+    }).await
+}
+
+pub async fn verify_password(plain: &str, hashed: &str) -> Result<bool> {
+    let (plain, hashed) = (plain.to_string(), hashed.to_string());
+    run_blocking(move |_| match detect(&hashed) {
+        HashAlgorithm::Argon2id | HashAlgorithm::Argon2Legacy => {
+            let parsed = PasswordHash::new(&hashed)
+                .map_err(|e| AppError::Internal(format!("Stored hash: {e}")))?;
+            // Parameters come from the stored hash, not the current policy.
+            Ok(Argon2::default().verify_password(plain.as_bytes(), &parsed).is_ok())
+        }
+        HashAlgorithm::Bcrypt => bcrypt::verify(&plain, &hashed).map_err(AppError::from),
+        HashAlgorithm::Unknown => Err(AppError::Internal("Unrecognised password hash format".into())),
+    }).await
+}
+
+/// True when `hashed` isn't Argon2id with the current policy's parameters.
+pub fn needs_rehash(hashed: &str) -> bool {
+    if detect(hashed) != HashAlgorithm::Argon2id {
+        return true;
+    }
+    let policy = hasher().policy;
+    let Ok(parsed) = PasswordHash::new(hashed) else { return true };
+    match Params::try_from(&parsed) {
+        Ok(params) => {
+            params.m_cost() != policy.memory_kib
+                || params.t_cost() != policy.iterations
+                || params.p_cost() != policy.parallelism
+        }
+        Err(_) => true,
+    }
+}
+
+static DUMMY_HASH: tokio::sync::OnceCell<String> = tokio::sync::OnceCell::const_new();
+
+/// Spend the same time as `verify_password` when there is no stored hash to check
+/// (unknown account, single sign-on only), so response timing doesn't reveal which
+/// emails are registered.  Always false.
+pub async fn verify_dummy(plain: &str) -> Result<bool> {
+    let hashed = DUMMY_HASH.get_or_try_init(|| hash_password("dummy password for timing")).await?;
+    verify_password(plain, hashed).await?;
+    Ok(false)
+}
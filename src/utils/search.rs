@@ -0,0 +1,10 @@
+/// ILIKE pattern matching `query` anywhere, with LIKE wildcards in it taken literally.
+/// `None` for a blank query.
+pub fn contains_pattern(query: Option<&str>) -> Option<String> {
+    let query = query?.trim();
+    if query.is_empty() {
+        return None;
+    }
+    let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    Some(format!("%{escaped}%"))
+}
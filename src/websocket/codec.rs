@@ -0,0 +1,142 @@
+/// Wire encodings for WebSocket frames.  The serde types in `protocol` are shared by
+/// every encoding; only the framing differs (JSON text vs. MessagePack/CBOR binary).
+use std::sync::Arc;
+use axum::extract::ws::{Message as WsMsg, CloseFrame};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::protocol::{ClientMessage, ServerMessage};
+
+/// Sub-protocols we accept in `Sec-WebSocket-Protocol`, in server preference order.
+pub const SUBPROTOCOLS: [&str; 3] = ["msgpack", "cbor", "json"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    #[default]
+    Json,
+    MsgPack,
+    Cbor,
+}
+
+/// An encoded outgoing frame, cheap to clone across recipients.
+#[derive(Debug, Clone)]
+pub enum Frame {
+    Text(Arc<str>),
+    Binary(Arc<[u8]>),
+    /// Ask the transport to close with this code; sent by the hub on shutdown.
+    Close { code: u16, reason: Arc<str> },
+}
+
+impl Frame {
+    pub fn into_ws(self) -> WsMsg {
+        match self {
+            Frame::Text(text)  => WsMsg::Text(text.to_string()),
+            Frame::Binary(buf) => WsMsg::Binary(buf.to_vec()),
+            Frame::Close { code, reason } => WsMsg::Close(Some(CloseFrame {
+                code,
+                reason: reason.to_string().into(),
+            })),
+        }
+    }
+}
+
+/// Why an incoming frame couldn't be turned into a `ClientMessage`.
+#[derive(Debug)]
+pub struct DecodeError {
+    pub code:    &'static str,
+    pub message: String,
+}
+
+/// Just the tag of a client frame, to classify frames that fail full decoding.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+impl WireFormat {
+    pub const ALL: [WireFormat; 3] = [WireFormat::Json, WireFormat::MsgPack, WireFormat::Cbor];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "json"                    => Some(WireFormat::Json),
+            "msgpack" | "messagepack" => Some(WireFormat::MsgPack),
+            "cbor"                    => Some(WireFormat::Cbor),
+            _                         => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WireFormat::Json    => "json",
+            WireFormat::MsgPack => "msgpack",
+            WireFormat::Cbor    => "cbor",
+        }
+    }
+
+    /// Position in `ALL`, used by the hub to cache one encoding per format.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn encode(self, msg: &ServerMessage) -> Result<Frame, String> {
+        match self {
+            WireFormat::Json => serde_json::to_string(msg)
+                .map(|text| Frame::Text(Arc::from(text)))
+                .map_err(|e| e.to_string()),
+            // Named (map) encoding so the internally-tagged `type` field survives.
+            WireFormat::MsgPack => rmp_serde::to_vec_named(msg)
+                .map(|buf| Frame::Binary(Arc::from(buf)))
+                .map_err(|e| e.to_string()),
+            WireFormat::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(msg, &mut buf)
+                    .map(|_| Frame::Binary(Arc::from(buf)))
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Decode an incoming client frame.  Text frames are always JSON; binary frames
+    /// must match the negotiated binary encoding.  Control frames yield `None`.
+    ///
+    /// Failures are classified so clients can tell an undecodable frame (`PARSE_ERROR`)
+    /// from a type this server doesn't know (`UNKNOWN_MESSAGE_TYPE`) or a known type
+    /// with a bad payload (`INVALID_MESSAGE`).
+    pub fn decode(self, msg: &WsMsg) -> Option<Result<ClientMessage, DecodeError>> {
+        let err = match self.decode_as::<ClientMessage>(msg)? {
+            Ok(msg) => return Some(Ok(msg)),
+            Err(e) => e,
+        };
+        let error = match self.decode_as::<Envelope>(msg)? {
+            Err(_) => DecodeError {
+                code:    "PARSE_ERROR",
+                message: format!("Invalid message: {err}"),
+            },
+            Ok(Envelope { kind: None }) => DecodeError {
+                code:    "INVALID_MESSAGE",
+                message: "Missing message type".into(),
+            },
+            Ok(Envelope { kind: Some(kind) }) if !ClientMessage::TYPES.contains(&kind.as_str()) => DecodeError {
+                code:    "UNKNOWN_MESSAGE_TYPE",
+                message: format!("Unknown message type: {kind}"),
+            },
+            Ok(Envelope { kind: Some(kind) }) => DecodeError {
+                code:    "INVALID_MESSAGE",
+                message: format!("Invalid {kind} message: {err}"),
+            },
+        };
+        Some(Err(error))
+    }
+
+    fn decode_as<T: DeserializeOwned>(self, msg: &WsMsg) -> Option<Result<T, String>> {
+        match msg {
+            WsMsg::Text(text) => Some(serde_json::from_str(text).map_err(|e| e.to_string())),
+            WsMsg::Binary(buf) => Some(match self {
+                WireFormat::Json    => Err("Binary frames require the msgpack or cbor encoding".into()),
+                WireFormat::MsgPack => rmp_serde::from_slice(buf).map_err(|e| e.to_string()),
+                WireFormat::Cbor    => ciborium::from_reader(buf.as_slice()).map_err(|e| e.to_string()),
+            }),
+            _ => None,
+        }
+    }
+}
@@ -1,31 +1,91 @@
-use axum::extract::ws::{WebSocket, Message as WsMsg};
+use std::time::Duration;
+use axum::extract::ws::{WebSocket, Message as WsMsg, CloseFrame, close_code};
 use tokio::sync::mpsc;
+use tokio::time::{self, Instant, MissedTickBehavior};
 use uuid::Uuid;
 use tracing::{info, warn, error};
 
+use super::codec::{Frame, WireFormat};
 use super::protocol::{ClientMessage, ServerMessage};
+use crate::error::AppError;
+
+/// Hub-assigned id of one connection, unique for the life of the process.
+pub type ConnectionId = u64;
 
 /// One live WebSocket connection.  The hub holds the sender; the handler owns the receiver.
+/// Frames arrive already encoded in the connection's wire format so a broadcast is
+/// serialized once, not once per socket.
 #[derive(Clone)]
 pub struct Connection {
+    pub id:       ConnectionId,
     pub user_id:  Uuid,
     pub username: String,
-    pub tx:       mpsc::Sender<ServerMessage>,
+    pub format:   WireFormat,
+    /// Negotiated protocol version; frames newer than this are never sent.
+    pub version:  u16,
+    /// Active workspace of the session; only rooms in it are delivered.
+    pub workspace_id: Option<Uuid>,
+    pub tx:       mpsc::Sender<Frame>,
+}
+
+/// Per-connection parameters fixed at upgrade time.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionSettings {
+    pub format:              WireFormat,
+    pub rate_limit_messages: u32,
+    pub rate_limit_window:   Duration,
+    /// How often the server sends a WebSocket ping.
+    pub ping_interval:       Duration,
+    /// How long after a ping the peer has to show any sign of life.
+    pub pong_timeout:        Duration,
+    /// Close after this long without a client data frame; `None` disables it.
+    pub idle_timeout:        Option<Duration>,
+}
+
+/// Why a connection loop ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disconnect {
+    /// Client closed the socket.
+    Closed,
+    /// Socket read or write failed.
+    Error,
+    /// The hub dropped our channel.
+    HubClosed,
+    /// No pong (or any other frame) before the deadline — reaped as a dead peer.
+    PongTimeout,
+    /// No client data frame within the idle timeout — reaped.
+    IdleTimeout,
+    /// The server is shutting down and closed the socket.
+    Shutdown,
 }
 
 /// Spawn the reader loop for a single WebSocket.
 /// Returns the receiver half so the hub can feed outgoing frames.
+///
+/// The loop also drives heartbeats: it pings every `ping_interval` and gives up on
+/// peers that stay silent past the pong deadline or the idle timeout.
 pub async fn run_connection(
     mut socket: WebSocket,
     user_id:    Uuid,
-    username:   String,
-    tx:         mpsc::Sender<ServerMessage>,
-    rx:         mpsc::Receiver<ServerMessage>,
+    settings:   ConnectionSettings,
+    rx:         mpsc::Receiver<Frame>,
     on_message: impl Fn(Uuid, ClientMessage) + Send + 'static,
     on_disconnect: impl FnOnce(Uuid) + Send + 'static,
-) {
+) -> Disconnect {
     // Use the receiver directly in the merged read/write loop
     let mut rx = rx;
+    let format = settings.format;
+
+    // Fixed-window rate limit on client frames.
+    let mut window_start = Instant::now();
+    let mut window_count = 0u32;
+
+    // Heartbeat state.  The first ping goes out one interval after connect.
+    let mut ping = time::interval_at(Instant::now() + settings.ping_interval, settings.ping_interval);
+    ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut pong_deadline: Option<Instant> = None;
+    let mut last_data = Instant::now();
+    let reason;
 
     // ── Merged read/write loop ──────────────────────────
     // We poll both the socket (incoming) and the rx channel (outgoing).
@@ -35,49 +95,100 @@ pub async fn run_connection(
         select! {
             // Incoming frame from client
             frame = socket.recv() => {
+                // Any inbound frame proves the peer is alive.
+                if let Some(Ok(_)) = frame {
+                    pong_deadline = None;
+                }
                 match frame {
-                    Some(Ok(WsMsg::Text(text))) => {
-                        match serde_json::from_str::<ClientMessage>(&text) {
-                            Ok(msg) => on_message(user_id, msg),
-                            Err(e) => {
-                                warn!("parse ClientMessage: {e}");
-                                let err = ServerMessage::Error {
-                                    code:    "PARSE_ERROR".into(),
-                                    message: format!("Invalid message: {e}"),
-                                };
-                                let _ = socket.send(WsMsg::Text(serde_json::to_string(&err).unwrap())).await;
-                            }
-                        }
-                    }
                     Some(Ok(WsMsg::Close(_))) | None => {
                         info!("WebSocket closed for user {user_id}");
+                        reason = Disconnect::Closed;
                         break;
                     }
+                    Some(Ok(WsMsg::Ping(_) | WsMsg::Pong(_))) => {}
+                    Some(Ok(msg)) => {
+                        last_data = Instant::now();
+                        let reply = match format.decode(&msg) {
+                            None => None,
+                            Some(decoded) => {
+                                if window_start.elapsed() >= settings.rate_limit_window {
+                                    window_start = Instant::now();
+                                    window_count = 0;
+                                }
+                                window_count += 1;
+                                if window_count > settings.rate_limit_messages {
+                                    let (_, code, message) = AppError::RateLimited.parts();
+                                    Some(ServerMessage::Error { code: code.into(), message })
+                                } else {
+                                    match decoded {
+                                        Ok(msg) => { on_message(user_id, msg); None }
+                                        Err(e) => {
+                                            warn!("parse ClientMessage: {}", e.message);
+                                            Some(ServerMessage::Error { code: e.code.into(), message: e.message })
+                                        }
+                                    }
+                                }
+                            }
+                        };
+                        if let Some(reply) = reply {
+                            match format.encode(&reply) {
+                                Ok(frame) => { let _ = socket.send(frame.into_ws()).await; }
+                                Err(e) => error!("serialize: {e}"),
+                            }
+                        }
+                    }
                     Some(Err(e)) => {
                         warn!("WebSocket error for user {user_id}: {e}");
+                        reason = Disconnect::Error;
                         break;
                     }
-                    _ => {}
                 }
             }
             // Outgoing frame from hub
             msg = rx.recv() => {
                 match msg {
-                    Some(server_msg) => {
-                        match serde_json::to_string(&server_msg) {
-                            Ok(text) => {
-                                if socket.send(WsMsg::Text(text)).await.is_err() {
-                                    break;
-                                }
-                            }
-                            Err(e) => error!("serialize: {e}"),
+                    Some(frame @ Frame::Close { .. }) => {
+                        let _ = time::timeout(settings.pong_timeout, socket.send(frame.into_ws())).await;
+                        reason = Disconnect::Shutdown;
+                        break;
+                    }
+                    Some(frame) => {
+                        if socket.send(frame.into_ws()).await.is_err() {
+                            reason = Disconnect::Error;
+                            break;
                         }
                     }
-                    None => break, // channel closed
+                    None => { // channel closed
+                        reason = Disconnect::HubClosed;
+                        break;
+                    }
+                }
+            }
+            // Server heartbeat
+            _ = ping.tick() => {
+                if socket.send(WsMsg::Ping(Vec::new())).await.is_err() {
+                    reason = Disconnect::Error;
+                    break;
                 }
+                pong_deadline.get_or_insert(Instant::now() + settings.pong_timeout);
+            }
+            _ = time::sleep_until(pong_deadline.unwrap_or_else(Instant::now)), if pong_deadline.is_some() => {
+                warn!("WebSocket for user {user_id} missed its pong deadline; reaping");
+                reason = Disconnect::PongTimeout;
+                break;
+            }
+            _ = time::sleep_until(last_data + settings.idle_timeout.unwrap_or_default()), if settings.idle_timeout.is_some() => {
+                info!("WebSocket for user {user_id} idle; closing");
+                let _ = time::timeout(settings.pong_timeout, socket.send(WsMsg::Close(Some(CloseFrame {
+                    code:   close_code::AWAY,
+                    reason: "idle timeout".into(),
+                })))).await;
+                reason = Disconnect::IdleTimeout;
+                break;
             }
         }
     }
 
     on_disconnect(user_id);
-}
\ No newline at end of file
+    reason
+}
@@ -1,59 +1,205 @@
 /// In-memory connection hub.  Keeps track of every live WebSocket and which rooms
 /// each user has joined.  Provides broadcast helpers used by the WebSocket handler.
-use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, Mutex};
+///
+/// A user may hold several connections at once (another device, an SSE session).
+/// Room subscriptions belong to the user and reach all of them; they are dropped when
+/// the user's last connection goes.
+///
+/// State lives in sharded maps so joins, leaves and broadcasts in different rooms
+/// don't contend on a single lock.  Lock order is always `rooms` → `connections`;
+/// `user_rooms` is never held together with another map.
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use dashmap::DashMap;
+use serde::Serialize;
 use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
 use uuid::Uuid;
-use tracing::{info, warn};
+use tracing::{info, warn, error};
 
 use super::protocol::ServerMessage;
-use super::connection::Connection;
+use super::connection::{Connection, ConnectionId, Disconnect};
+use super::codec::{Frame, WireFormat};
 
 #[derive(Clone)]
 pub struct Hub {
-    inner: Arc<Mutex<HubInner>>,
+    inner: Arc<HubInner>,
 }
 
 struct HubInner {
-    /// user_id → active connection (sender channel)
-    connections: HashMap<Uuid, Connection>,
-    /// room_id → set of user_ids currently in that room
-    rooms: HashMap<Uuid, HashSet<Uuid>>,
+    /// user_id → that user's live connections
+    connections: DashMap<Uuid, Vec<Connection>>,
+    /// room_id → the room's workspace and the user_ids currently in it
+    rooms: DashMap<Uuid, RoomMembers>,
+    /// user_id → set of room_ids the user is in (reverse index for disconnect)
+    user_rooms: DashMap<Uuid, HashSet<Uuid>>,
+    /// connections closed by the server for missing the pong deadline
+    reaped_pong_timeout: AtomicU64,
+    /// connections closed by the server for idling
+    reaped_idle: AtomicU64,
+    /// set once shutdown starts; no new connections are accepted
+    shutting_down: AtomicBool,
+    /// source of `ConnectionId`s
+    next_connection_id: AtomicU64,
+}
+
+/// Live subscribers of one room.  The workspace travels with the set so a broadcast
+/// never reaches a connection acting in another tenant.
+struct RoomMembers {
+    workspace_id: Uuid,
+    users:        HashSet<Uuid>,
+}
+
+/// Point-in-time hub counters, served on `/metrics`.
+#[derive(Debug, Serialize)]
+pub struct HubStats {
+    pub connections:         usize,
+    pub rooms:               usize,
+    pub reaped_pong_timeout: u64,
+    pub reaped_idle:         u64,
 }
 
 impl Hub {
     pub fn new() -> Self {
         Hub {
-            inner: Arc::new(Mutex::new(HubInner {
-                connections: HashMap::new(),
-                rooms:       HashMap::new(),
-            })),
+            inner: Arc::new(HubInner {
+                connections: DashMap::new(),
+                rooms:       DashMap::new(),
+                user_rooms:  DashMap::new(),
+                reaped_pong_timeout: AtomicU64::new(0),
+                reaped_idle:         AtomicU64::new(0),
+                shutting_down:       AtomicBool::new(false),
+                next_connection_id:  AtomicU64::new(1),
+            }),
         }
     }
 
-    /// Register a new connection and return its channel pair.
-    pub fn register(&self, user_id: Uuid, username: String) -> (mpsc::Sender<ServerMessage>, mpsc::Receiver<ServerMessage>) {
+    /// Register a new connection alongside any the user already has, and return its id
+    /// and receiver.  The hub keeps the only sender, so removing the connection here
+    /// ends its socket loop.
+    pub fn register(
+        &self,
+        user_id: Uuid,
+        username: String,
+        workspace_id: Option<Uuid>,
+        format: WireFormat,
+        version: u16,
+    ) -> (ConnectionId, mpsc::Receiver<Frame>) {
         let (tx, rx) = mpsc::channel(64);
-        let conn = Connection { user_id, username, tx: tx.clone() };
-        self.inner.lock().unwrap().connections.insert(user_id, conn);
-        info!("Hub: registered {user_id}");
-        (tx, rx)
-    }
-
-    /// Remove a connection (on disconnect).
-    pub fn disconnect(&self, user_id: Uuid) {
-        let mut inner = self.inner.lock().unwrap();
-        inner.connections.remove(&user_id);
-        for members in inner.rooms.values_mut() {
-            members.remove(&user_id);
+        let id = self.inner.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let conn = Connection { id, user_id, username, format, version, workspace_id, tx };
+        self.inner.connections.entry(user_id).or_default().push(conn);
+        info!("Hub: registered {user_id}#{id} ({}, v{version})", format.name());
+        (id, rx)
+    }
+
+    /// Remove one connection (on disconnect).  A stale id is a no-op, so a socket that
+    /// ends late can't take a newer connection with it.  Room subscriptions go with the
+    /// user's last connection, and only the rooms the user was in are touched.
+    pub fn disconnect(&self, user_id: Uuid, connection_id: ConnectionId) {
+        if !self.remove_connection(user_id, connection_id) {
+            return;
+        }
+        if let Some((_, room_ids)) = self.inner.user_rooms.remove(&user_id) {
+            for room_id in room_ids {
+                self.remove_member(room_id, user_id);
+            }
         }
-        info!("Hub: disconnected {user_id}");
+        info!("Hub: disconnected {user_id}#{connection_id}, no connections left");
+    }
+
+    /// Drop `connection_id` from the user's list; `true` if it was their last one.
+    fn remove_connection(&self, user_id: Uuid, connection_id: ConnectionId) -> bool {
+        self.inner.connections
+            .remove_if_mut(&user_id, |_, conns| {
+                conns.retain(|conn| conn.id != connection_id);
+                conns.is_empty()
+            })
+            .is_some()
+    }
+
+    /// Count a connection that ended for `reason`, if the server reaped it.
+    pub fn record_disconnect(&self, user_id: Uuid, reason: Disconnect) {
+        let counter = match reason {
+            Disconnect::PongTimeout => &self.inner.reaped_pong_timeout,
+            Disconnect::IdleTimeout => &self.inner.reaped_idle,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        info!("Hub: reaped {user_id} ({reason:?})");
+    }
+
+    /// Stop accepting connections and ask every live one to close with `code`.
+    pub fn begin_shutdown(&self, code: u16, reason: &str) {
+        self.inner.shutting_down.store(true, Ordering::SeqCst);
+        let frame = Frame::Close { code, reason: Arc::from(reason) };
+        let mut stuck = Vec::new();
+        for conns in self.inner.connections.iter() {
+            stuck.extend(conns.iter().filter_map(|conn| queue_close(conn, &frame)));
+        }
+        self.finish_close(stuck, frame);
+        info!("Hub: shutdown started, closing {} connections", self.connection_count());
+    }
+
+    /// Ask each of one user's connections to close with `code`, e.g. after their sessions
+    /// are revoked.
+    pub fn close_user(&self, user_id: Uuid, code: u16, reason: &str) {
+        let frame = Frame::Close { code, reason: Arc::from(reason) };
+        let stuck: Vec<_> = match self.inner.connections.get(&user_id) {
+            Some(conns) => conns.iter().filter_map(|conn| queue_close(conn, &frame)).collect(),
+            None => return,
+        };
+        self.finish_close(stuck, frame);
+    }
+
+    /// Connections whose channel was full get `CLOSE_SEND_TIMEOUT` for room to open up.
+    /// One that still can't take the close frame is evicted: the hub holds its only
+    /// sender, so its socket ends once it drains what is already queued.
+    fn finish_close(&self, stuck: Vec<StuckClose>, frame: Frame) {
+        for (user_id, connection_id, tx) in stuck {
+            let hub = self.clone();
+            let frame = frame.clone();
+            tokio::spawn(async move {
+                if tx.send_timeout(frame, CLOSE_SEND_TIMEOUT).await.is_err() {
+                    warn!("Hub: could not queue close for {user_id}#{connection_id}; evicting");
+                    hub.disconnect(user_id, connection_id);
+                }
+            });
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.inner.shutting_down.load(Ordering::SeqCst)
+    }
+
+    pub fn stats(&self) -> HubStats {
+        HubStats {
+            connections:         self.connection_count(),
+            rooms:               self.inner.rooms.len(),
+            reaped_pong_timeout: self.inner.reaped_pong_timeout.load(Ordering::Relaxed),
+            reaped_idle:         self.inner.reaped_idle.load(Ordering::Relaxed),
+        }
+    }
+
+    fn connection_count(&self) -> usize {
+        self.inner.connections.iter().map(|conns| conns.len()).sum()
     }
 
-    /// Add user to a room and notify the room.
-    pub fn join_room(&self, room_id: Uuid, user_id: Uuid, username: &str, display_name: Option<&str>) {
-        let mut inner = self.inner.lock().unwrap();
-        inner.rooms.entry(room_id).or_default().insert(user_id);
+    /// Add user to a room and notify the room.  Returns `false`, changing nothing,
+    /// when none of the user's connections acts in the room's workspace.
+    pub fn join_room(&self, room_id: Uuid, workspace_id: Uuid, user_id: Uuid, username: &str, display_name: Option<&str>) -> bool {
+        let same_workspace = self.inner.connections.get(&user_id)
+            .is_some_and(|conns| conns.iter().any(|conn| conn.workspace_id == Some(workspace_id)));
+        if !same_workspace {
+            return false;
+        }
+        self.inner.rooms.entry(room_id)
+            .or_insert_with(|| RoomMembers { workspace_id, users: HashSet::new() })
+            .users
+            .insert(user_id);
+        self.inner.user_rooms.entry(user_id).or_default().insert(room_id);
 
         let joined_msg = ServerMessage::UserJoined {
             room_id,
@@ -64,51 +210,130 @@ impl Hub {
             },
         };
         // Notify everyone else in the room
-        Self::broadcast_inner(&inner, room_id, &joined_msg, Some(user_id));
+        self.broadcast_to_room(room_id, &joined_msg, Some(user_id));
 
         // Send online-users list to the joiner
-        let online: Vec<String> = inner.rooms.get(&room_id)
-            .map(|s| s.iter().map(|id| id.to_string()).collect())
+        let online: Vec<String> = self.inner.rooms.get(&room_id)
+            .map(|s| s.users.iter().map(|id| id.to_string()).collect())
             .unwrap_or_default();
-        if let Some(conn) = inner.connections.get(&user_id) {
-            let _ = conn.tx.try_send(ServerMessage::OnlineUsers { room_id, user_ids: online });
-        }
+        self.send_to_user(user_id, workspace_id, &ServerMessage::OnlineUsers { room_id, user_ids: online });
+        true
     }
 
     /// Remove user from a room and notify.
     pub fn leave_room(&self, room_id: Uuid, user_id: Uuid) {
-        let mut inner = self.inner.lock().unwrap();
-        if let Some(members) = inner.rooms.get_mut(&room_id) {
-            members.remove(&user_id);
+        self.remove_member(room_id, user_id);
+        if let Some(mut rooms) = self.inner.user_rooms.get_mut(&user_id) {
+            rooms.remove(&room_id);
         }
         let left_msg = ServerMessage::UserLeft { room_id, user_id };
-        Self::broadcast_inner(&inner, room_id, &left_msg, None);
+        self.broadcast_to_room(room_id, &left_msg, None);
+    }
+
+    /// Users currently subscribed to a room.
+    pub fn online_count(&self, room_id: Uuid) -> usize {
+        self.inner.rooms.get(&room_id).map_or(0, |members| members.users.len())
+    }
+
+    /// Drop every live subscriber from a room without notifying anyone; callers
+    /// broadcast the reason first.
+    pub fn close_room(&self, room_id: Uuid) {
+        let Some((_, members)) = self.inner.rooms.remove(&room_id) else { return };
+        for user_id in members.users {
+            if let Some(mut rooms) = self.inner.user_rooms.get_mut(&user_id) {
+                rooms.remove(&room_id);
+            }
+        }
     }
 
     /// Broadcast a message to every user in a room (optionally skipping one).
+    /// The message is serialized at most once per wire format and the same buffer is
+    /// shared by every recipient using that format.  Connections on a protocol version
+    /// older than the message, or acting in another workspace, are skipped.
     pub fn broadcast_to_room(&self, room_id: Uuid, msg: &ServerMessage, skip_user: Option<Uuid>) {
-        let inner = self.inner.lock().unwrap();
-        Self::broadcast_inner(&inner, room_id, msg, skip_user);
-    }
-
-    fn broadcast_inner(inner: &HubInner, room_id: Uuid, msg: &ServerMessage, skip: Option<Uuid>) {
-        if let Some(members) = inner.rooms.get(&room_id) {
-            for &uid in members {
-                if skip == Some(uid) { continue; }
-                if let Some(conn) = inner.connections.get(&uid) {
-                    if conn.tx.try_send(msg.clone()).is_err() {
-                        warn!("Hub: channel full or closed for {uid}");
-                    }
+        let Some(members) = self.inner.rooms.get(&room_id) else { return };
+        let min_version = msg.min_version();
+        let mut frames = EncodedFrames::new(msg);
+        for &uid in members.users.iter() {
+            if skip_user == Some(uid) { continue; }
+            let Some(conns) = self.inner.connections.get(&uid) else { continue };
+            for conn in conns.iter() {
+                if conn.version < min_version { continue; }
+                if conn.workspace_id != Some(members.workspace_id) { continue; }
+                let Some(frame) = frames.get(conn.format) else { continue };
+                if conn.tx.try_send(frame).is_err() {
+                    warn!("Hub: channel full or closed for {uid}#{}", conn.id);
                 }
             }
         }
     }
 
-    /// Send a message to a single user (for DMs / pong).
-    pub fn send_to_user(&self, user_id: Uuid, msg: &ServerMessage) {
-        let inner = self.inner.lock().unwrap();
-        if let Some(conn) = inner.connections.get(&user_id) {
-            let _ = conn.tx.try_send(msg.clone());
+    /// Send a message to every connection a user has open in `workspace_id` (DMs,
+    /// invitations, moderation notices).  Connections in their other workspaces don't see it.
+    pub fn send_to_user(&self, user_id: Uuid, workspace_id: Uuid, msg: &ServerMessage) {
+        let Some(conns) = self.inner.connections.get(&user_id) else { return };
+        let mut frames = EncodedFrames::new(msg);
+        for conn in conns.iter() {
+            if conn.version < msg.min_version() { continue; }
+            if conn.workspace_id != Some(workspace_id) { continue; }
+            if let Some(frame) = frames.get(conn.format) {
+                let _ = conn.tx.try_send(frame);
+            }
+        }
+    }
+
+    /// Send a message to one connection only, e.g. its `hello` or a reply to its frame.
+    pub fn send_to_connection(&self, user_id: Uuid, connection_id: ConnectionId, msg: &ServerMessage) {
+        let Some(conns) = self.inner.connections.get(&user_id) else { return };
+        let Some(conn) = conns.iter().find(|conn| conn.id == connection_id) else { return };
+        if conn.version < msg.min_version() { return; }
+        if let Some(frame) = EncodedFrames::new(msg).get(conn.format) {
+            let _ = conn.tx.try_send(frame);
+        }
+    }
+
+    /// Drop `user_id` from a room's member set, removing the room once it's empty.
+    fn remove_member(&self, room_id: Uuid, user_id: Uuid) {
+        self.inner.rooms.remove_if_mut(&room_id, |_, members| {
+            members.users.remove(&user_id);
+            members.users.is_empty()
+        });
+    }
+}
+
+/// How long a full channel gets to make room for a close frame before eviction.
+const CLOSE_SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A connection that couldn't take a close frame straight away.
+type StuckClose = (Uuid, ConnectionId, mpsc::Sender<Frame>);
+
+/// Try to queue `frame` on `conn`; returns the connection if its channel is full.
+fn queue_close(conn: &Connection, frame: &Frame) -> Option<StuckClose> {
+    match conn.tx.try_send(frame.clone()) {
+        Err(TrySendError::Full(_)) => Some((conn.user_id, conn.id, conn.tx.clone())),
+        _ => None,
+    }
+}
+
+/// Lazily encodes one message, caching the result for each wire format.
+struct EncodedFrames<'a> {
+    msg:    &'a ServerMessage,
+    frames: [Option<Frame>; WireFormat::ALL.len()],
+}
+
+impl<'a> EncodedFrames<'a> {
+    fn new(msg: &'a ServerMessage) -> Self {
+        EncodedFrames { msg, frames: Default::default() }
+    }
+
+    fn get(&mut self, format: WireFormat) -> Option<Frame> {
+        let slot = &mut self.frames[format.index()];
+        if slot.is_none() {
+            match format.encode(self.msg) {
+                Ok(frame) => *slot = Some(frame),
+                Err(e) => error!("Hub: serialize ({}): {e}", format.name()),
+            }
         }
+        slot.clone()
     }
 }
@@ -1,3 +1,5 @@
 pub mod hub;
 pub mod protocol;
-pub mod connection;
\ No newline at end of file
+pub mod connection;
+pub mod codec;
+pub mod sse;
\ No newline at end of file
@@ -3,22 +3,45 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::models::posting_policy::PostingPolicy;
+use crate::models::room::Room;
+
+/// Current protocol revision.  v1 is the original, un-announced frame set; v2 adds the
+/// `hello` handshake, and v2 clients must ignore frame types they don't recognise.
+pub const PROTOCOL_VERSION: u16 = 2;
+/// Oldest revision a client may still request.
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
 // Client → Server 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientMessage {
     JoinRoom   { room_id: Uuid },
-    LeaveRoom  { room_id: Uuid },
+    /// The owner names `successor_id` unless an admin can take over.
+    LeaveRoom  { room_id: Uuid, #[serde(default)] successor_id: Option<Uuid> },
     Message    { room_id: Uuid, content: String },
     Typing     { room_id: Uuid, is_typing: bool },
     Dm         { recipient_id: Uuid, content: String },
     Ping,
 }
 
+impl ClientMessage {
+    /// Every `type` tag a client may send; used to tell unknown types from malformed ones.
+    pub const TYPES: [&'static str; 6] = ["join_room", "leave_room", "message", "typing", "dm", "ping"];
+}
+
 //  Server → Client 
 #[derive(Debug, Serialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ServerMessage {
+    /// First frame on a v2+ connection.
+    Hello {
+        protocol_version:        u16,
+        server_version:          String,
+        capabilities:            Vec<String>,
+        limits:                  ProtocolLimits,
+        heartbeat_interval_secs: u64,
+    },
     Message {
         message_id: Uuid,
         room_id:    Uuid,
@@ -49,6 +72,85 @@ pub enum ServerMessage {
         room_id: Uuid,
         user_ids: Vec<String>,
     },
+    /// A member's room role changed.
+    RoleChanged {
+        room_id:    Uuid,
+        user_id:    Uuid,
+        role:       String,
+        changed_by: Uuid,
+    },
+    /// Ownership was transferred, or passed on when the owner left.
+    OwnerChanged {
+        room_id:           Uuid,
+        previous_owner_id: Uuid,
+        new_owner_id:      Uuid,
+    },
+    /// A moderator acted on a member: `kick`, `ban`, `unban`, `mute` or `unmute`.
+    ModerationAction {
+        room_id:      Uuid,
+        user_id:      Uuid,
+        action:       String,
+        moderator_id: Uuid,
+        reason:       Option<String>,
+        expires_at:   Option<DateTime<Utc>>,
+    },
+    /// Sent to the invitee.
+    RoomInvitation {
+        invitation_id: Uuid,
+        room_id:       Uuid,
+        room_name:     String,
+        inviter:       WsUser,
+    },
+    /// Sent to members who can approve it.
+    JoinRequested {
+        request_id: Uuid,
+        room_id:    Uuid,
+        user:       WsUser,
+        message:    Option<String>,
+    },
+    /// Sent to the requester.
+    JoinRequestReviewed {
+        request_id: Uuid,
+        room_id:    Uuid,
+        approved:   bool,
+    },
+    /// Room metadata changed; `changed` names the fields, `message_id` is the system
+    /// message recording it.
+    RoomUpdated {
+        room_id:    Uuid,
+        room:       Box<Room>,
+        changed:    Vec<String>,
+        updated_by: Uuid,
+        message_id: Uuid,
+    },
+    /// Sent to a member joining a room that has rules or a topic set.
+    RoomWelcome {
+        room_id: Uuid,
+        topic:   Option<String>,
+        rules:   Option<String>,
+    },
+    /// The room was archived (or scheduled for deletion, with `purge_after`); live
+    /// subscribers are dropped from it right after this frame.
+    RoomArchived {
+        room_id:     Uuid,
+        archived_by: Uuid,
+        purge_after: Option<DateTime<Utc>>,
+    },
+    PostingPolicyChanged {
+        room_id:    Uuid,
+        policy:     PostingPolicy,
+        changed_by: Uuid,
+    },
+    MessagePinned {
+        room_id:    Uuid,
+        message_id: Uuid,
+        pinned_by:  Uuid,
+    },
+    MessageUnpinned {
+        room_id:     Uuid,
+        message_id:  Uuid,
+        unpinned_by: Uuid,
+    },
     Error {
         code:    String,
         message: String,
@@ -56,6 +158,41 @@ pub enum ServerMessage {
     Pong,
 }
 
+impl ServerMessage {
+    /// Lowest protocol version that understands this frame.  Anything added after v1
+    /// belongs here so older clients never see a type they can't parse.
+    pub fn min_version(&self) -> u16 {
+        match self {
+            ServerMessage::Hello { .. }
+            | ServerMessage::RoleChanged { .. }
+            | ServerMessage::OwnerChanged { .. }
+            | ServerMessage::ModerationAction { .. }
+            | ServerMessage::RoomInvitation { .. }
+            | ServerMessage::JoinRequested { .. }
+            | ServerMessage::JoinRequestReviewed { .. }
+            | ServerMessage::RoomUpdated { .. }
+            | ServerMessage::RoomWelcome { .. }
+            | ServerMessage::RoomArchived { .. }
+            | ServerMessage::PostingPolicyChanged { .. }
+            | ServerMessage::MessagePinned { .. }
+            | ServerMessage::MessageUnpinned { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Limits announced in `hello` and enforced by the server.
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct ProtocolLimits {
+    /// Largest WebSocket message accepted, in bytes.
+    pub max_message_size:       usize,
+    /// Longest chat message / DM body, in bytes.
+    pub max_content_length:     usize,
+    /// Client frames allowed per rate-limit window.
+    pub rate_limit_messages:    u32,
+    pub rate_limit_window_secs: u64,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct WsUser {
     pub id:           Uuid,
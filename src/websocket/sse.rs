@@ -0,0 +1,228 @@
+/// Server-Sent Events transport.  An SSE session registers with the hub exactly like a
+/// WebSocket (JSON encoding), and a pump task copies hub frames into a bounded backlog.
+/// Streams read from that backlog, so a client that reconnects with `Last-Event-ID`
+/// inside the resume window gets everything it missed — including frames that arrived
+/// while it had no stream open.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use dashmap::DashMap;
+use tokio::sync::{mpsc, Notify};
+use tokio::time;
+use uuid::Uuid;
+use tracing::info;
+
+use super::codec::{Frame, WireFormat};
+use super::connection::ConnectionId;
+use super::hub::Hub;
+use super::protocol::PROTOCOL_VERSION;
+
+#[derive(Clone)]
+pub struct SseSessions {
+    inner: Arc<SseInner>,
+}
+
+struct SseInner {
+    hub:           Hub,
+    sessions:      DashMap<SessionKey, Arc<SseSession>>,
+    backlog:       usize,
+    resume_window: Duration,
+}
+
+/// Sessions are per user and active workspace: a stream opened after switching
+/// workspace gets its own session, and the old one expires with the resume window.
+type SessionKey = (Uuid, Option<Uuid>);
+
+/// One user's SSE session in one workspace, shared by every stream the user opens
+/// there within the resume window.
+pub struct SseSession {
+    /// The session's registration with the hub.
+    connection_id: ConnectionId,
+    /// Random per-session prefix so event ids from an expired session are never
+    /// mistaken for ids in a new one.
+    epoch:       u32,
+    backlog:     Mutex<Backlog>,
+    notify:      Notify,
+    attached:    AtomicUsize,
+    /// Unix millis when the last stream detached; 0 while any stream is attached.
+    detached_at: AtomicU64,
+    /// Set once the hub registration is gone; open streams end.
+    closed:      AtomicBool,
+}
+
+struct Backlog {
+    next_seq: u64,
+    events:   VecDeque<(u64, Arc<str>)>,
+}
+
+/// Where a new stream should start reading.
+pub enum Resume {
+    /// Events after `seq`.
+    After(u64),
+    /// The `Last-Event-ID` is unknown or too old; the client has missed events.
+    Gap,
+}
+
+impl SseSessions {
+    pub fn new(hub: Hub, backlog: usize, resume_window: Duration) -> Self {
+        SseSessions {
+            inner: Arc::new(SseInner { hub, sessions: DashMap::new(), backlog, resume_window }),
+        }
+    }
+
+    /// Get the user's live session in `workspace_id` or register a new one with the hub.
+    /// The flag is `true` when the session was just created.
+    pub fn attach(&self, user_id: Uuid, username: &str, workspace_id: Option<Uuid>) -> (Arc<SseSession>, bool) {
+        let mut created = false;
+        let session = self.inner.sessions.entry((user_id, workspace_id)).or_insert_with(|| {
+            created = true;
+            let (connection_id, rx) = self.inner.hub.register(
+                user_id, username.to_string(), workspace_id, WireFormat::Json, PROTOCOL_VERSION,
+            );
+            let session = Arc::new(SseSession {
+                connection_id,
+                epoch:       rand::random(),
+                backlog:     Mutex::new(Backlog { next_seq: 1, events: VecDeque::new() }),
+                notify:      Notify::new(),
+                attached:    AtomicUsize::new(0),
+                detached_at: AtomicU64::new(0),
+                closed:      AtomicBool::new(false),
+            });
+            tokio::spawn(self.clone().pump((user_id, workspace_id), session.clone(), rx));
+            session
+        });
+        // Still holding the map entry, so the pump can't expire the session under us.
+        session.attached.fetch_add(1, Ordering::SeqCst);
+        session.detached_at.store(0, Ordering::SeqCst);
+        (session.clone(), created)
+    }
+
+    /// The hub connection of the user's live session in `workspace_id`, if any.
+    pub fn connection_id(&self, user_id: Uuid, workspace_id: Option<Uuid>) -> Option<ConnectionId> {
+        self.inner.sessions.get(&(user_id, workspace_id)).map(|session| session.connection_id)
+    }
+
+    /// Copy hub frames into the session backlog until the hub drops us or the session
+    /// has had no stream attached for longer than the resume window.
+    async fn pump(self, key: SessionKey, session: Arc<SseSession>, mut rx: mpsc::Receiver<Frame>) {
+        let (user_id, _) = key;
+        let mut check = time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                frame = rx.recv() => match frame {
+                    Some(Frame::Text(text)) => session.push(text, self.inner.backlog),
+                    Some(Frame::Binary(_)) => {}
+                    Some(Frame::Close { .. }) => {
+                        self.inner.hub.disconnect(user_id, session.connection_id);
+                        break;
+                    }
+                    None => break,
+                },
+                _ = check.tick() => {
+                    let window = self.inner.resume_window.as_millis() as u64;
+                    let expired = self.inner.sessions.remove_if(&key, |_, s| {
+                        let detached_at = s.detached_at.load(Ordering::SeqCst);
+                        Arc::ptr_eq(s, &session)
+                            && s.is_detached()
+                            && detached_at != 0
+                            && now_millis().saturating_sub(detached_at) >= window
+                    });
+                    if expired.is_some() {
+                        self.inner.hub.disconnect(user_id, session.connection_id);
+                        break;
+                    }
+                }
+            }
+        }
+        self.inner.sessions.remove_if(&key, |_, s| Arc::ptr_eq(s, &session));
+        session.closed.store(true, Ordering::SeqCst);
+        session.notify.notify_waiters();
+        info!("SSE: session for {user_id} ended");
+    }
+}
+
+impl SseSession {
+    fn push(&self, data: Arc<str>, cap: usize) {
+        let mut backlog = self.backlog.lock().unwrap();
+        let seq = backlog.next_seq;
+        backlog.next_seq += 1;
+        backlog.events.push_back((seq, data));
+        while backlog.events.len() > cap {
+            backlog.events.pop_front();
+        }
+        drop(backlog);
+        self.notify.notify_waiters();
+    }
+
+    /// Resolve a client's `Last-Event-ID` against this session.  No header means
+    /// "start from now".
+    pub fn resume_from(&self, last_event_id: Option<&str>) -> Resume {
+        let backlog = self.backlog.lock().unwrap();
+        let tip = backlog.next_seq - 1;
+        let Some(id) = last_event_id else { return Resume::After(tip) };
+        let Some(seq) = self.parse_id(id) else { return Resume::Gap };
+        let oldest = backlog.events.front().map(|(s, _)| *s).unwrap_or(backlog.next_seq);
+        if seq > tip || seq + 1 < oldest {
+            Resume::Gap
+        } else {
+            Resume::After(seq)
+        }
+    }
+
+    /// Everything still buffered after `cursor`.
+    pub fn read_after(&self, cursor: u64) -> Vec<(u64, Arc<str>)> {
+        let backlog = self.backlog.lock().unwrap();
+        backlog.events.iter()
+            .filter(|(seq, _)| *seq > cursor)
+            .cloned()
+            .collect()
+    }
+
+    /// Resolves on the next push or when the session closes.  Create it before
+    /// `read_after` so nothing pushed in between is missed.
+    pub fn notified(&self) -> tokio::sync::futures::Notified<'_> {
+        self.notify.notified()
+    }
+
+    /// Oldest event still held, for clients that resume past a gap.
+    pub fn oldest_cursor(&self) -> u64 {
+        let backlog = self.backlog.lock().unwrap();
+        backlog.events.front().map(|(s, _)| s - 1).unwrap_or(backlog.next_seq - 1)
+    }
+
+    pub fn connection_id(&self) -> ConnectionId {
+        self.connection_id
+    }
+
+    pub fn event_id(&self, seq: u64) -> String {
+        format!("{:08x}-{seq}", self.epoch)
+    }
+
+    fn parse_id(&self, id: &str) -> Option<u64> {
+        let (epoch, seq) = id.split_once('-')?;
+        if u32::from_str_radix(epoch, 16).ok()? != self.epoch {
+            return None;
+        }
+        seq.parse().ok()
+    }
+
+    /// Called when a stream ends; starts the resume window once none are left.
+    pub fn detach(&self) {
+        if self.attached.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.detached_at.store(now_millis(), Ordering::SeqCst);
+        }
+    }
+
+    fn is_detached(&self) -> bool {
+        self.attached.load(Ordering::SeqCst) == 0
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}